@@ -0,0 +1,83 @@
+//! Агент telemt-admin: HTTP/JSON API для управления telemt на удалённом сервере.
+//!
+//! Агент запускается тем же бинарником (`telemt-admin agent <config>`) рядом с
+//! telemt и выполняет запросы бота: изменение `[access.users]`, чтение параметров
+//! ссылок и управление сервисом. Бот обращается к агенту вместо локальных файлов
+//! и systemd, если у экземпляра задана секция `[instances.agent]`. Каждый запрос
+//! авторизуется общим секретом в заголовке `Authorization: Bearer <token>`.
+
+#[path = "agent/client.rs"]
+mod client;
+#[path = "agent/server.rs"]
+mod server;
+
+pub use client::AgentClient;
+pub use server::run;
+
+use crate::telemt_cfg::UserPolicy;
+use serde::{Deserialize, Serialize};
+
+/// Версия протокола: префикс всех путей API.
+const API_PREFIX: &str = "/v1";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HealthResponse {
+    version: String,
+    backend: String,
+    service: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UpsertUserRequest {
+    name: String,
+    secret: String,
+    policy: UserPolicy,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RemoveUserRequest {
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SetPolicyRequest {
+    name: String,
+    policy: UserPolicy,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ApplyUsersRequest {
+    upserts: Vec<UpsertUserRequest>,
+    removals: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RollbackRequest {
+    id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RollbackPendingResponse {
+    id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChangedResponse {
+    changed: bool,
+}
+
+/// Итог проверки конфига: `error` задан, если конфиг не прошёл проверку.
+#[derive(Debug, Serialize, Deserialize)]
+struct ValidateResponse {
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CanReloadResponse {
+    can_reload: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
@@ -0,0 +1,259 @@
+//! Клиент API агента: используется ботом для удалённых экземпляров telemt.
+
+use super::{
+    ApplyUsersRequest, CanReloadResponse, ChangedResponse, ErrorResponse, HealthResponse,
+    RemoveUserRequest, RollbackPendingResponse, RollbackRequest, SetPolicyRequest, UpsertUserRequest,
+    ValidateResponse, API_PREFIX,
+};
+use crate::config::AgentClientConfig;
+use crate::service::{
+    JournalEntry, LogPriority, LogWindow, ServiceAction, ServiceBackend, ServiceResult, ServiceStatus,
+};
+use crate::telemt_cfg::{BackupInfo, TelemtLinkParams, UserPolicy};
+use anyhow::anyhow;
+use futures_util::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Подключение к агенту. Дёшево клонируется: HTTP-клиент общий.
+#[derive(Clone)]
+pub struct AgentClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+impl std::fmt::Debug for AgentClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentClient")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AgentClient {
+    pub fn new(config: &AgentClientConfig) -> Result<Self, anyhow::Error> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .map_err(|e| anyhow!("Не удалось создать HTTP-клиент агента: {}", e))?;
+        Ok(Self {
+            http,
+            base_url: config.url.trim_end_matches('/').to_string(),
+            token: config.token.clone(),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Проверяет доступность агента и токен; возвращает описание удалённого сервиса.
+    pub async fn health(&self) -> Result<String, anyhow::Error> {
+        let health: HealthResponse = self.get("/health", &[]).await?;
+        Ok(format!(
+            "{} v{} ({}: {})",
+            self.base_url, health.version, health.backend, health.service
+        ))
+    }
+
+    pub async fn read_link_params(&self) -> Result<TelemtLinkParams, anyhow::Error> {
+        self.get("/link-params", &[]).await
+    }
+
+    pub async fn read_users(&self) -> Result<BTreeMap<String, String>, anyhow::Error> {
+        self.get("/users", &[]).await
+    }
+
+    pub async fn read_policies(&self) -> Result<BTreeMap<String, UserPolicy>, anyhow::Error> {
+        self.get("/policies", &[]).await
+    }
+
+    pub async fn upsert_user(
+        &self,
+        username: &str,
+        secret: &str,
+        policy: &UserPolicy,
+    ) -> Result<(), anyhow::Error> {
+        let _: ChangedResponse = self
+            .post(
+                "/users/upsert",
+                &UpsertUserRequest {
+                    name: username.to_string(),
+                    secret: secret.to_string(),
+                    policy: *policy,
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_user(&self, username: &str) -> Result<bool, anyhow::Error> {
+        let response: ChangedResponse = self
+            .post(
+                "/users/remove",
+                &RemoveUserRequest {
+                    name: username.to_string(),
+                },
+            )
+            .await?;
+        Ok(response.changed)
+    }
+
+    pub async fn set_user_policy(&self, username: &str, policy: &UserPolicy) -> Result<bool, anyhow::Error> {
+        let response: ChangedResponse = self
+            .post(
+                "/users/policy",
+                &SetPolicyRequest {
+                    name: username.to_string(),
+                    policy: *policy,
+                },
+            )
+            .await?;
+        Ok(response.changed)
+    }
+
+    pub async fn apply_users_changes(
+        &self,
+        upserts: &[(String, String, UserPolicy)],
+        removals: &[String],
+    ) -> Result<(), anyhow::Error> {
+        let request = ApplyUsersRequest {
+            upserts: upserts
+                .iter()
+                .map(|(name, secret, policy)| UpsertUserRequest {
+                    name: name.clone(),
+                    secret: secret.clone(),
+                    policy: *policy,
+                })
+                .collect(),
+            removals: removals.to_vec(),
+        };
+        let _: ChangedResponse = self.post("/users/apply", &request).await?;
+        Ok(())
+    }
+
+    pub async fn validate_config(&self) -> Result<(), anyhow::Error> {
+        let response: ValidateResponse = self.get("/config/validate", &[]).await?;
+        match response.error {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, anyhow::Error> {
+        self.get("/backups", &[]).await
+    }
+
+    pub async fn rollback(&self, id: &str) -> Result<(), anyhow::Error> {
+        let _: ChangedResponse = self
+            .post("/backups/rollback", &RollbackRequest { id: id.to_string() })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn rollback_pending(&self) -> Result<Option<String>, anyhow::Error> {
+        let response: RollbackPendingResponse = self.post("/backups/rollback-pending", &()).await?;
+        Ok(response.id)
+    }
+
+    pub async fn mark_applied(&self) -> Result<(), anyhow::Error> {
+        let _: ChangedResponse = self.post("/backups/mark-applied", &()).await?;
+        Ok(())
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T, anyhow::Error> {
+        let request = self
+            .http
+            .get(format!("{}{}{}", self.base_url, API_PREFIX, path))
+            .query(query);
+        self.send(request).await
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T, anyhow::Error> {
+        let request = self
+            .http
+            .post(format!("{}{}{}", self.base_url, API_PREFIX, path))
+            .json(body);
+        self.send(request).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, anyhow::Error> {
+        let response = request
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Агент {} недоступен: {}", self.base_url, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response
+                .json::<ErrorResponse>()
+                .await
+                .map(|error| error.error)
+                .unwrap_or_else(|_| status.to_string());
+            return Err(anyhow!("Агент {}: {}", self.base_url, message));
+        }
+        response
+            .json::<T>()
+            .await
+            .map_err(|e| anyhow!("Некорректный ответ агента {}: {}", self.base_url, e))
+    }
+}
+
+impl ServiceBackend for AgentClient {
+    fn kind(&self) -> &'static str {
+        "agent"
+    }
+
+    fn target(&self) -> &str {
+        &self.base_url
+    }
+
+    fn run(&self, action: ServiceAction) -> BoxFuture<'_, ServiceResult> {
+        Box::pin(async move {
+            let path = format!("/service/{}", action.as_str());
+            match self.post::<_, ServiceResult>(&path, &()).await {
+                Ok(result) => result,
+                Err(error) => ServiceResult {
+                    success: false,
+                    stdout: String::new(),
+                    stderr: error.to_string(),
+                },
+            }
+        })
+    }
+
+    fn status(&self) -> BoxFuture<'_, Result<ServiceStatus, anyhow::Error>> {
+        Box::pin(self.get("/service/status", &[]))
+    }
+
+    fn can_reload(&self) -> BoxFuture<'_, bool> {
+        Box::pin(async move {
+            match self.get::<CanReloadResponse>("/service/can-reload", &[]).await {
+                Ok(response) => response.can_reload,
+                Err(error) => {
+                    tracing::warn!(agent = %self.base_url, error = %error, "Не удалось узнать у агента, доступен ли reload");
+                    false
+                }
+            }
+        })
+    }
+
+    fn journal(
+        &self,
+        lines: usize,
+        priority: LogPriority,
+        window: LogWindow,
+    ) -> BoxFuture<'_, Result<Vec<JournalEntry>, anyhow::Error>> {
+        Box::pin(async move {
+            let query = [
+                ("lines", lines.to_string()),
+                ("priority", priority.as_arg().to_string()),
+                ("window", window.as_arg().to_string()),
+            ];
+            self.get::<Vec<JournalEntry>>("/service/journal", &query).await
+        })
+    }
+}
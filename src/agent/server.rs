@@ -0,0 +1,378 @@
+//! HTTP-сервер агента: принимает запросы бота и выполняет их над локальным telemt.
+
+use super::{
+    ApplyUsersRequest, CanReloadResponse, ChangedResponse, HealthResponse,
+    RemoveUserRequest, RollbackPendingResponse, RollbackRequest, SetPolicyRequest, UpsertUserRequest,
+    ValidateResponse, API_PREFIX,
+};
+use crate::config::AgentConfig;
+use crate::http::{authorized, error_response, json_response, parse_body, BadRequest};
+use crate::service::{LogPriority, LogWindow, ServiceController};
+use crate::telemt_cfg::{ConfigWatcher, TelemtConfig, UserPolicy};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Максимальный размер тела запроса: полная синхронизация пользователей укладывается с запасом.
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Лимит строк журнала за один запрос.
+const MAX_JOURNAL_LINES: usize = 1_000;
+
+struct AgentState {
+    token: String,
+    service_name: String,
+    telemt_cfg: TelemtConfig,
+    service: ServiceController,
+}
+
+/// Запускает HTTP API агента и обслуживает запросы до завершения процесса.
+pub async fn run(config: AgentConfig) -> Result<(), anyhow::Error> {
+    let listener = TcpListener::bind(config.listen)
+        .await
+        .map_err(|e| anyhow::anyhow!("Не удалось открыть {}: {}", config.listen, e))?;
+    serve(config, listener).await
+}
+
+/// Обслуживает запросы на уже открытом `listener`.
+async fn serve(config: AgentConfig, listener: TcpListener) -> Result<(), anyhow::Error> {
+    let instance = config.instance();
+    let state = Arc::new(AgentState {
+        token: config.token().to_string(),
+        service_name: instance.service_name.clone(),
+        telemt_cfg: TelemtConfig::new(&instance.telemt_config_path)
+            .with_backups(config.backups.instance_dir(&instance.name), config.backups.keep)
+            .with_check_command(instance.telemt_check_command.clone()),
+        service: ServiceController::from_config(&instance),
+    });
+    spawn_config_watcher(state.clone());
+    tracing::info!(listen = %listener.local_addr()?, "telemt-admin agent started");
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                tracing::warn!(error = %error, "Не удалось принять подключение к агенту");
+                continue;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let state = state.clone();
+                async move { Ok::<_, Infallible>(handle(&state, peer, request).await) }
+            });
+            if let Err(error) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!(peer = %peer, error = %error, "Agent connection closed with error");
+            }
+        });
+    }
+}
+
+/// Сбрасывает кэш параметров ссылок при ручной правке конфига на сервере агента.
+fn spawn_config_watcher(state: Arc<AgentState>) {
+    let path = state.telemt_cfg.path().to_path_buf();
+    let mut watcher = match ConfigWatcher::new(std::slice::from_ref(&path)) {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            tracing::warn!(error = %error, "Наблюдение за конфигом telemt не запущено");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while watcher.changed().await.is_some() {
+            state.telemt_cfg.invalidate_cache();
+            let external = blocking(&state, |config| {
+                let content = std::fs::read_to_string(config.path())?;
+                Ok(!config.is_own_write(&content))
+            })
+            .await;
+            if let Ok(true) = external {
+                tracing::info!(path = %path.display(), "telemt config edited outside the agent");
+            }
+        }
+    });
+}
+
+async fn handle(state: &Arc<AgentState>, peer: SocketAddr, request: Request<Incoming>) -> Response<Full<Bytes>> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if !authorized(&request, &state.token) {
+        tracing::warn!(peer = %peer, method = %method, path = %path, "Agent request with invalid token");
+        return error_response(StatusCode::UNAUTHORIZED, "Неверный токен агента");
+    }
+    let query = request.uri().query().unwrap_or_default().to_string();
+    let body = match Limited::new(request.into_body(), MAX_BODY_BYTES).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(error) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Не удалось прочитать тело запроса: {}", error),
+            );
+        }
+    };
+
+    tracing::debug!(peer = %peer, method = %method, path = %path, "Agent request");
+    match route(state, &method, &path, &query, &body).await {
+        Ok(response) => response,
+        Err(error) => {
+            let status = if error.downcast_ref::<BadRequest>().is_some() {
+                StatusCode::BAD_REQUEST
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            tracing::warn!(
+                peer = %peer,
+                method = %method,
+                path = %path,
+                error = %error,
+                "Agent request failed"
+            );
+            error_response(status, &error.to_string())
+        }
+    }
+}
+
+async fn route(
+    state: &Arc<AgentState>,
+    method: &Method,
+    path: &str,
+    query: &str,
+    body: &[u8],
+) -> Result<Response<Full<Bytes>>, anyhow::Error> {
+    let Some(path) = path.strip_prefix(API_PREFIX) else {
+        return Ok(error_response(StatusCode::NOT_FOUND, "Неизвестный метод API"));
+    };
+    match (method, path) {
+        (&Method::GET, "/health") => json_response(&HealthResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            backend: state.service.backend_kind().to_string(),
+            service: state.service_name.clone(),
+        }),
+        (&Method::GET, "/link-params") => {
+            json_response(&blocking(state, |config| config.read_link_params()).await?)
+        }
+        (&Method::GET, "/users") => json_response(&blocking(state, |config| config.read_users()).await?),
+        (&Method::GET, "/policies") => {
+            json_response(&blocking(state, |config| config.read_policies()).await?)
+        }
+        (&Method::POST, "/users/upsert") => {
+            let request: UpsertUserRequest = parse_body(body)?;
+            blocking(state, move |config| {
+                config.upsert_user(&request.name, &request.secret, &request.policy)
+            })
+            .await?;
+            json_response(&ChangedResponse { changed: true })
+        }
+        (&Method::POST, "/users/remove") => {
+            let request: RemoveUserRequest = parse_body(body)?;
+            let changed = blocking(state, move |config| config.remove_user(&request.name)).await?;
+            json_response(&ChangedResponse { changed })
+        }
+        (&Method::POST, "/users/policy") => {
+            let request: SetPolicyRequest = parse_body(body)?;
+            let changed = blocking(state, move |config| {
+                config.set_user_policy(&request.name, &request.policy)
+            })
+            .await?;
+            json_response(&ChangedResponse { changed })
+        }
+        (&Method::POST, "/users/apply") => {
+            let request: ApplyUsersRequest = parse_body(body)?;
+            let upserts: Vec<(String, String, UserPolicy)> = request
+                .upserts
+                .into_iter()
+                .map(|user| (user.name, user.secret, user.policy))
+                .collect();
+            blocking(state, move |config| {
+                config.apply_users_changes(&upserts, &request.removals)
+            })
+            .await?;
+            json_response(&ChangedResponse { changed: true })
+        }
+        (&Method::GET, "/config/validate") => json_response(&ValidateResponse {
+            error: blocking(state, |config| Ok(config.validate().err()))
+                .await?
+                .map(|error| error.to_string()),
+        }),
+        (&Method::GET, "/backups") => json_response(&blocking(state, |config| config.list_backups()).await?),
+        (&Method::POST, "/backups/rollback") => {
+            let request: RollbackRequest = parse_body(body)?;
+            blocking(state, move |config| config.rollback(&request.id)).await?;
+            json_response(&ChangedResponse { changed: true })
+        }
+        (&Method::POST, "/backups/rollback-pending") => json_response(&RollbackPendingResponse {
+            id: blocking(state, |config| config.rollback_pending()).await?,
+        }),
+        (&Method::POST, "/backups/mark-applied") => {
+            blocking(state, |config| {
+                config.mark_applied();
+                Ok(())
+            })
+            .await?;
+            json_response(&ChangedResponse { changed: true })
+        }
+        (&Method::POST, "/service/start") => json_response(&state.service.start().await),
+        (&Method::POST, "/service/stop") => json_response(&state.service.stop().await),
+        (&Method::POST, "/service/restart") => json_response(&state.service.restart().await),
+        (&Method::POST, "/service/reload") => json_response(&state.service.reload().await),
+        (&Method::GET, "/service/status") => json_response(&state.service.status().await?),
+        (&Method::GET, "/service/can-reload") => json_response(&CanReloadResponse {
+            can_reload: state.service.can_reload().await,
+        }),
+        (&Method::GET, "/service/journal") => {
+            let (lines, priority, window) = parse_journal_query(query)?;
+            json_response(&state.service.journal(lines, priority, window).await?)
+        }
+        _ => Ok(error_response(StatusCode::NOT_FOUND, "Неизвестный метод API")),
+    }
+}
+
+/// Выполняет операцию с конфигом telemt в пуле блокирующих задач: запись файла
+/// и `telemt_check_command` могут занять до десятков секунд.
+async fn blocking<T, F>(state: &Arc<AgentState>, operation: F) -> Result<T, anyhow::Error>
+where
+    T: Send + 'static,
+    F: FnOnce(&TelemtConfig) -> Result<T, anyhow::Error> + Send + 'static,
+{
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || operation(&state.telemt_cfg))
+        .await
+        .map_err(|e| anyhow::anyhow!("Операция с конфигом telemt прервана: {}", e))?
+}
+
+/// Разбирает `lines=N&priority=all|warn|err&window=15m|1h|24h|all`.
+fn parse_journal_query(query: &str) -> Result<(usize, LogPriority, LogWindow), anyhow::Error> {
+    let mut lines = 50;
+    let mut priority = LogPriority::All;
+    let mut window = LogWindow::All;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "lines" => {
+                lines = value
+                    .parse::<usize>()
+                    .map_err(|_| BadRequest("Некорректный lines".to_string()))?
+                    .min(MAX_JOURNAL_LINES);
+            }
+            "priority" => {
+                priority = LogPriority::from_arg(value)
+                    .ok_or_else(|| BadRequest("Некорректный priority".to_string()))?;
+            }
+            "window" => {
+                window = LogWindow::from_arg(value)
+                    .ok_or_else(|| BadRequest("Некорректный window".to_string()))?;
+            }
+            _ => {}
+        }
+    }
+    Ok((lines, priority, window))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentClient, ErrorResponse};
+    use crate::config::{AgentClientConfig, BackupConfig, ServiceBackendConfig};
+    use std::collections::BTreeMap;
+
+    const TOKEN: &str = "agent-test-token-0123456789";
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+    const TELEMT_CONFIG: &str = r#"
+[server]
+port = 443
+
+[[server.listeners]]
+ip = "0.0.0.0"
+announce = "proxy.example.com"
+
+[censorship]
+tls_domain = "www.google.com"
+
+[access.users]
+"#;
+
+    /// Поднимает агента на свободном порту над конфигом telemt в каталоге `dir`.
+    async fn start_agent(dir: &std::path::Path) -> String {
+        std::fs::create_dir_all(dir).unwrap();
+        let telemt_config_path = dir.join("telemt.toml");
+        std::fs::write(&telemt_config_path, TELEMT_CONFIG).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen = listener.local_addr().unwrap();
+        let config = AgentConfig {
+            listen,
+            token: Some(TOKEN.to_string()),
+            telemt_config_path,
+            service_name: "telemt".to_string(),
+            service: ServiceBackendConfig::Noop,
+            telemt_check_command: Vec::new(),
+            backups: BackupConfig {
+                dir: dir.join("backups"),
+                keep: 0,
+                auto_rollback: false,
+            },
+        };
+        tokio::spawn(serve(config, listener));
+        format!("http://{}", listen)
+    }
+
+    fn client(url: &str, token: &str) -> AgentClient {
+        AgentClient::new(&AgentClientConfig {
+            url: url.to_string(),
+            token: token.to_string(),
+            timeout_secs: 10,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_agent_client_end_to_end() {
+        let dir = std::env::temp_dir().join(format!("telemt-admin-agent-test-{}", std::process::id()));
+        let url = start_agent(&dir).await;
+        let agent = client(&url, TOKEN);
+
+        agent.upsert_user("alice", SECRET, &UserPolicy::default()).await.unwrap();
+        assert_eq!(
+            agent.read_users().await.unwrap(),
+            BTreeMap::from([("alice".to_string(), SECRET.to_string())])
+        );
+        assert!(agent.remove_user("alice").await.unwrap());
+        assert!(!agent.remove_user("alice").await.unwrap());
+        assert!(agent.read_users().await.unwrap().is_empty());
+
+        assert!(client(&url, "wrong-token-0123456789").read_users().await.is_err());
+        let http = reqwest::Client::new();
+        let unauthorized = http
+            .get(format!("{}{}/users", url, API_PREFIX))
+            .bearer_auth("wrong-token-0123456789")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unauthorized.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let missing = http.get(format!("{}{}/users", url, API_PREFIX)).send().await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let bad_json = http
+            .post(format!("{}{}/users/upsert", url, API_PREFIX))
+            .bearer_auth(TOKEN)
+            .body("{\"name\": ")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(bad_json.status(), reqwest::StatusCode::BAD_REQUEST);
+        let error: ErrorResponse = bad_json.json().await.unwrap();
+        assert!(error.error.starts_with("Некорректный JSON"), "{}", error.error);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
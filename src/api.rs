@@ -0,0 +1,537 @@
+//! Локальный HTTP API администрирования для внутренних инструментов, которым
+//! нужен доступ без Telegram-аккаунта.
+//!
+//! Сервер включается секцией `[api]`, слушает loopback-адрес или unix-сокет и
+//! принимает только запросы с заголовком `Authorization: Bearer <token>`. Операции
+//! выполняются теми же функциями, что и команды бота: записываются в журнал
+//! действий как система, а перезапуск telemt идёт через общую очередь бота.
+
+use crate::bot::handlers::{
+    apply_config_sync, approve_request_and_build_link, approve_user_direct_and_build_link,
+    build_bot_start_link, build_user_links, create_invite_token, hard_ban_user, reject_request,
+    revoke_invite_token, BotState,
+};
+use crate::config::ApiConfig;
+use crate::db::{AuditAction, RequestStatus, UserLimits};
+use crate::http::{authorized, error_response, json_response, parse_body, BadRequest};
+use crate::json::{links_json, request_json, sync_report_json, token_json, user_json};
+use crate::sync::SyncReport;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+
+/// Версия API: префикс всех путей.
+const API_PREFIX: &str = "/v1";
+
+/// Максимальный размер тела запроса.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Сколько ожидающих заявок отдавать за один запрос.
+const MAX_PENDING_REQUESTS: i64 = 1_000;
+
+struct ApiState {
+    bot: Bot,
+    state: BotState,
+    token: String,
+}
+
+/// Пользователь, заявка или токен не найдены — отвечаем 404.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct NotFound(String);
+
+#[derive(Deserialize)]
+struct CreateUserRequest {
+    tg_user_id: i64,
+    access_days: Option<i64>,
+    limits: Option<UserLimits>,
+    /// Отправить пользователю ссылку в Telegram
+    #[serde(default)]
+    notify: bool,
+}
+
+#[derive(Deserialize)]
+struct CreateTokenRequest {
+    days: Option<i64>,
+    #[serde(default)]
+    auto_approve: bool,
+    max_uses: Option<i64>,
+    access_days: Option<i64>,
+    #[serde(default)]
+    limits: UserLimits,
+}
+
+/// Открывает адрес из `[api]` и обслуживает запросы в фоне до завершения бота.
+pub async fn spawn(bot: Bot, state: BotState, config: &ApiConfig) -> Result<(), anyhow::Error> {
+    let api = Arc::new(ApiState {
+        bot,
+        state,
+        token: config.token().to_string(),
+    });
+    if let Some(path) = &config.socket {
+        return spawn_unix(api, path);
+    }
+    let Some(listen) = config.listen else {
+        return Ok(());
+    };
+    let listener = TcpListener::bind(listen)
+        .await
+        .map_err(|e| anyhow::anyhow!("Не удалось открыть {} для HTTP API: {}", listen, e))?;
+    tracing::info!(listen = %listen, "HTTP admin API started");
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => serve_connection(api.clone(), stream, peer.to_string()),
+                Err(error) => {
+                    tracing::warn!(error = %error, "Не удалось принять подключение к HTTP API");
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn spawn_unix(api: Arc<ApiState>, path: &std::path::Path) -> Result<(), anyhow::Error> {
+    let listener = bind_unix_socket(path)?;
+    tracing::info!(socket = %path.display(), "HTTP admin API started");
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => serve_connection(api.clone(), stream, "unix".to_string()),
+                Err(error) => {
+                    tracing::warn!(error = %error, "Не удалось принять подключение к HTTP API");
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Открывает unix-сокет `path` с правами 0600. Сокет от прошлого запуска
+/// заменяется, любой другой файл по этому пути — ошибка конфига.
+#[cfg(unix)]
+fn bind_unix_socket(path: &std::path::Path) -> Result<tokio::net::UnixListener, anyhow::Error> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path).map_err(|e| {
+                anyhow::anyhow!("Не удалось удалить старый сокет {}: {}", path.display(), e)
+            })?;
+        }
+        Ok(_) => {
+            return Err(anyhow::anyhow!(
+                "api.socket: по пути {} уже есть файл, и это не сокет",
+                path.display()
+            ));
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+        Err(error) => {
+            return Err(anyhow::anyhow!("Не удалось проверить {}: {}", path.display(), error));
+        }
+    }
+    let staging = path.with_file_name(format!(
+        ".{}.{}",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id()
+    ));
+    let listener = bind_private_socket(&staging, path);
+    if let Err(error) = std::fs::remove_dir_all(&staging)
+        && error.kind() != std::io::ErrorKind::NotFound
+    {
+        tracing::warn!(dir = %staging.display(), error = %error, "Не удалось удалить временный каталог сокета");
+    }
+    listener
+}
+
+/// Создаёт сокет в каталоге `staging` с правами 0700, выставляет ему 0600 и
+/// только потом ссылкой переносит в `path`: до chmod к сокету нельзя
+/// подключиться даже при мягком umask, а появившийся за это время файл
+/// по пути `path` не перезаписывается.
+#[cfg(unix)]
+fn bind_private_socket(
+    staging: &std::path::Path,
+    path: &std::path::Path,
+) -> Result<tokio::net::UnixListener, anyhow::Error> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(staging)
+        .map_err(|e| anyhow::anyhow!("Не удалось создать каталог {}: {}", staging.display(), e))?;
+    let staged = staging.join("api.sock");
+    let listener = tokio::net::UnixListener::bind(&staged)
+        .map_err(|e| anyhow::anyhow!("Не удалось открыть сокет {} для HTTP API: {}", path.display(), e))?;
+    std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| anyhow::anyhow!("Не удалось выставить права на {}: {}", path.display(), e))?;
+    std::fs::hard_link(&staged, path)
+        .map_err(|e| anyhow::anyhow!("Не удалось создать сокет {}: {}", path.display(), e))?;
+    Ok(listener)
+}
+
+#[cfg(not(unix))]
+fn spawn_unix(_api: Arc<ApiState>, path: &std::path::Path) -> Result<(), anyhow::Error> {
+    Err(anyhow::anyhow!(
+        "api.socket ({}) поддерживается только в Unix, используйте api.listen",
+        path.display()
+    ))
+}
+
+fn serve_connection<S>(api: Arc<ApiState>, stream: S, peer: String)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let service_peer = peer.clone();
+        let service = service_fn(move |request| {
+            let api = api.clone();
+            let peer = service_peer.clone();
+            async move { Ok::<_, Infallible>(handle(&api, &peer, request).await) }
+        });
+        if let Err(error) = http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await
+        {
+            tracing::debug!(peer = %peer, error = %error, "API connection closed with error");
+        }
+    });
+}
+
+async fn handle(api: &ApiState, peer: &str, request: Request<Incoming>) -> Response<Full<Bytes>> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if !authorized(&request, &api.token) {
+        tracing::warn!(peer = %peer, method = %method, path = %path, "API request with invalid token");
+        return error_response(StatusCode::UNAUTHORIZED, "Неверный токен API");
+    }
+    let body = match Limited::new(request.into_body(), MAX_BODY_BYTES).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(error) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Не удалось прочитать тело запроса: {}", error),
+            );
+        }
+    };
+
+    tracing::debug!(peer = %peer, method = %method, path = %path, "API request");
+    match route(api, &method, &path, &body).await {
+        Ok(response) => response,
+        Err(error) => {
+            let status = if error.downcast_ref::<BadRequest>().is_some() {
+                StatusCode::BAD_REQUEST
+            } else if error.downcast_ref::<NotFound>().is_some() {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            if status == StatusCode::INTERNAL_SERVER_ERROR {
+                tracing::warn!(
+                    peer = %peer,
+                    method = %method,
+                    path = %path,
+                    error = %error,
+                    "API request failed"
+                );
+            }
+            error_response(status, &error.to_string())
+        }
+    }
+}
+
+async fn route(
+    api: &ApiState,
+    method: &Method,
+    path: &str,
+    body: &[u8],
+) -> Result<Response<Full<Bytes>>, anyhow::Error> {
+    let Some(path) = path.strip_prefix(API_PREFIX) else {
+        return Ok(error_response(StatusCode::NOT_FOUND, "Неизвестный метод API"));
+    };
+    let state = &api.state;
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        (&Method::GET, ["health"]) => json_response(&json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "bot_username": state.bot_username,
+            "instances": state.instances.names(),
+        })),
+        (&Method::GET, ["users"]) => {
+            let users = state.db.list_approved_users().await?;
+            let assignments = state.db.list_user_instances().await?;
+            let data: Vec<Value> = users
+                .iter()
+                .map(|user| {
+                    let instances = assignments.get(&user.tg_user_id).cloned().unwrap_or_default();
+                    user_json(user, &instances)
+                })
+                .collect();
+            json_response(&json!({ "users": data }))
+        }
+        (&Method::POST, ["users"]) => create_user(api, parse_body(body)?).await,
+        (&Method::GET, ["users", id]) => {
+            let tg_user_id = parse_id(id)?;
+            let user = state
+                .db
+                .get_request_by_tg_user(tg_user_id)
+                .await?
+                .filter(|user| user.status == RequestStatus::Approved)
+                .ok_or_else(|| NotFound(format!("Пользователь {} не найден", tg_user_id)))?;
+            let instances = state.db.get_user_instances(tg_user_id).await?;
+            let mut data = user_json(&user, &instances);
+            if let Some(secret) = user.secret.as_deref() {
+                let links = build_user_links(state, tg_user_id, secret).await?;
+                data["links"] = links_json(&links);
+            }
+            json_response(&data)
+        }
+        (&Method::DELETE, ["users", id]) => {
+            let tg_user_id = parse_id(id)?;
+            if !hard_ban_user(state, tg_user_id, None).await? {
+                return Err(NotFound(format!("Пользователь {} не найден", tg_user_id)).into());
+            }
+            json_response(&json!({ "tg_user_id": tg_user_id, "removed": true }))
+        }
+        (&Method::GET, ["requests"]) => {
+            let requests = state.db.list_pending_requests(MAX_PENDING_REQUESTS).await?;
+            let data: Vec<Value> = requests.iter().map(request_json).collect();
+            json_response(&json!({ "requests": data }))
+        }
+        (&Method::POST, ["requests", id, "approve"]) => {
+            let request_id = parse_id(id)?;
+            let (request, links) = approve_request_and_build_link(state, request_id, None)
+                .await?
+                .ok_or_else(|| NotFound("Заявка не найдена или уже обработана".to_string()))?;
+            let notified = notify_user(
+                api,
+                request.tg_user_id,
+                format!("Ваша ссылка на прокси:\n\n{}", links),
+            )
+            .await;
+            tracing::info!(request_id = request_id, "Request approved via HTTP API");
+            json_response(&json!({
+                "id": request_id,
+                "tg_user_id": request.tg_user_id,
+                "instances": links_json(&links),
+                "notified": notified,
+            }))
+        }
+        (&Method::POST, ["requests", id, "reject"]) => {
+            let request_id = parse_id(id)?;
+            let request = reject_request(state, request_id, None)
+                .await?
+                .ok_or_else(|| NotFound("Заявка не найдена или уже обработана".to_string()))?;
+            let notified = notify_user(
+                api,
+                request.tg_user_id,
+                "Ваша заявка на регистрацию отклонена администратором.".to_string(),
+            )
+            .await;
+            tracing::info!(request_id = request_id, "Request rejected via HTTP API");
+            json_response(&json!({
+                "id": request_id,
+                "tg_user_id": request.tg_user_id,
+                "notified": notified,
+            }))
+        }
+        (&Method::GET, ["tokens"]) => {
+            let total = state.db.count_invite_tokens().await?;
+            let tokens = state.db.list_invite_tokens_page(total.max(1), 0).await?;
+            let now = chrono::Utc::now().timestamp();
+            let data: Vec<Value> = tokens.iter().map(|token| token_json(token, now)).collect();
+            json_response(&json!({ "tokens": data }))
+        }
+        (&Method::POST, ["tokens"]) => {
+            let request: CreateTokenRequest = parse_body(body)?;
+            let token = create_invite_token(
+                state,
+                request.days,
+                request.auto_approve,
+                request.max_uses,
+                None,
+                request.access_days,
+                request.limits,
+            )
+            .await?
+            .map_err(BadRequest)?;
+            let mut data = token_json(&token, chrono::Utc::now().timestamp());
+            if let Some(bot_username) = state.bot_username.as_deref() {
+                data["start_link"] = json!(build_bot_start_link(bot_username, &token.token));
+            }
+            json_response(&data)
+        }
+        (&Method::DELETE, ["tokens", token]) => {
+            if !revoke_invite_token(state, token, None).await? {
+                return Err(NotFound("Токен не найден или уже отозван".to_string()).into());
+            }
+            json_response(&json!({ "token": token, "revoked": true }))
+        }
+        (&Method::GET, ["service"]) => {
+            let mut data = Vec::new();
+            for instance in state.instances.all() {
+                let (status, error) = match instance.service.status().await {
+                    Ok(status) => (Some(status), None),
+                    Err(error) => (None, Some(error.to_string())),
+                };
+                data.push(json!({
+                    "instance": instance.name,
+                    "service": instance.service_name,
+                    "backend": instance.service.backend_kind(),
+                    "status": status,
+                    "error": error,
+                }));
+            }
+            json_response(&json!({ "instances": data }))
+        }
+        (&Method::GET, ["sync"]) => {
+            let reports = crate::sync::build_reports(&state.db, &state.instances).await?;
+            let data: Vec<Value> = reports.iter().map(sync_report_json).collect();
+            json_response(&json!({
+                "reports": data,
+                "has_fixes": reports.iter().any(SyncReport::has_fixes),
+            }))
+        }
+        (&Method::POST, ["sync"]) => {
+            let reports = crate::sync::build_reports(&state.db, &state.instances).await?;
+            let applied = reports.iter().any(SyncReport::has_fixes);
+            let summary = if applied {
+                Some(apply_config_sync(state, None).await?)
+            } else {
+                None
+            };
+            let data: Vec<Value> = reports.iter().map(sync_report_json).collect();
+            json_response(&json!({ "reports": data, "applied": applied, "summary": summary }))
+        }
+        _ => Ok(error_response(StatusCode::NOT_FOUND, "Неизвестный метод API")),
+    }
+}
+
+/// Выдаёт доступ без заявки, как `/create`; при `notify` отправляет ссылку пользователю.
+async fn create_user(
+    api: &ApiState,
+    request: CreateUserRequest,
+) -> Result<Response<Full<Bytes>>, anyhow::Error> {
+    let state = &api.state;
+    if request.access_days.is_some_and(|days| days < 1) {
+        return Err(BadRequest("access_days должен быть не меньше 1".to_string()).into());
+    }
+    // Как /create: без новых лимитов сохраняются прежние лимиты пользователя.
+    let existing = state.db.get_request_by_tg_user(request.tg_user_id).await?;
+    let limits = request
+        .limits
+        .or(existing.as_ref().map(|existing| existing.limits))
+        .unwrap_or_default();
+    let links = approve_user_direct_and_build_link(
+        state,
+        request.tg_user_id,
+        existing.as_ref().and_then(|existing| existing.tg_username.as_deref()),
+        existing.as_ref().and_then(|existing| existing.tg_display_name.as_deref()),
+        None,
+        AuditAction::UserCreate,
+        request.access_days,
+        limits,
+    )
+    .await?;
+    let notified = request.notify
+        && notify_user(
+            api,
+            request.tg_user_id,
+            format!("Ваша ссылка на прокси:\n\n{}", links),
+        )
+        .await;
+    tracing::info!(tg_user_id = request.tg_user_id, "User created via HTTP API");
+    json_response(&json!({
+        "tg_user_id": request.tg_user_id,
+        "instances": links_json(&links),
+        "notified": notified,
+    }))
+}
+
+/// Сообщение пользователю в Telegram; ошибка отправки не отменяет уже выполненное действие.
+async fn notify_user(api: &ApiState, tg_user_id: i64, text: String) -> bool {
+    match api.bot.send_message(ChatId(tg_user_id), text).await {
+        Ok(_) => true,
+        Err(error) => {
+            tracing::warn!(
+                tg_user_id = tg_user_id,
+                error = %error,
+                "Не удалось отправить пользователю сообщение из HTTP API"
+            );
+            false
+        }
+    }
+}
+
+fn parse_id(value: &str) -> Result<i64, anyhow::Error> {
+    value
+        .parse::<i64>()
+        .map_err(|_| BadRequest(format!("Ожидался числовой идентификатор, получено \"{}\"", value)).into())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("telemt-admin-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn socket_is_private_before_it_appears() {
+        let dir = temp_dir("api-socket");
+        let path = dir.join("api.sock");
+        let staging = dir.join(".api.sock.staging");
+
+        let _listener = bind_private_socket(&staging, &path).unwrap();
+        let metadata = std::fs::symlink_metadata(&path).unwrap();
+        assert!(metadata.file_type().is_socket());
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        assert_eq!(
+            std::fs::metadata(&staging).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+        tokio::net::UnixStream::connect(&path).await.unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_socket_is_replaced() {
+        let dir = temp_dir("api-stale-socket");
+        let path = dir.join("api.sock");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let _listener = bind_unix_socket(&path).unwrap();
+        tokio::net::UnixStream::connect(&path).await.unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "временный каталог не удалён");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn regular_file_at_socket_path_survives() {
+        let dir = temp_dir("api-socket-file");
+        let path = dir.join("telemt.toml");
+        std::fs::write(&path, "[server]\nport = 443\n").unwrap();
+
+        assert!(bind_unix_socket(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[server]\nport = 443\n");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
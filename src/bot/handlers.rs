@@ -4,15 +4,35 @@
 mod callbacks;
 #[path = "handlers/commands/mod.rs"]
 mod commands;
+#[path = "handlers/config_reload.rs"]
+mod config_reload;
+#[path = "handlers/config_watch.rs"]
+mod config_watch;
+#[path = "handlers/expiry.rs"]
+mod expiry;
 #[path = "handlers/format.rs"]
 mod format;
 #[path = "handlers/menu.rs"]
 mod menu;
+#[path = "handlers/navigation.rs"]
+mod navigation;
+#[path = "handlers/restart.rs"]
+mod restart;
 #[path = "handlers/shared.rs"]
 mod shared;
 #[path = "handlers/state.rs"]
 mod state;
 
+pub use config_reload::spawn_sighup_reloader;
+pub use config_watch::spawn_config_watcher;
+pub use expiry::spawn_expiry_watcher;
+pub use format::{format_timestamp, format_user_limits, render_sync_reports};
+pub use restart::{apply_queued_restarts, spawn_restart_worker, RestartQueue, RestartScheduler};
+pub use shared::{
+    apply_config_sync, approve_request_and_build_link, approve_user_direct_and_build_link,
+    build_bot_start_link, build_user_links, create_invite_token, hard_ban_user, parse_data_quota,
+    parse_limit_count, reject_request, revoke_invite_token, spawn_startup_sync_check,
+};
 pub use state::BotState;
 
 use teloxide::dispatching::DpHandlerDescription;
@@ -1,9 +1,14 @@
-use super::format::render_user_card_text;
+use super::format::{render_invite_token_card, render_user_card_text};
 use super::shared::{
-    admin_show_users_page, approve_request_and_build_link, callback_message_target,
-    callback_prefix_filter, parse_callback_page, parse_callback_request_id, parse_callback_user_action,
-    perform_hard_ban, require_admin_callback, send_user_qr_to_admin, HandlerResult,
+    admin_show_audit_page, admin_show_service_logs, admin_show_tokens_page, admin_show_users_page, apply_config_sync,
+    approve_request_and_build_link,
+    callback_message_target, callback_prefix_filter, parse_callback_audit_page, parse_callback_logs, parse_callback_page, parse_callback_request_id,
+    parse_callback_token_action, parse_callback_user_action, perform_hard_ban, record_audit, reject_request, render_service_panel_text, revoke_invite_token,
+    check_self_rotate_allowed, require_admin_callback, rotate_user_secret, self_rotate_cooldown_text,
+    send_rotated_link_to_user, send_user_qr_to_admin, service_audit_action, service_panel_keyboard, HandlerResult, SecretRotateOutcome,
 };
+use crate::db::{AuditAction, AuditTargetKind, TokenStatus};
+use crate::roles::{AdminRole, Permission};
 use super::state::BotState;
 use teloxide::dptree;
 use teloxide::prelude::*;
@@ -16,6 +21,12 @@ pub fn handler() -> teloxide::dispatching::UpdateHandler<Box<dyn std::error::Err
         .branch(dptree::filter_map(callback_prefix_filter("user_open:")).endpoint(callback_user_open))
         .branch(dptree::filter_map(callback_prefix_filter("user_view:")).endpoint(callback_user_view))
         .branch(dptree::filter_map(callback_prefix_filter("user_ban:")).endpoint(callback_user_ban))
+        .branch(
+            dptree::filter_map(callback_prefix_filter("user_rotate:")).endpoint(callback_user_rotate),
+        )
+        .branch(
+            dptree::filter_map(callback_prefix_filter("self_rotate:")).endpoint(callback_self_rotate),
+        )
         .branch(dptree::filter_map(callback_prefix_filter("approve:")).endpoint(callback_approve))
         .branch(dptree::filter_map(callback_prefix_filter("reject:")).endpoint(callback_reject))
         .branch(
@@ -24,10 +35,20 @@ pub fn handler() -> teloxide::dispatching::UpdateHandler<Box<dyn std::error::Err
         .branch(
             dptree::filter_map(callback_prefix_filter("service:")).endpoint(callback_service_action),
         )
+        .branch(
+            dptree::filter_map(callback_prefix_filter("tokens_page:")).endpoint(callback_tokens_page),
+        )
+        .branch(dptree::filter_map(callback_prefix_filter("token:")).endpoint(callback_token_action))
+        .branch(
+            dptree::filter_map(callback_prefix_filter("audit_page:")).endpoint(callback_audit_page),
+        )
+        .branch(dptree::filter_map(callback_prefix_filter("sync:")).endpoint(callback_sync))
+        .branch(dptree::filter_map(callback_prefix_filter("logs:")).endpoint(callback_service_logs))
+        .branch(dptree::filter_map(callback_prefix_filter("admin:")).endpoint(callback_admin_change))
 }
 
 async fn callback_approve(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
-    let Some(admin_id) = require_admin_callback(&bot, &q, &state).await? else {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ModerateRequests).await? else {
         return Ok(());
     };
 
@@ -40,7 +61,7 @@ async fn callback_approve(bot: Bot, q: CallbackQuery, state: BotState) -> Handle
     );
     let message_target = callback_message_target(&q);
 
-    let (request, link) = match approve_request_and_build_link(&state, request_id).await? {
+    let (request, link) = match approve_request_and_build_link(&state, request_id, Some(admin_id)).await? {
         Some(payload) => payload,
         None => {
             bot.answer_callback_query(q.id.clone())
@@ -69,7 +90,7 @@ async fn callback_approve(bot: Bot, q: CallbackQuery, state: BotState) -> Handle
 }
 
 async fn callback_reject(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
-    let Some(admin_id) = require_admin_callback(&bot, &q, &state).await? else {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ModerateRequests).await? else {
         return Ok(());
     };
 
@@ -81,7 +102,7 @@ async fn callback_reject(bot: Bot, q: CallbackQuery, state: BotState) -> Handler
         "Reject callback received"
     );
     let message_target = callback_message_target(&q);
-    let request = state.db.reject(request_id).await?;
+    let request = reject_request(&state, request_id, Some(admin_id)).await?;
 
     bot.answer_callback_query(q.id.clone()).text("Отклонено").await?;
 
@@ -103,7 +124,7 @@ async fn callback_reject(bot: Bot, q: CallbackQuery, state: BotState) -> Handler
 }
 
 async fn callback_users_page(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
-    if require_admin_callback(&bot, &q, &state).await?.is_none() {
+    if require_admin_callback(&bot, &q, &state, Permission::ViewUsers).await?.is_none() {
         return Ok(());
     }
 
@@ -118,9 +139,9 @@ async fn callback_users_page(bot: Bot, q: CallbackQuery, state: BotState) -> Han
 }
 
 async fn callback_user_open(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
-    if require_admin_callback(&bot, &q, &state).await?.is_none() {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ViewUsers).await? else {
         return Ok(());
-    }
+    };
 
     let data = q.data.as_deref().unwrap_or("");
     let (tg_user_id, page) = parse_callback_user_action(data, "user_open:")?;
@@ -138,14 +159,18 @@ async fn callback_user_open(bot: Bot, q: CallbackQuery, state: BotState) -> Hand
         .await?;
     if let Some((chat_id, message_id)) = callback_message_target(&q) {
         bot.edit_message_text(chat_id, message_id, render_user_card_text(&user))
-            .reply_markup(crate::bot::keyboards::user_card_keyboard(user.tg_user_id, page))
+            .reply_markup(crate::bot::keyboards::user_card_keyboard(
+                user.tg_user_id,
+                page,
+                state.has_permission(admin_id, Permission::ManageUsers),
+            ))
             .await?;
     }
     Ok(())
 }
 
 async fn callback_user_view(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
-    if require_admin_callback(&bot, &q, &state).await?.is_none() {
+    if require_admin_callback(&bot, &q, &state, Permission::ManageUsers).await?.is_none() {
         return Ok(());
     }
 
@@ -168,13 +193,13 @@ async fn callback_user_view(bot: Bot, q: CallbackQuery, state: BotState) -> Hand
 }
 
 async fn callback_user_ban(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
-    if require_admin_callback(&bot, &q, &state).await?.is_none() {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ManageUsers).await? else {
         return Ok(());
-    }
+    };
 
     let data = q.data.as_deref().unwrap_or("");
     let (tg_user_id, page) = parse_callback_user_action(data, "user_ban:")?;
-    let status_text = perform_hard_ban(&state, tg_user_id).await?;
+    let status_text = perform_hard_ban(&state, tg_user_id, admin_id).await?;
     bot.answer_callback_query(q.id.clone())
         .text(status_text.clone())
         .await?;
@@ -186,14 +211,125 @@ async fn callback_user_ban(bot: Bot, q: CallbackQuery, state: BotState) -> Handl
     Ok(())
 }
 
-async fn callback_delete_user(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
-    if require_admin_callback(&bot, &q, &state).await?.is_none() {
+async fn callback_user_rotate(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ManageUsers).await? else {
         return Ok(());
+    };
+
+    let data = q.data.as_deref().unwrap_or("");
+    let (tg_user_id, page) = parse_callback_user_action(data, "user_rotate:")?;
+    tracing::info!(admin_id = admin_id, tg_user_id = tg_user_id, "Admin rotates user secret");
+    let SecretRotateOutcome::Rotated(link) = rotate_user_secret(&state, tg_user_id, admin_id, None).await? else {
+        bot.answer_callback_query(q.id.clone())
+            .text("Пользователь уже неактивен")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    };
+    bot.answer_callback_query(q.id.clone())
+        .text("Ссылка перевыпущена")
+        .await?;
+
+    let delivered = match send_rotated_link_to_user(&bot, tg_user_id, &link).await {
+        Ok(()) => true,
+        Err(error) => {
+            tracing::warn!(
+                tg_user_id = tg_user_id,
+                error = %error,
+                "Не удалось отправить пользователю новую ссылку"
+            );
+            false
+        }
+    };
+    let rotations = state.db.count_secret_history(tg_user_id).await?;
+    if let Some((chat_id, message_id)) = callback_message_target(&q) {
+        bot.send_message(
+            chat_id,
+            format!(
+                "♻️ Ссылка пользователя {} перевыпущена (всего перевыпусков: {}).\n{}",
+                tg_user_id,
+                rotations,
+                if delivered {
+                    "Новая ссылка отправлена пользователю."
+                } else {
+                    "Пользователю не удалось отправить сообщение — перешлите ссылку вручную."
+                }
+            ),
+        )
+        .await?;
+        if let Some(user) = state.db.get_active_user_by_tg_user(tg_user_id).await? {
+            send_user_qr_to_admin(&bot, &q, &user, &state).await?;
+            bot.edit_message_text(chat_id, message_id, render_user_card_text(&user))
+                .reply_markup(crate::bot::keyboards::user_card_keyboard(tg_user_id, page, true))
+                .await?;
+        }
     }
+    Ok(())
+}
+
+async fn callback_self_rotate(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    let tg_user_id = q.from.id.0 as i64;
+    let data = q.data.as_deref().unwrap_or("");
+    let message_target = callback_message_target(&q);
+    if let Some((chat_id, message_id)) = message_target {
+        bot.edit_message_reply_markup(chat_id, message_id)
+            .reply_markup(teloxide::types::InlineKeyboardMarkup::default())
+            .await?;
+    }
+    if data != "self_rotate:confirm" {
+        bot.answer_callback_query(q.id.clone()).text("Отменено").await?;
+        return Ok(());
+    }
+
+    // Лимит проверяется повторно: между вопросом и подтверждением могло пройти время.
+    if let Err(reason) = check_self_rotate_allowed(&state, tg_user_id).await? {
+        bot.answer_callback_query(q.id.clone())
+            .text(reason)
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+    tracing::info!(tg_user_id = tg_user_id, "User rotates own secret");
+    let cooldown_hours = state.config().security.self_rotate_cooldown_hours;
+    let link = match rotate_user_secret(
+        &state,
+        tg_user_id,
+        tg_user_id,
+        Some(cooldown_hours.saturating_mul(3_600)),
+    )
+    .await?
+    {
+        SecretRotateOutcome::Rotated(link) => link,
+        SecretRotateOutcome::Inactive => {
+            bot.answer_callback_query(q.id.clone())
+                .text("У вас нет доступа к прокси")
+                .show_alert(true)
+                .await?;
+            return Ok(());
+        }
+        SecretRotateOutcome::Cooldown(next_allowed) => {
+            bot.answer_callback_query(q.id.clone())
+                .text(self_rotate_cooldown_text(cooldown_hours, next_allowed))
+                .show_alert(true)
+                .await?;
+            return Ok(());
+        }
+    };
+    bot.answer_callback_query(q.id.clone())
+        .text("Ссылка перевыпущена")
+        .await?;
+    send_rotated_link_to_user(&bot, tg_user_id, &link).await?;
+    Ok(())
+}
+
+async fn callback_delete_user(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ManageUsers).await? else {
+        return Ok(());
+    };
 
     let data = q.data.as_deref().unwrap_or("");
     let tg_user_id = parse_callback_request_id(data, "delete_user:")?;
-    let status_text = perform_hard_ban(&state, tg_user_id).await?;
+    let status_text = perform_hard_ban(&state, tg_user_id, admin_id).await?;
 
     bot.answer_callback_query(q.id.clone())
         .text(status_text.clone())
@@ -204,38 +340,350 @@ async fn callback_delete_user(bot: Bot, q: CallbackQuery, state: BotState) -> Ha
             .reply_markup(teloxide::types::InlineKeyboardMarkup::default())
             .await?;
         bot.send_message(chat_id, status_text)
-            .reply_markup(crate::bot::keyboards::admin_menu())
+            .reply_markup(crate::bot::keyboards::admin_menu(&state.admin_access(admin_id)))
             .await?;
     }
     Ok(())
 }
 
 async fn callback_service_action(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
-    if require_admin_callback(&bot, &q, &state).await?.is_none() {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ManageService).await? else {
         return Ok(());
-    }
+    };
 
+    // service:<action>[:<instance>]; без экземпляра — основной (старые сообщения).
     let data = q.data.as_deref().unwrap_or("");
-    let action = data.strip_prefix("service:").unwrap_or("status");
-    let (action_name, result) = match action {
-        "restart" => ("restart", state.service.restart()),
-        "reload" => ("reload", state.service.reload()),
-        "status" => ("status", state.service.status()),
-        _ => ("status", state.service.status()),
+    let mut parts = data.strip_prefix("service:").unwrap_or("status").split(':');
+    let action = parts.next().unwrap_or("status");
+    let Some(instance) = parts
+        .next()
+        .map_or(Some(state.instances.primary()), |name| state.instances.get(name))
+    else {
+        bot.answer_callback_query(q.id.clone())
+            .text("Экземпляр telemt не найден")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    };
+    let result = match action {
+        "restart" => Some(("restart", instance.restart().await)),
+        "reload" => Some(("reload", instance.reload().await)),
+        _ => None,
     };
 
+    if let Some((action_name, result)) = &result
+        && let Some(audit_action) = service_audit_action(action_name)
+    {
+        record_audit(
+            &state,
+            Some(admin_id),
+            audit_action,
+            AuditTargetKind::Service,
+            &instance.service_name,
+            None,
+            Some(if result.success { "ok" } else { "failed" }),
+        )
+        .await;
+    }
+
+    let action_name = result.as_ref().map_or("status", |(name, _)| *name);
     bot.answer_callback_query(q.id.clone())
         .text(format!("Выполнено: {}", action_name))
         .await?;
 
     if let Some((chat_id, message_id)) = callback_message_target(&q) {
-        let text = format!(
-            "⚙️ Сервис telemt\n\n{}",
-            state.service.format_result(action_name, &result)
-        );
+        let text = render_service_panel_text(
+            &state,
+            instance,
+            result.as_ref().map(|(name, result)| (*name, result)),
+        )
+        .await;
         bot.edit_message_text(chat_id, message_id, text)
-            .reply_markup(crate::bot::keyboards::service_control_buttons())
+            .reply_markup(service_panel_keyboard(&state, instance))
             .await?;
     }
     Ok(())
 }
+
+async fn callback_service_logs(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    if require_admin_callback(&bot, &q, &state, Permission::ManageService).await?.is_none() {
+        return Ok(());
+    }
+
+    let data = q.data.as_deref().unwrap_or("");
+    let (as_file, priority, window, instance) = parse_callback_logs(data)?;
+    let Some(instance) = instance.map_or(Some(state.instances.primary()), |name| state.instances.get(name))
+    else {
+        bot.answer_callback_query(q.id.clone())
+            .text("Экземпляр telemt не найден")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    };
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    if let Some((chat_id, message_id)) = callback_message_target(&q) {
+        // Файл и логи под документом не получить редактированием текста — шлём новым сообщением.
+        let from_text = q
+            .regular_message()
+            .is_some_and(|message| message.text().is_some());
+        let message_id = (!as_file && from_text).then_some(message_id);
+        admin_show_service_logs(
+            &bot, chat_id, &state, instance, priority, window, as_file, message_id,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn callback_tokens_page(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    if require_admin_callback(&bot, &q, &state, Permission::IssueTokens).await?.is_none() {
+        return Ok(());
+    }
+
+    let data = q.data.as_deref().unwrap_or("");
+    let page = parse_callback_page(data, "tokens_page:")?;
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    if let Some((chat_id, message_id)) = callback_message_target(&q) {
+        admin_show_tokens_page(&bot, chat_id, &state, page, Some(message_id)).await?;
+    }
+    Ok(())
+}
+
+async fn callback_token_action(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::IssueTokens).await? else {
+        return Ok(());
+    };
+
+    let data = q.data.as_deref().unwrap_or("");
+    if data.starts_with("token:view:") {
+        let (token, page) = parse_callback_token_action(data, "token:view:")?;
+        return show_token_card(&bot, &q, &state, &token, page, None).await;
+    }
+
+    if data.starts_with("token:revoke:") {
+        let (token, page) = parse_callback_token_action(data, "token:revoke:")?;
+        let Some(row) = state.db.get_invite_token(&token).await? else {
+            bot.answer_callback_query(q.id.clone())
+                .text("Токен не найден")
+                .show_alert(true)
+                .await?;
+            return Ok(());
+        };
+        if !row.is_active {
+            return show_token_card(&bot, &q, &state, &token, page, Some("Токен уже отозван")).await;
+        }
+
+        bot.answer_callback_query(q.id.clone()).await?;
+        if let Some((chat_id, message_id)) = callback_message_target(&q) {
+            bot.edit_message_text(
+                chat_id,
+                message_id,
+                format!(
+                    "Отозвать токен {}?\n\nНовые регистрации по нему станут невозможны. \
+                     Уже выданный доступ сохранится.",
+                    token
+                ),
+            )
+            .reply_markup(crate::bot::keyboards::token_revoke_confirm_keyboard(&token, page))
+            .await?;
+        }
+        return Ok(());
+    }
+
+    if data.starts_with("token:revoke_confirm:") {
+        let (token, page) = parse_callback_token_action(data, "token:revoke_confirm:")?;
+        let revoked = revoke_invite_token(&state, &token, Some(admin_id)).await?;
+        if revoked {
+            tracing::info!(admin_id = admin_id, token = %token, "Admin revoked invite token");
+        }
+        let notice = if revoked {
+            "Токен отозван"
+        } else {
+            "Токен не найден или уже отозван"
+        };
+        return show_token_card(&bot, &q, &state, &token, page, Some(notice)).await;
+    }
+
+    bot.answer_callback_query(q.id.clone())
+        .text("Неизвестное действие")
+        .await?;
+    Ok(())
+}
+
+async fn show_token_card(
+    bot: &Bot,
+    q: &CallbackQuery,
+    state: &BotState,
+    token: &str,
+    page: i64,
+    notice: Option<&str>,
+) -> HandlerResult {
+    let Some(row) = state.db.get_invite_token(token).await? else {
+        bot.answer_callback_query(q.id.clone())
+            .text("Токен не найден")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    };
+    let uses = state.db.list_invite_token_uses(row.id, 20).await?;
+    let now = chrono::Utc::now().timestamp();
+
+    let mut answer = bot.answer_callback_query(q.id.clone());
+    if let Some(notice) = notice {
+        answer = answer.text(notice);
+    }
+    answer.await?;
+
+    if let Some((chat_id, message_id)) = callback_message_target(q) {
+        let can_revoke = row.status_at(now) != TokenStatus::Revoked;
+        bot.edit_message_text(chat_id, message_id, render_invite_token_card(&row, &uses, now))
+            .reply_markup(crate::bot::keyboards::token_card_keyboard(token, page, can_revoke))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn callback_audit_page(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    if require_admin_callback(&bot, &q, &state, Permission::ViewAudit).await?.is_none() {
+        return Ok(());
+    }
+
+    let data = q.data.as_deref().unwrap_or("");
+    let (filter, page_size, page) = parse_callback_audit_page(data)?;
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    if let Some((chat_id, message_id)) = callback_message_target(&q) {
+        admin_show_audit_page(&bot, chat_id, &state, filter, page_size, page, Some(message_id))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn callback_sync(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ManageService).await? else {
+        return Ok(());
+    };
+
+    let data = q.data.as_deref().unwrap_or("");
+    let text = match data {
+        "sync:apply" => {
+            bot.answer_callback_query(q.id.clone())
+                .text("Применяю исправления")
+                .await?;
+            apply_config_sync(&state, Some(admin_id)).await?
+        }
+        _ => {
+            bot.answer_callback_query(q.id.clone()).text("Отменено").await?;
+            "Синхронизация отменена.".to_string()
+        }
+    };
+
+    if let Some((chat_id, message_id)) = callback_message_target(&q) {
+        bot.edit_message_text(chat_id, message_id, text)
+            .reply_markup(teloxide::types::InlineKeyboardMarkup::default())
+            .await?;
+    }
+    Ok(())
+}
+
+async fn callback_admin_change(bot: Bot, q: CallbackQuery, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_admin_callback(&bot, &q, &state, Permission::ManageAdmins).await? else {
+        return Ok(());
+    };
+
+    let data = q.data.as_deref().unwrap_or("");
+    let parts: Vec<&str> = data.split(':').collect();
+    let (action, tg_user_id, role) = match parts.as_slice() {
+        ["admin", action @ ("add" | "remove"), tg_user_id, role] => {
+            let Ok(tg_user_id) = tg_user_id.parse::<i64>() else {
+                return Err(anyhow::anyhow!("Некорректный callback payload").into());
+            };
+            (*action, tg_user_id, AdminRole::parse(role))
+        }
+        _ => {
+            bot.answer_callback_query(q.id.clone()).text("Отменено").await?;
+            if let Some((chat_id, message_id)) = callback_message_target(&q) {
+                bot.edit_message_text(chat_id, message_id, "Изменение ролей отменено.")
+                    .reply_markup(teloxide::types::InlineKeyboardMarkup::default())
+                    .await?;
+            }
+            return Ok(());
+        }
+    };
+    tracing::info!(
+        admin_id = admin_id,
+        tg_user_id = tg_user_id,
+        action = action,
+        role = ?role,
+        "Admin role change confirmed"
+    );
+
+    let (text, user_notice) = match (action, role) {
+        ("add", Some(role)) => {
+            if state.db.add_admin_grant(tg_user_id, role, Some(admin_id)).await? {
+                record_audit(
+                    &state,
+                    Some(admin_id),
+                    AuditAction::AdminAdd,
+                    AuditTargetKind::Admin,
+                    &tg_user_id.to_string(),
+                    None,
+                    Some(role.as_str()),
+                )
+                .await;
+                (
+                    format!("🛡 {} выдана роль {}.", tg_user_id, role.label()),
+                    Some(format!(
+                        "🛡 Вам выдана роль {} в боте. Доступные команды: /help",
+                        role.label()
+                    )),
+                )
+            } else {
+                (format!("Роль {} у {} уже есть.", role.label(), tg_user_id), None)
+            }
+        }
+        ("add", None) => return Err(anyhow::anyhow!("Некорректный callback payload").into()),
+        (_, role) => {
+            let removed = state.db.remove_admin_grants(tg_user_id, role).await?;
+            if removed > 0 {
+                let label = role.map(AdminRole::as_str).unwrap_or("all");
+                record_audit(
+                    &state,
+                    Some(admin_id),
+                    AuditAction::AdminRemove,
+                    AuditTargetKind::Admin,
+                    &tg_user_id.to_string(),
+                    Some(label),
+                    None,
+                )
+                .await;
+                let roles = role
+                    .map(|role| format!("роль {}", role.label()))
+                    .unwrap_or_else(|| "все роли, выданные из бота".to_string());
+                (
+                    format!("🛡 С {} снята {}.", tg_user_id, roles),
+                    Some(format!("🛡 С вас снята {} в боте.", roles)),
+                )
+            } else {
+                (format!("У {} нечего снимать: роли уже сняты.", tg_user_id), None)
+            }
+        }
+    };
+    state.reload_admin_grants().await?;
+
+    bot.answer_callback_query(q.id.clone()).text("Готово").await?;
+    if let Some((chat_id, message_id)) = callback_message_target(&q) {
+        bot.edit_message_text(chat_id, message_id, text)
+            .reply_markup(teloxide::types::InlineKeyboardMarkup::default())
+            .await?;
+    }
+    if let Some(notice) = user_notice
+        && let Err(error) = bot.send_message(ChatId(tg_user_id), notice).await
+    {
+        // Пользователь мог ещё не открыть чат с ботом; роль от этого не зависит.
+        tracing::warn!(tg_user_id = tg_user_id, error = %error, "Не удалось сообщить пользователю об изменении ролей");
+    }
+    Ok(())
+}
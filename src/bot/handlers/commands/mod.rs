@@ -1,18 +1,61 @@
-use super::format::{format_date, format_mode, render_invite_token_line};
 use super::shared::{
-    admin_show_pending, admin_show_service_panel, admin_show_stats, admin_show_users_page,
-    approve_request_and_build_link, approve_user_direct_and_build_link, build_bot_start_link,
-    is_user_waiting_for_invite, mark_user_waiting_for_invite, parse_create_target, parse_start_token,
-    perform_hard_ban, process_invite_token, send_user_link, unmark_user_waiting_for_invite,
-    user_id_or_reply, CreateTarget, HandlerResult,
+    admin_show_audit_page, admin_show_pending, admin_show_service_panel, admin_show_stats, admin_show_tokens_page,
+    admin_show_users_page,
+    apply_config_sync, approve_request_and_build_link, approve_user_direct_and_build_link,
+    create_invite_token_for_admin,
+    is_user_waiting_for_invite, mark_user_waiting_for_invite, parse_create_target, parse_data_quota,
+    parse_limit_count, parse_start_token,
+    build_user_links, perform_hard_ban, process_invite_token, record_audit, reject_request, require_permission, revoke_invite_token, render_service_panel_text, service_audit_action, send_user_link, unmark_user_waiting_for_invite,
+    user_id_or_reply, user_instances, CreateTarget, HandlerResult, AUDIT_DEFAULT_PAGE_SIZE, AUDIT_MAX_PAGE_SIZE,
 };
-use super::state::{is_admin_message, sender_display_name, sender_user_id, telemt_username, BotState};
-use crate::db::RequestStatus;
+use super::format::{
+    format_access_days, format_user_limits, render_admin_list, render_config_backups,
+    render_config_reload, render_sync_reports, user_display_name,
+};
+use super::config_reload::reload_bot_config;
+use super::navigation::reset_admin_menu;
+use super::state::{sender_display_name, sender_user_id, telemt_username, BotState};
+use crate::db::{AuditAction, AuditFilter, AuditTargetKind, RequestStatus, UserLimits};
+use crate::instance::TelemtInstance;
+use crate::roles::{AdminRole, Permission};
+use crate::telemt_cfg::UserPolicy;
+use std::sync::Arc;
 use teloxide::dptree;
 use teloxide::prelude::*;
 use teloxide::types::ParseMode;
 use teloxide::utils::command::BotCommands;
 
+const TOKEN_CREATE_USAGE: &str = "Использование: /token create [days] [--auto|-a] [--max-uses N] [--access-days N] [--conns N] [--ips N] [--quota 10G]";
+const SERVICE_USAGE: &str = "Использование: /service [экземпляр] <start|stop|restart|reload|status>";
+const CONFIG_USAGE: &str = "Использование: /config [экземпляр] backups — копии конфига telemt\n\
+     /config [экземпляр] rollback <id> — вернуть конфиг к копии и перезапустить telemt";
+const ADMIN_USAGE: &str = "Использование: /admin list — администраторы и их роли\n\
+     /admin add <tg_user_id | @username> <owner|moderator|viewer|token_issuer> — выдать роль\n\
+     /admin remove <tg_user_id | @username> [роль] — снять роль или все роли, выданные из бота";
+const LIMITS_USAGE: &str = "Использование: /limits <tg_user_id | @username> [conns=N|-] [ips=N|-] [quota=10G|-]\n\
+     /limits <tg_user_id | @username> reset — снять все лимиты.\n\
+     Без параметров показывает текущие лимиты.";
+
+/// Справка по админским командам: каждая строка видна только с нужным правом.
+const ADMIN_HELP: &[(Permission, &str)] = &[
+    (Permission::ModerateRequests, "/approve <id> — одобрить заявку"),
+    (Permission::ModerateRequests, "/reject <id> — отклонить заявку"),
+    (Permission::ManageUsers, "/create <tg_user_id | @username> [days] — создать пользователя (с ограничением доступа в днях)"),
+    (Permission::ManageUsers, "/delete <tg_user_id> — удалить пользователя"),
+    (Permission::ManageService, "/service [экземпляр] <start|stop|restart|reload|status> — управление сервисом telemt"),
+    (Permission::ManageUsers, "/instances <tg_user_id | @username> [имя ...|all] — экземпляры telemt пользователя"),
+    (Permission::ManageUsers, "/limits <tg_user_id | @username> [conns=N] [ips=N] [quota=10G] — лимиты пользователя («-» снимает лимит, reset — все)"),
+    (Permission::IssueTokens, "/token create [days] [--auto|-a] [--max-uses N] [--access-days N] [--conns N] [--ips N] [--quota 10G] — создать invite-токен"),
+    (Permission::IssueTokens, "/token list — все invite-токены с карточками и отзывом"),
+    (Permission::IssueTokens, "/token revoke <token> — отозвать invite-токен"),
+    (Permission::ViewAudit, "/audit [user|token|admin] [N] — журнал действий, N записей на страницу"),
+    (Permission::ManageService, "/sync [apply] — сверить БД с конфигом telemt и исправить расхождения"),
+    (Permission::ManageService, "/config [экземпляр] backups — резервные копии конфига telemt"),
+    (Permission::ManageService, "/config [экземпляр] rollback <id> — откатить конфиг telemt к копии"),
+    (Permission::ManageAdmins, "/admin list|add|remove — администраторы и их роли"),
+    (Permission::ReloadConfig, "/reload_config — перечитать telemt-admin.toml без перезапуска бота"),
+];
+
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase")]
 pub enum BotCommand {
@@ -34,6 +77,20 @@ pub enum BotCommand {
     Service,
     #[command(description = "Управление invite-токенами (админ)")]
     Token,
+    #[command(description = "Журнал действий (админ)")]
+    Audit,
+    #[command(description = "Сверить БД с конфигом telemt (админ)")]
+    Sync,
+    #[command(description = "Лимиты пользователя (админ)")]
+    Limits,
+    #[command(description = "Экземпляры telemt пользователя (админ)")]
+    Instances,
+    #[command(description = "Резервные копии конфига telemt (админ)")]
+    Config,
+    #[command(description = "Администраторы и роли (владелец)")]
+    Admin,
+    #[command(rename = "reload_config", description = "Перечитать конфиг бота (владелец)")]
+    ReloadConfig,
 }
 
 pub fn handler() -> teloxide::dispatching::UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
@@ -47,28 +104,41 @@ pub fn handler() -> teloxide::dispatching::UpdateHandler<Box<dyn std::error::Err
         .branch(dptree::case![BotCommand::Delete].endpoint(cmd_delete))
         .branch(dptree::case![BotCommand::Service].endpoint(cmd_service))
         .branch(dptree::case![BotCommand::Token].endpoint(cmd_token))
+        .branch(dptree::case![BotCommand::Audit].endpoint(cmd_audit))
+        .branch(dptree::case![BotCommand::Sync].endpoint(cmd_sync))
+        .branch(dptree::case![BotCommand::Limits].endpoint(cmd_limits))
+        .branch(dptree::case![BotCommand::Instances].endpoint(cmd_instances))
+        .branch(dptree::case![BotCommand::Config].endpoint(cmd_config))
+        .branch(dptree::case![BotCommand::Admin].endpoint(cmd_admin))
+        .branch(dptree::case![BotCommand::ReloadConfig].endpoint(cmd_reload_config))
 }
 
 pub async fn cmd_help(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
     let Some(user_id) = sender_user_id(&msg) else {
         return Ok(());
     };
-    let is_admin = state.config.is_admin(user_id);
-    let text = r#"Команды:
-/start — зарегистрироваться (заявка на подтверждение админу)
-/link — получить ссылку на прокси (если уже одобрены)
-
-Для администраторов:
-/approve <id> — одобрить заявку
-/reject <id> — отклонить заявку
-/create <tg_user_id | @username> — создать пользователя
-/delete <tg_user_id> — удалить пользователя
-/service <start|stop|restart|reload|status> — управление telemt.service
-/token create [days] [--auto|-a] [--max-uses N] — создать invite-токен
-/token list — список активных invite-токенов
-/token revoke <token> — отозвать invite-токен"#;
-    let reply_markup = if is_admin {
-        crate::bot::keyboards::admin_menu()
+    let access = state.admin_access(user_id);
+    let mut text = String::from(
+        "Команды:\n\
+         /start — зарегистрироваться (заявка на подтверждение админу)\n\
+         /link — получить ссылку на прокси (если уже одобрены)",
+    );
+    let admin_commands: Vec<&str> = ADMIN_HELP
+        .iter()
+        .filter(|(permission, _)| access.allows(*permission))
+        .map(|(_, line)| *line)
+        .collect();
+    if !admin_commands.is_empty() {
+        let roles: Vec<&str> = access.roles().map(|role| role.label()).collect();
+        text.push_str(&format!(
+            "\n\nДля администраторов (ваши роли: {}):\n",
+            roles.join(", ")
+        ));
+        text.push_str(&admin_commands.join("\n"));
+    }
+    let reply_markup = if access.is_admin() {
+        reset_admin_menu(&state, msg.chat.id).await;
+        crate::bot::keyboards::admin_menu(&access)
     } else {
         crate::bot::keyboards::user_menu()
     };
@@ -95,12 +165,14 @@ async fn start_cmd(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
         "Received /start command"
     );
 
-    if state.config.is_admin(user_id) {
+    let access = state.admin_access(user_id);
+    if access.is_admin() {
+        reset_admin_menu(&state, msg.chat.id).await;
         bot.send_message(
             msg.chat.id,
             "Добро пожаловать в панель администратора. Используйте кнопки ниже.",
         )
-        .reply_markup(crate::bot::keyboards::admin_menu())
+        .reply_markup(crate::bot::keyboards::admin_menu(&access))
         .await?;
         return Ok(());
     }
@@ -109,8 +181,7 @@ async fn start_cmd(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
         match existing.status {
             RequestStatus::Approved => {
                 if let Some(secret) = existing.secret {
-                    let params = state.telemt_cfg.read_link_params()?;
-                    let link = crate::link::build_proxy_link(&params, &secret)?;
+                    let link = build_user_links(&state, user_id, &secret).await?;
                     bot.send_message(msg.chat.id, format!("Ваша ссылка на прокси:\n\n{}", link))
                         .reply_markup(crate::bot::keyboards::user_menu())
                         .await?;
@@ -138,7 +209,7 @@ async fn start_cmd(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
                 unmark_user_waiting_for_invite(&state, user_id).await;
                 return Ok(());
             }
-            RequestStatus::Deleted => {}
+            RequestStatus::Deleted | RequestStatus::Expired => {}
         }
     }
 
@@ -177,9 +248,9 @@ async fn cmd_link(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
 }
 
 async fn cmd_approve(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
-    if !is_admin_message(&msg, &state) {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ModerateRequests).await? else {
         return Ok(());
-    }
+    };
 
     let text = msg.text().unwrap_or("");
     let request_id: i64 = match text.split_whitespace().nth(1).unwrap_or("").parse() {
@@ -192,7 +263,7 @@ async fn cmd_approve(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
     };
     tracing::info!(request_id = request_id, "Admin command /approve");
 
-    let (request, link) = match approve_request_and_build_link(&state, request_id).await? {
+    let (request, link) = match approve_request_and_build_link(&state, request_id, Some(admin_id)).await? {
         Some(payload) => payload,
         None => {
             bot.send_message(msg.chat.id, "Заявка не найдена или уже обработана")
@@ -215,9 +286,9 @@ async fn cmd_approve(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
 }
 
 async fn cmd_reject(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
-    if !is_admin_message(&msg, &state) {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ModerateRequests).await? else {
         return Ok(());
-    }
+    };
 
     let text = msg.text().unwrap_or("");
     let request_id: i64 = match text.split_whitespace().nth(1).unwrap_or("").parse() {
@@ -230,7 +301,7 @@ async fn cmd_reject(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
     };
     tracing::info!(request_id = request_id, "Admin command /reject");
 
-    let req = state.db.reject(request_id).await?;
+    let req = reject_request(&state, request_id, Some(admin_id)).await?;
     if let Some(r) = req {
         bot.send_message(msg.chat.id, "Заявка отклонена").await?;
         bot.send_message(
@@ -246,12 +317,26 @@ async fn cmd_reject(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
 }
 
 async fn cmd_create(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
-    if !is_admin_message(&msg, &state) {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ManageUsers).await? else {
         return Ok(());
-    }
+    };
 
     let text = msg.text().unwrap_or("");
-    let arg = text.split_whitespace().nth(1).unwrap_or("");
+    let mut args = text.split_whitespace().skip(1);
+    let arg = args.next().unwrap_or("");
+    let access_days = match args.next().map(str::parse::<i64>) {
+        None => None,
+        Some(Ok(days)) if days >= 1 => Some(days),
+        Some(_) => {
+            bot.send_message(
+                msg.chat.id,
+                "Использование: /create <telegram_user_id | @username> [days]\n\
+                 days — срок доступа в днях (целое число >= 1).",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
     let tg_user_id: i64 = match parse_create_target(arg) {
         Some(CreateTarget::UserId(id)) => id,
         Some(CreateTarget::Username(username)) => {
@@ -274,7 +359,7 @@ async fn cmd_create(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
         None => {
             bot.send_message(
                 msg.chat.id,
-                "Использование: /create <telegram_user_id | @username>",
+                "Использование: /create <telegram_user_id | @username> [days]",
             )
             .await?;
             return Ok(());
@@ -283,20 +368,41 @@ async fn cmd_create(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
     tracing::info!(tg_user_id = tg_user_id, "Admin command /create");
 
     let telemt_user = telemt_username(tg_user_id);
-    let link = approve_user_direct_and_build_link(&state, tg_user_id, None, None).await?;
+    let limits = state
+        .db
+        .get_request_by_tg_user(tg_user_id)
+        .await?
+        .map(|request| request.limits)
+        .unwrap_or_default();
+    let link = approve_user_direct_and_build_link(
+        &state,
+        tg_user_id,
+        None,
+        None,
+        Some(admin_id),
+        AuditAction::UserCreate,
+        access_days,
+        limits,
+    )
+    .await?;
 
     bot.send_message(
         msg.chat.id,
-        format!("Пользователь {} создан.\nСсылка:\n{}", telemt_user, link),
+        format!(
+            "Пользователь {} создан.\nСрок доступа: {}\nСсылка:\n{}",
+            telemt_user,
+            format_access_days(access_days),
+            link
+        ),
     )
     .await?;
     Ok(())
 }
 
 async fn cmd_delete(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
-    if !is_admin_message(&msg, &state) {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ManageUsers).await? else {
         return Ok(());
-    }
+    };
 
     let text = msg.text().unwrap_or("");
     let tg_user_id: i64 = match text.split_whitespace().nth(1).unwrap_or("").parse() {
@@ -309,53 +415,367 @@ async fn cmd_delete(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
     };
     tracing::info!(tg_user_id = tg_user_id, "Admin command /delete");
 
-    let status_text = perform_hard_ban(&state, tg_user_id).await?;
+    let status_text = perform_hard_ban(&state, tg_user_id, admin_id).await?;
     bot.send_message(msg.chat.id, status_text).await?;
     Ok(())
 }
 
-async fn cmd_service(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
-    if !is_admin_message(&msg, &state) {
+async fn cmd_limits(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ManageUsers).await? else {
+        return Ok(());
+    };
+
+    let text = msg.text().unwrap_or("");
+    let mut args = text.split_whitespace().skip(1);
+    let tg_user_id = match parse_create_target(args.next().unwrap_or("")) {
+        Some(CreateTarget::UserId(id)) => id,
+        Some(CreateTarget::Username(username)) => {
+            match state.db.find_tg_user_id_by_username(&username).await? {
+                Some(user_id) => user_id,
+                None => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!("Пользователь @{} не найден в базе.", username),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => {
+            bot.send_message(msg.chat.id, LIMITS_USAGE).await?;
+            return Ok(());
+        }
+    };
+    let Some(user) = state.db.get_request_by_tg_user(tg_user_id).await? else {
+        bot.send_message(msg.chat.id, "Пользователь не найден в базе.")
+            .await?;
         return Ok(());
+    };
+
+    let mut limits = user.limits;
+    let mut changed = false;
+    for arg in args {
+        if arg == "reset" {
+            limits = UserLimits::default();
+            changed = true;
+            continue;
+        }
+        let Some((key, value)) = arg.split_once('=') else {
+            bot.send_message(msg.chat.id, LIMITS_USAGE).await?;
+            return Ok(());
+        };
+        let parsed = match (key, value) {
+            (_, "-") => Some(None),
+            ("conns" | "ips", value) => parse_limit_count(value).map(Some),
+            ("quota", value) => parse_data_quota(value).map(Some),
+            _ => None,
+        };
+        let Some(parsed) = parsed else {
+            bot.send_message(msg.chat.id, LIMITS_USAGE).await?;
+            return Ok(());
+        };
+        match key {
+            "conns" => limits.max_tcp_conns = parsed,
+            "ips" => limits.max_unique_ips = parsed,
+            "quota" => limits.data_quota_bytes = parsed,
+            _ => {
+                bot.send_message(msg.chat.id, LIMITS_USAGE).await?;
+                return Ok(());
+            }
+        }
+        changed = true;
     }
 
+    if !changed {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "🚦 Лимиты {} ({}): {}",
+                user_display_name(&user),
+                tg_user_id,
+                format_user_limits(&user.limits)
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    tracing::info!(tg_user_id = tg_user_id, limits = ?limits, "Admin command /limits");
+
+    state.db.set_user_limits(tg_user_id, limits).await?;
+    // Лимиты применяются в telemt только для пользователей с активным доступом.
+    let active_user = user
+        .telemt_username
+        .as_deref()
+        .filter(|_| user.status == RequestStatus::Approved);
+    let mut changed_instances = Vec::new();
+    if let Some(telemt_user) = active_user {
+        let policy = UserPolicy {
+            limits,
+            expires_at: user.access_expires_at,
+        };
+        for instance in user_instances(&state, tg_user_id).await? {
+            if instance.telemt_cfg.set_user_policy(telemt_user, &policy).await? {
+                changed_instances.push(instance);
+            }
+        }
+    }
+    record_audit(
+        &state,
+        Some(admin_id),
+        AuditAction::UserLimits,
+        AuditTargetKind::User,
+        &tg_user_id.to_string(),
+        Some(&format_user_limits(&user.limits)),
+        Some(&format_user_limits(&limits)),
+    )
+    .await;
+    state
+        .restart_scheduler
+        .schedule(&changed_instances, "изменение лимитов", Some(admin_id));
+
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "✅ Лимиты {} обновлены: {}{}",
+            tg_user_id,
+            format_user_limits(&limits),
+            if active_user.is_some() {
+                ""
+            } else {
+                "\nКонфиг telemt не изменён: лимиты вступят в силу при выдаче доступа."
+            }
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn cmd_instances(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ManageUsers).await? else {
+        return Ok(());
+    };
+
     let text = msg.text().unwrap_or("");
-    let args: Vec<&str> = text.split_whitespace().collect();
-    let action = args.get(1).copied().unwrap_or("status");
-    tracing::info!(action = action, "Admin command /service");
-
-    let (action_name, result) = match action {
-        "start" => ("start", state.service.start()),
-        "stop" => ("stop", state.service.stop()),
-        "restart" => ("restart", state.service.restart()),
-        "reload" => ("reload", state.service.reload()),
-        "status" => ("status", state.service.status()),
-        _ => {
-            bot.send_message(
-                msg.chat.id,
-                "Использование: /service <start|stop|restart|reload|status>",
-            )
+    let mut args = text.split_whitespace().skip(1);
+    let tg_user_id = match parse_create_target(args.next().unwrap_or("")) {
+        Some(CreateTarget::UserId(id)) => id,
+        Some(CreateTarget::Username(username)) => {
+            match state.db.find_tg_user_id_by_username(&username).await? {
+                Some(user_id) => user_id,
+                None => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!("Пользователь @{} не найден в базе.", username),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => {
+            bot.send_message(msg.chat.id, instances_usage(&state)).await?;
+            return Ok(());
+        }
+    };
+    let Some(user) = state.db.get_request_by_tg_user(tg_user_id).await? else {
+        bot.send_message(msg.chat.id, "Пользователь не найден в базе.")
             .await?;
+        return Ok(());
+    };
+
+    let before = user_instances(&state, tg_user_id).await?;
+    let args: Vec<&str> = args.collect();
+    if args.is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "📡 Экземпляры {} ({}): {}",
+                user_display_name(&user),
+                tg_user_id,
+                format_instance_names(&before)
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // Пустое назначение означает «все экземпляры», в том числе добавленные позже.
+    let assigned: Vec<String> = if args == ["all"] {
+        Vec::new()
+    } else {
+        let mut names = Vec::with_capacity(args.len());
+        for name in args {
+            if state.instances.get(name).is_none() {
+                bot.send_message(
+                    msg.chat.id,
+                    format!("Экземпляр «{}» не найден.\n{}", name, instances_usage(&state)),
+                )
+                .await?;
+                return Ok(());
+            }
+            if !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    };
+    tracing::info!(tg_user_id = tg_user_id, instances = ?assigned, "Admin command /instances");
+
+    state.db.set_user_instances(tg_user_id, &assigned).await?;
+    let after = user_instances(&state, tg_user_id).await?;
+
+    // Конфиги меняются только для пользователей с активным доступом.
+    let mut changed_instances = Vec::new();
+    if user.status == RequestStatus::Approved
+        && let (Some(telemt_user), Some(secret)) = (user.telemt_username.as_deref(), user.secret.as_deref())
+    {
+        let policy = UserPolicy {
+            limits: user.limits,
+            expires_at: user.access_expires_at,
+        };
+        for instance in &after {
+            if !before.iter().any(|known| known.name == instance.name) {
+                instance.telemt_cfg.upsert_user(telemt_user, secret, &policy).await?;
+                changed_instances.push(instance.clone());
+            }
+        }
+        for instance in &before {
+            if !after.iter().any(|known| known.name == instance.name)
+                && instance.telemt_cfg.remove_user(telemt_user).await?
+            {
+                changed_instances.push(instance.clone());
+            }
+        }
+    }
+    record_audit(
+        &state,
+        Some(admin_id),
+        AuditAction::UserInstances,
+        AuditTargetKind::User,
+        &tg_user_id.to_string(),
+        Some(&format_instance_names(&before)),
+        Some(&format_instance_names(&after)),
+    )
+    .await;
+    state
+        .restart_scheduler
+        .schedule(&changed_instances, "назначение экземпляров", Some(admin_id));
+
+    if !changed_instances.is_empty()
+        && let Some(secret) = user.secret.as_deref()
+    {
+        let link = build_user_links(&state, tg_user_id, secret).await?;
+        if let Err(error) = bot
+            .send_message(
+                ChatId(tg_user_id),
+                format!("📡 Список серверов прокси изменён. Ваши ссылки:\n\n{}", link),
+            )
+            .await
+        {
+            tracing::warn!(
+                tg_user_id = tg_user_id,
+                error = %error,
+                "Не удалось отправить пользователю обновлённые ссылки"
+            );
+        }
+    }
+
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "✅ Экземпляры {} обновлены: {}",
+            tg_user_id,
+            format_instance_names(&after)
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn instances_usage(state: &BotState) -> String {
+    format!(
+        "Использование: /instances <tg_user_id | @username> [имя ...|all]\n\
+         Без имён показывает назначенные экземпляры, all — доступ ко всем.\n\
+         Экземпляры: {}",
+        state.instances.names().join(", ")
+    )
+}
+
+fn format_instance_names(instances: &[Arc<TelemtInstance>]) -> String {
+    instances
+        .iter()
+        .map(|instance| instance.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn cmd_service(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ManageService).await? else {
+        return Ok(());
+    };
+
+    let text = msg.text().unwrap_or("");
+    let mut args: Vec<&str> = text.split_whitespace().skip(1).collect();
+    // Первым аргументом можно указать экземпляр: /service <instance> <action>.
+    let instance = match args.first().and_then(|name| state.instances.get(name)) {
+        Some(instance) => {
+            args.remove(0);
+            instance
+        }
+        None => state.instances.primary(),
+    };
+    let action = args.first().copied().unwrap_or("status");
+    tracing::info!(instance = %instance.name, action = action, "Admin command /service");
+
+    let result = match action {
+        "start" => Some(("start", instance.start().await)),
+        "stop" => Some(("stop", instance.service.stop().await)),
+        "restart" => Some(("restart", instance.restart().await)),
+        "reload" => Some(("reload", instance.reload().await)),
+        "status" => None,
+        _ => {
+            bot.send_message(msg.chat.id, SERVICE_USAGE).await?;
             return Ok(());
         }
     };
 
-    let reply = state.service.format_result(action_name, &result);
+    if let Some((action_name, result)) = &result
+        && let Some(audit_action) = service_audit_action(action_name)
+    {
+        record_audit(
+            &state,
+            Some(admin_id),
+            audit_action,
+            AuditTargetKind::Service,
+            &instance.service_name,
+            None,
+            Some(if result.success { "ok" } else { "failed" }),
+        )
+        .await;
+    }
+
+    let reply = render_service_panel_text(
+        &state,
+        instance,
+        result.as_ref().map(|(name, result)| (*name, result)),
+    )
+    .await;
     bot.send_message(msg.chat.id, reply).await?;
     Ok(())
 }
 
 async fn cmd_token(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
-    if !is_admin_message(&msg, &state) {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::IssueTokens).await? else {
         return Ok(());
-    }
+    };
 
     let text = msg.text().unwrap_or("");
     let args: Vec<&str> = text.split_whitespace().collect();
     let Some(subcommand) = args.get(1).copied() else {
         bot.send_message(
             msg.chat.id,
-            "Использование:\n/token create [days] [--auto|-a] [--max-uses N]\n/token list\n/token revoke <token>",
+            "Использование:\n/token create [days] [--auto|-a] [--max-uses N] [--access-days N]\n/token list\n/token revoke <token>",
         )
         .await?;
         return Ok(());
@@ -366,6 +786,8 @@ async fn cmd_token(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
             let mut days: Option<i64> = None;
             let mut auto_approve = false;
             let mut max_uses: Option<i64> = None;
+            let mut access_days: Option<i64> = None;
+            let mut limits = UserLimits::default();
             let mut index = 2;
 
             while index < args.len() {
@@ -378,7 +800,7 @@ async fn cmd_token(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
                         let Some(value) = args.get(index + 1) else {
                             bot.send_message(
                                 msg.chat.id,
-                                "Использование: /token create [days] [--auto|-a] [--max-uses N]",
+                                TOKEN_CREATE_USAGE,
                             )
                             .await?;
                             return Ok(());
@@ -397,12 +819,57 @@ async fn cmd_token(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
                         max_uses = Some(parsed);
                         index += 2;
                     }
+                    "--access-days" => {
+                        let parsed = match args.get(index + 1).map(|value| value.parse::<i64>()) {
+                            Some(Ok(parsed)) if parsed >= 1 => parsed,
+                            _ => {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    "Параметр --access-days должен быть целым числом >= 1.",
+                                )
+                                .await?;
+                                return Ok(());
+                            }
+                        };
+                        access_days = Some(parsed);
+                        index += 2;
+                    }
+                    "--conns" | "--ips" => {
+                        let Some(parsed) = args.get(index + 1).and_then(|value| parse_limit_count(value))
+                        else {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Параметр {} должен быть целым числом >= 1.", args[index]),
+                            )
+                            .await?;
+                            return Ok(());
+                        };
+                        if args[index] == "--conns" {
+                            limits.max_tcp_conns = Some(parsed);
+                        } else {
+                            limits.max_unique_ips = Some(parsed);
+                        }
+                        index += 2;
+                    }
+                    "--quota" => {
+                        let Some(parsed) = args.get(index + 1).and_then(|value| parse_data_quota(value))
+                        else {
+                            bot.send_message(
+                                msg.chat.id,
+                                "Параметр --quota задаётся в байтах или с суффиксом K/M/G/T, например 10G.",
+                            )
+                            .await?;
+                            return Ok(());
+                        };
+                        limits.data_quota_bytes = Some(parsed);
+                        index += 2;
+                    }
                     value => {
                         if let Ok(parsed_days) = value.parse::<i64>() {
                             if days.is_some() {
                                 bot.send_message(
                                     msg.chat.id,
-                                    "Использование: /token create [days] [--auto|-a] [--max-uses N]",
+                                    TOKEN_CREATE_USAGE,
                                 )
                                 .await?;
                                 return Ok(());
@@ -413,7 +880,7 @@ async fn cmd_token(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
                         }
                         bot.send_message(
                             msg.chat.id,
-                            "Использование: /token create [days] [--auto|-a] [--max-uses N]",
+                            TOKEN_CREATE_USAGE,
                         )
                         .await?;
                         return Ok(());
@@ -421,86 +888,29 @@ async fn cmd_token(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
                 }
             }
 
-            let security = &state.config.security;
-            let days = days.unwrap_or(security.default_token_days);
-            if days < 1 {
-                bot.send_message(msg.chat.id, "Срок действия должен быть не меньше 1 дня.")
-                    .await?;
-                return Ok(());
-            }
-            if days > security.max_token_days {
-                bot.send_message(
-                    msg.chat.id,
-                    format!(
-                        "Нельзя создать токен на срок больше {} дней.",
-                        security.max_token_days
-                    ),
-                )
-                .await?;
-                return Ok(());
-            }
-            if auto_approve && !security.allow_auto_approve_tokens {
-                bot.send_message(
-                    msg.chat.id,
-                    "Автоподтверждение токенов запрещено в конфигурации.",
-                )
-                .await?;
-                return Ok(());
+            match create_invite_token_for_admin(
+                &state,
+                days,
+                auto_approve,
+                max_uses,
+                Some(admin_id),
+                access_days,
+                limits,
+            )
+            .await?
+            {
+                Ok(response) => {
+                    bot.send_message(msg.chat.id, response)
+                        .parse_mode(ParseMode::Html)
+                        .await?;
+                }
+                Err(reason) => {
+                    bot.send_message(msg.chat.id, reason).await?;
+                }
             }
-
-            let created_by = sender_user_id(&msg);
-            let token = state
-                .db
-                .create_invite_token(days, auto_approve, max_uses, created_by)
-                .await?;
-
-            let link_line = state
-                .bot_username
-                .as_deref()
-                .map(|bot_username| {
-                    let invite_link = build_bot_start_link(bot_username, &token.token);
-                    format!("Ссылка: {}\n", invite_link)
-                })
-                .unwrap_or_else(|| {
-                    "Ссылка: недоступна (у бота не задан username в Telegram).\n".to_string()
-                });
-
-            let response = format!(
-                "✅ Токен создан:\n\
-                 Код: <code>{}</code>\n\
-                 {}\
-                 Режим: {}\n\
-                 Действует до: {}\n\
-                 Лимит использований: {}\n\
-                 Используйте команду <code>/token revoke {}</code> для отзыва.",
-                token.token,
-                link_line,
-                format_mode(token.auto_approve),
-                format_date(token.expires_at),
-                token
-                    .max_usage
-                    .map(|value| value.to_string())
-                    .unwrap_or_else(|| "без лимита".to_string()),
-                token.token
-            );
-            bot.send_message(msg.chat.id, response)
-                .parse_mode(ParseMode::Html)
-                .await?;
         }
         "list" => {
-            let tokens = state.db.list_active_invite_tokens(50).await?;
-            if tokens.is_empty() {
-                bot.send_message(msg.chat.id, "Активных invite-токенов нет.")
-                    .await?;
-                return Ok(());
-            }
-
-            let mut lines: Vec<String> = Vec::with_capacity(tokens.len());
-            for token in tokens {
-                lines.push(render_invite_token_line(&token));
-            }
-            let text = format!("Активные токены:\n\n{}", lines.join("\n"));
-            bot.send_message(msg.chat.id, text).await?;
+            admin_show_tokens_page(&bot, msg.chat.id, &state, 1, None).await?;
         }
         "revoke" => {
             let Some(token_value) = args.get(2).copied() else {
@@ -508,7 +918,7 @@ async fn cmd_token(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
                     .await?;
                 return Ok(());
             };
-            let revoked = state.db.revoke_invite_token(token_value).await?;
+            let revoked = revoke_invite_token(&state, token_value, Some(admin_id)).await?;
             if revoked {
                 bot.send_message(msg.chat.id, format!("Токен {} отозван.", token_value))
                     .await?;
@@ -520,12 +930,305 @@ async fn cmd_token(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
         _ => {
             bot.send_message(
                 msg.chat.id,
-                "Использование:\n/token create [days] [--auto|-a] [--max-uses N]\n/token list\n/token revoke <token>",
+                "Использование:\n/token create [days] [--auto|-a] [--max-uses N] [--access-days N]\n/token list\n/token revoke <token>",
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn cmd_audit(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
+    if require_permission(&bot, &msg, &state, Permission::ViewAudit).await?.is_none() {
+        return Ok(());
+    }
+
+    let text = msg.text().unwrap_or("");
+    let mut filter = AuditFilter::All;
+    let mut page_size = AUDIT_DEFAULT_PAGE_SIZE;
+    for arg in text.split_whitespace().skip(1) {
+        if let Some(parsed) = AuditFilter::from_arg(arg) {
+            filter = parsed;
+        } else if let Ok(parsed) = arg.parse::<i64>()
+            && (1..=AUDIT_MAX_PAGE_SIZE).contains(&parsed)
+        {
+            page_size = parsed;
+        } else {
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Использование: /audit [user|token|admin] [N]\nN — от 1 до {}.",
+                    AUDIT_MAX_PAGE_SIZE
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+    tracing::info!(filter = filter.as_arg(), page_size = page_size, "Admin command /audit");
+
+    admin_show_audit_page(&bot, msg.chat.id, &state, filter, page_size, 1, None).await
+}
+
+async fn cmd_sync(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ManageService).await? else {
+        return Ok(());
+    };
+
+    let text = msg.text().unwrap_or("");
+    let apply = match text.split_whitespace().nth(1) {
+        None => false,
+        Some("apply") => true,
+        Some(_) => {
+            bot.send_message(msg.chat.id, "Использование: /sync [apply]")
+                .await?;
+            return Ok(());
+        }
+    };
+    tracing::info!(apply = apply, "Admin command /sync");
+
+    if apply {
+        let summary = apply_config_sync(&state, Some(admin_id)).await?;
+        bot.send_message(msg.chat.id, summary).await?;
+        return Ok(());
+    }
+
+    let reports = crate::sync::build_reports(&state.db, &state.instances).await?;
+    let mut request = bot.send_message(
+        msg.chat.id,
+        render_sync_reports(&reports, state.instances.is_multi()),
+    );
+    if reports.iter().any(|report| report.has_fixes()) {
+        request = request.reply_markup(crate::bot::keyboards::sync_confirm_keyboard());
+    }
+    request.await?;
+    Ok(())
+}
+
+async fn cmd_config(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ManageService).await? else {
+        return Ok(());
+    };
+
+    let text = msg.text().unwrap_or("");
+    let mut args: Vec<&str> = text.split_whitespace().skip(1).collect();
+    // Как и в /service, первым аргументом можно указать экземпляр.
+    let instance = match args.first().and_then(|name| state.instances.get(name)) {
+        Some(instance) => {
+            args.remove(0);
+            instance.clone()
+        }
+        None => state.instances.primary().clone(),
+    };
+    tracing::info!(instance = %instance.name, args = ?args, "Admin command /config");
+
+    match args.as_slice() {
+        ["backups"] => {
+            let reply = match instance.telemt_cfg.list_backups().await {
+                Ok(backups) => {
+                    render_config_backups(&instance.name, &instance.telemt_cfg.location(), &backups)
+                }
+                Err(error) => format!("❌ Не удалось получить список копий: {}", error),
+            };
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+        ["rollback", id] => {
+            if let Err(error) = instance.telemt_cfg.rollback(id).await {
+                tracing::warn!(instance = %instance.name, backup = %id, error = %error, "Config rollback failed");
+                bot.send_message(msg.chat.id, format!("❌ Не удалось откатить конфиг: {}", error))
+                    .await?;
+                return Ok(());
+            }
+            record_audit(
+                &state,
+                Some(admin_id),
+                AuditAction::ConfigRollback,
+                AuditTargetKind::Config,
+                &instance.telemt_cfg.location(),
+                None,
+                Some(id),
+            )
+            .await;
+            state.restart_scheduler.schedule(
+                std::slice::from_ref(&instance),
+                "откат конфига",
+                Some(admin_id),
+            );
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "↩️ Конфиг {} откатан к копии {}, telemt будет перезапущен.\n\
+                     Текущий конфиг сохранён отдельной копией. Проверьте расхождения с БД: /sync",
+                    instance.name, id
+                ),
             )
             .await?;
         }
+        _ => {
+            bot.send_message(msg.chat.id, CONFIG_USAGE).await?;
+        }
     }
+    Ok(())
+}
+
+async fn cmd_admin(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ManageAdmins).await? else {
+        return Ok(());
+    };
+
+    let text = msg.text().unwrap_or("");
+    let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+    tracing::info!(args = ?args, "Admin command /admin");
 
+    let (action, target, role) = match args.as_slice() {
+        [] | ["list"] => {
+            let grants = state.db.list_admin_grants().await?;
+            let admins: Vec<_> = state
+                .admin_user_ids()
+                .into_iter()
+                .map(|tg_user_id| {
+                    let user_grants = grants
+                        .iter()
+                        .filter(|grant| grant.tg_user_id == tg_user_id)
+                        .cloned()
+                        .collect();
+                    (tg_user_id, state.config().admin_access(tg_user_id), user_grants)
+                })
+                .collect();
+            bot.send_message(msg.chat.id, render_admin_list(&admins)).await?;
+            return Ok(());
+        }
+        ["add", target, role] => ("add", *target, Some(*role)),
+        ["remove", target] => ("remove", *target, None),
+        ["remove", target, role] => ("remove", *target, Some(*role)),
+        _ => {
+            bot.send_message(msg.chat.id, ADMIN_USAGE).await?;
+            return Ok(());
+        }
+    };
+    let role = match role.map(AdminRole::parse) {
+        None => None,
+        Some(Some(role)) => Some(role),
+        Some(None) => {
+            bot.send_message(msg.chat.id, ADMIN_USAGE).await?;
+            return Ok(());
+        }
+    };
+    let tg_user_id = match parse_create_target(target) {
+        Some(CreateTarget::UserId(id)) => id,
+        Some(CreateTarget::Username(username)) => {
+            match state.db.find_tg_user_id_by_username(&username).await? {
+                Some(user_id) => user_id,
+                None => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!(
+                            "Пользователь @{} не найден в базе.\n\
+                             Он должен хотя бы раз отправить боту /start, либо укажите его tg_user_id.",
+                            username
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => {
+            bot.send_message(msg.chat.id, ADMIN_USAGE).await?;
+            return Ok(());
+        }
+    };
+    if tg_user_id == admin_id {
+        bot.send_message(msg.chat.id, "Свои роли изменить нельзя: попросите другого владельца.")
+            .await?;
+        return Ok(());
+    }
+
+    let from_config = state.config().admin_access(tg_user_id);
+    let granted: Vec<AdminRole> = state
+        .db
+        .list_admin_grants()
+        .await?
+        .into_iter()
+        .filter(|grant| grant.tg_user_id == tg_user_id)
+        .map(|grant| grant.role)
+        .collect();
+    let question = match (action, role) {
+        ("add", Some(role)) => {
+            if from_config.roles().any(|existing| existing == role) || granted.contains(&role) {
+                bot.send_message(
+                    msg.chat.id,
+                    format!("У {} уже есть роль {}.", tg_user_id, role.label()),
+                )
+                .await?;
+                return Ok(());
+            }
+            format!("Выдать {} роль {}?", tg_user_id, role.label())
+        }
+        (_, Some(role)) => {
+            if !granted.contains(&role) {
+                let reason = if from_config.roles().any(|existing| existing == role) {
+                    "она задана в telemt-admin.toml и снимается только там"
+                } else {
+                    "она не была выдана"
+                };
+                bot.send_message(
+                    msg.chat.id,
+                    format!("Нельзя снять с {} роль {}: {}.", tg_user_id, role.label(), reason),
+                )
+                .await?;
+                return Ok(());
+            }
+            format!("Снять с {} роль {}?", tg_user_id, role.label())
+        }
+        (_, None) => {
+            if granted.is_empty() {
+                bot.send_message(
+                    msg.chat.id,
+                    format!("У {} нет ролей, выданных из бота.", tg_user_id),
+                )
+                .await?;
+                return Ok(());
+            }
+            let labels: Vec<&str> = granted.iter().map(|role| role.label()).collect();
+            format!("Снять с {} роли: {}?", tg_user_id, labels.join(", "))
+        }
+    };
+    bot.send_message(msg.chat.id, question)
+        .reply_markup(crate::bot::keyboards::admin_change_confirm_keyboard(
+            action,
+            tg_user_id,
+            role.map(AdminRole::as_str).unwrap_or("all"),
+        ))
+        .await?;
+    Ok(())
+}
+
+async fn cmd_reload_config(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
+    let Some(admin_id) = require_permission(&bot, &msg, &state, Permission::ReloadConfig).await? else {
+        return Ok(());
+    };
+    tracing::info!(admin_id = admin_id, "Admin command /reload_config");
+
+    let reply = match reload_bot_config(&state, Some(admin_id)).await {
+        Ok(changes) => render_config_reload(&changes),
+        Err(error) => {
+            tracing::warn!(error = %error, "Не удалось перечитать конфиг бота");
+            format!(
+                "❌ Конфиг бота не перечитан, действуют прежние настройки.\n{}",
+                error
+            )
+        }
+    };
+    // Роли могли измениться: меню показываем уже по новым правам.
+    let mut request = bot.send_message(msg.chat.id, reply);
+    let access = state.admin_access(admin_id);
+    if access.is_admin() {
+        reset_admin_menu(&state, msg.chat.id).await;
+        request = request.reply_markup(crate::bot::keyboards::admin_menu(&access));
+    }
+    request.await?;
     Ok(())
 }
 
@@ -541,6 +1244,10 @@ pub async fn admin_show_service_cmd(bot: &Bot, chat_id: ChatId, state: &BotState
     admin_show_service_panel(bot, chat_id, state).await
 }
 
+pub async fn admin_show_tokens_cmd(bot: &Bot, chat_id: ChatId, state: &BotState) -> HandlerResult {
+    admin_show_tokens_page(bot, chat_id, state, 1, None).await
+}
+
 pub async fn admin_show_stats_cmd(bot: &Bot, chat_id: ChatId, state: &BotState) -> HandlerResult {
     admin_show_stats(bot, chat_id, state).await
 }
@@ -551,7 +1258,7 @@ pub async fn try_process_waiting_invite(
     state: &BotState,
     user_id: i64,
 ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-    if !state.config.is_admin(user_id)
+    if !state.is_admin(user_id)
         && !msg.text().unwrap_or("").starts_with('/')
         && is_user_waiting_for_invite(state, user_id).await
     {
@@ -0,0 +1,96 @@
+//! Перечитывание telemt-admin.toml без перезапуска бота: по SIGHUP и команде
+//! `/reload_config`. Новый конфиг проверяется целиком и подменяет прежний одной
+//! операцией; настройки, которые используются только при запуске, вступают в силу
+//! после перезапуска бота.
+
+use super::format::render_config_reload;
+use super::shared::record_audit;
+use super::state::BotState;
+use crate::config::{Config, ConfigChanges};
+use crate::db::{AuditAction, AuditTargetKind};
+use crate::roles::Permission;
+use std::sync::Arc;
+use teloxide::prelude::*;
+
+/// Читает и проверяет конфиг бота и, если он корректен, заменяет текущий.
+pub async fn reload_bot_config(
+    state: &BotState,
+    actor: Option<i64>,
+) -> Result<ConfigChanges, anyhow::Error> {
+    let config = Config::load(&state.config_path)?;
+    if config.admin_ids.is_empty() {
+        return Err(anyhow::anyhow!(
+            "admin_ids пуст: у бота не останется владельцев"
+        ));
+    }
+    let changes = {
+        let mut current = state.config.write().unwrap_or_else(|e| e.into_inner());
+        let changes = config.changes_from(&current);
+        *current = Arc::new(config);
+        changes
+    };
+    tracing::info!(
+        path = %state.config_path.display(),
+        applied = ?changes.applied,
+        restart_required = ?changes.restart_required,
+        "Bot config reloaded"
+    );
+    let changed: Vec<&str> = changes
+        .applied
+        .iter()
+        .chain(&changes.restart_required)
+        .copied()
+        .collect();
+    record_audit(
+        state,
+        actor,
+        AuditAction::ConfigReload,
+        AuditTargetKind::Config,
+        &state.config_path.display().to_string(),
+        None,
+        Some(&changed.join(", ")),
+    )
+    .await;
+    Ok(changes)
+}
+
+/// Перечитывает конфиг по SIGHUP и сообщает итог владельцам.
+#[cfg(unix)]
+pub fn spawn_sighup_reloader(bot: Bot, state: BotState) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(error) => {
+                tracing::warn!(error = %error, "Не удалось подписаться на SIGHUP, перечитывание по сигналу отключено");
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            tracing::info!(path = %state.config_path.display(), "SIGHUP received, reloading bot config");
+            let text = match reload_bot_config(&state, None).await {
+                Ok(changes) => format!("SIGHUP: {}", render_config_reload(&changes)),
+                Err(error) => {
+                    tracing::warn!(error = %error, "Не удалось перечитать конфиг бота по SIGHUP");
+                    format!(
+                        "❌ SIGHUP: конфиг бота не перечитан, действуют прежние настройки.\n{}",
+                        error
+                    )
+                }
+            };
+            for admin_id in &state.admins_with(Permission::ReloadConfig) {
+                if let Err(error) = bot.send_message(ChatId(*admin_id), text.clone()).await {
+                    tracing::warn!(
+                        admin_id = *admin_id,
+                        error = %error,
+                        "Не удалось сообщить админу о перечитывании конфига"
+                    );
+                }
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_reloader(_bot: Bot, _state: BotState) {}
@@ -0,0 +1,265 @@
+//! Наблюдение за ручными правками конфигов telemt: сбрасывает кэш параметров
+//! ссылок и сообщает админам, какие пользователи `[access.users]` изменились.
+
+use super::state::BotState;
+use crate::instance::{ConfigStore, TelemtInstance};
+use crate::roles::Permission;
+use crate::telemt_cfg::{parse_users, ConfigWatcher, TelemtConfig};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use teloxide::prelude::*;
+
+/// Сколько имён показывать в каждой строке отчёта.
+const MAX_LISTED_USERS: usize = 20;
+
+/// Изменения `[access.users]` между двумя чтениями конфига.
+#[derive(Debug, Default)]
+struct UsersDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+impl UsersDiff {
+    fn between(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Self {
+        let mut diff = Self::default();
+        for (name, secret) in after {
+            match before.get(name) {
+                None => diff.added.push(name.clone()),
+                Some(previous) if previous != secret => diff.changed.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = before
+            .keys()
+            .filter(|name| !after.contains_key(*name))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub fn spawn_config_watcher(bot: Bot, state: BotState) {
+    // Конфиги удалённых экземпляров лежат на серверах агентов и здесь не наблюдаются.
+    let local: Vec<Arc<TelemtInstance>> = state
+        .instances
+        .all()
+        .iter()
+        .filter(|instance| matches!(instance.telemt_cfg, ConfigStore::Local(_)))
+        .cloned()
+        .collect();
+    if local.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let paths: Vec<PathBuf> = local
+            .iter()
+            .filter_map(|instance| local_config(instance).map(|config| config.path().to_path_buf()))
+            .collect();
+        let mut watcher = match ConfigWatcher::new(&paths) {
+            Ok(watcher) => watcher,
+            Err(error) => {
+                tracing::warn!(error = %error, "Наблюдение за конфигом telemt не запущено");
+                return;
+            }
+        };
+        let mut snapshots: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+        for instance in &local {
+            if let Some(config) = local_config(instance) {
+                let users = read_config(config).await.ok().and_then(|read| read.users.ok());
+                snapshots.insert(instance.name.clone(), users.unwrap_or_default());
+            }
+        }
+        tracing::info!(paths = ?paths, "telemt config watcher started");
+
+        while let Some(changed) = watcher.changed().await {
+            for instance in &local {
+                let Some(config) = local_config(instance) else {
+                    continue;
+                };
+                if !changed.contains(config.path()) {
+                    continue;
+                }
+                let snapshot = snapshots.entry(instance.name.clone()).or_default();
+                handle_change(&bot, &state, instance, config, snapshot).await;
+            }
+        }
+        tracing::warn!("Наблюдение за конфигом telemt остановлено");
+    });
+}
+
+fn local_config(instance: &TelemtInstance) -> Option<&Arc<TelemtConfig>> {
+    match &instance.telemt_cfg {
+        ConfigStore::Local(config) => Some(config),
+        ConfigStore::Remote(_) => None,
+    }
+}
+
+/// Конфиг telemt, прочитанный одним чтением файла.
+struct ConfigRead {
+    /// Текст записан самим ботом
+    own_write: bool,
+    users: Result<BTreeMap<String, String>, anyhow::Error>,
+}
+
+/// Читает файл один раз в пуле блокирующих задач: пользователи разбираются из
+/// того же текста, что сверяется с последней записью бота.
+async fn read_config(config: &Arc<TelemtConfig>) -> Result<ConfigRead, anyhow::Error> {
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        let content = std::fs::read_to_string(config.path())?;
+        Ok(ConfigRead {
+            own_write: config.is_own_write(&content),
+            users: parse_users(&content),
+        })
+    })
+    .await?
+}
+
+async fn handle_change(
+    bot: &Bot,
+    state: &BotState,
+    instance: &TelemtInstance,
+    config: &Arc<TelemtConfig>,
+    snapshot: &mut BTreeMap<String, String>,
+) {
+    config.invalidate_cache();
+    let read = match read_config(config).await {
+        Ok(read) => read,
+        Err(error) => {
+            // Файл мог быть удалён на время замены; следующее событие прочитает новый.
+            tracing::debug!(instance = %instance.name, error = %error, "telemt config is not readable yet");
+            return;
+        }
+    };
+    if read.own_write {
+        if let Ok(users) = read.users {
+            *snapshot = users;
+        }
+        return;
+    }
+
+    let (diff, read_error) = match read.users {
+        Ok(users) => {
+            let diff = UsersDiff::between(snapshot, &users);
+            *snapshot = users;
+            (diff, None)
+        }
+        Err(error) => (UsersDiff::default(), Some(error)),
+    };
+    let validation = instance.telemt_cfg.validate().await.err();
+    tracing::info!(
+        instance = %instance.name,
+        added = ?diff.added,
+        removed = ?diff.removed,
+        changed = ?diff.changed,
+        valid = validation.is_none(),
+        "telemt config edited outside the bot"
+    );
+    if diff.is_empty() && read_error.is_none() && validation.is_none() {
+        return;
+    }
+
+    let text = render_external_edit(state, instance, config, &diff, read_error, validation);
+    for admin_id in &state.admins_with(Permission::ManageService) {
+        if let Err(error) = bot.send_message(ChatId(*admin_id), text.clone()).await {
+            tracing::warn!(
+                admin_id = *admin_id,
+                error = %error,
+                "Не удалось сообщить админу о ручной правке конфига telemt"
+            );
+        }
+    }
+}
+
+fn render_external_edit(
+    state: &BotState,
+    instance: &TelemtInstance,
+    config: &TelemtConfig,
+    diff: &UsersDiff,
+    read_error: Option<anyhow::Error>,
+    validation: Option<anyhow::Error>,
+) -> String {
+    let target = if state.instances.is_multi() {
+        format!("telemt {} ({})", instance.name, config.path().display())
+    } else {
+        format!("telemt ({})", config.path().display())
+    };
+    let mut text = format!("✏️ Конфиг {} изменён вне бота.", target);
+    for (label, names) in [
+        ("➕ Добавлены", &diff.added),
+        ("➖ Удалены", &diff.removed),
+        ("🔑 Изменён секрет", &diff.changed),
+    ] {
+        if !names.is_empty() {
+            text.push_str(&format!("\n{}: {}", label, format_names(names)));
+        }
+    }
+    if let Some(error) = read_error {
+        text.push_str(&format!("\n⚠️ Не удалось прочитать [access.users]: {}", error));
+    }
+    if let Some(error) = validation {
+        text.push_str(&format!("\n\n⚠️ {}", error));
+    }
+    text.push_str("\n\nСверить с БД: /sync");
+    text
+}
+
+fn format_names(names: &[String]) -> String {
+    let mut listed = names
+        .iter()
+        .take(MAX_LISTED_USERS)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if names.len() > MAX_LISTED_USERS {
+        listed.push_str(&format!(" и ещё {}", names.len() - MAX_LISTED_USERS));
+    }
+    listed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, secret)| (name.to_string(), secret.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn users_diff_between_snapshots() {
+        let before = users(&[("alice", "a1"), ("bob", "b1"), ("carol", "c1")]);
+        let after = users(&[("alice", "a1"), ("bob", "b2"), ("dave", "d1"), ("erin", "e1")]);
+        let diff = UsersDiff::between(&before, &after);
+        assert_eq!(diff.added, ["dave", "erin"]);
+        assert_eq!(diff.removed, ["carol"]);
+        assert_eq!(diff.changed, ["bob"]);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn users_diff_of_same_snapshot_is_empty() {
+        let snapshot = users(&[("alice", "a1")]);
+        assert!(UsersDiff::between(&snapshot, &snapshot).is_empty());
+        assert!(UsersDiff::between(&BTreeMap::new(), &BTreeMap::new()).is_empty());
+        assert_eq!(UsersDiff::between(&BTreeMap::new(), &snapshot).added, ["alice"]);
+        assert_eq!(UsersDiff::between(&snapshot, &BTreeMap::new()).removed, ["alice"]);
+    }
+
+    #[test]
+    fn long_name_lists_are_cut() {
+        let names: Vec<String> = (0..MAX_LISTED_USERS + 3).map(|index| format!("u{}", index)).collect();
+        let listed = format_names(&names);
+        assert!(listed.starts_with("u0, u1, "));
+        assert!(listed.ends_with(" и ещё 3"));
+        assert_eq!(format_names(&names[..2]), "u0, u1");
+    }
+}
@@ -0,0 +1,164 @@
+//! Фоновая проверка срока доступа: предупреждения пользователям и отключение истёкших.
+
+use super::format::format_timestamp;
+use super::shared::{record_audit, remove_user_from_instances};
+use super::state::{telemt_username, BotState};
+use crate::db::{AuditAction, AuditTargetKind, RequestStatus};
+use crate::instance::TelemtInstance;
+use crate::roles::Permission;
+use std::sync::Arc;
+use std::time::Duration;
+use teloxide::prelude::*;
+use tokio::time::MissedTickBehavior;
+
+/// Минимальный период проверки, чтобы опечатка в конфиге не нагружала БД.
+const MIN_CHECK_INTERVAL_SECS: u64 = 30;
+
+pub fn spawn_expiry_watcher(bot: Bot, state: BotState) {
+    tokio::spawn(async move {
+        let period = state
+            .config()
+            .expiry
+            .check_interval_secs
+            .max(MIN_CHECK_INTERVAL_SECS);
+        let mut interval = tokio::time::interval(Duration::from_secs(period));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        tracing::info!(period_secs = period, "Access expiry watcher started");
+
+        loop {
+            interval.tick().await;
+            if let Err(error) = run_expiry_pass(&bot, &state).await {
+                tracing::warn!(error = %error, "Проверка срока доступа завершилась с ошибкой");
+            }
+        }
+    });
+}
+
+async fn run_expiry_pass(bot: &Bot, state: &BotState) -> Result<(), anyhow::Error> {
+    let now = chrono::Utc::now().timestamp();
+    warn_expiring_users(bot, state, now).await?;
+
+    let expired = state.db.list_expired_users(now).await?;
+    if expired.is_empty() {
+        return Ok(());
+    }
+
+    let mut removed_from: Vec<Arc<TelemtInstance>> = Vec::new();
+    let mut expired_ids: Vec<i64> = Vec::with_capacity(expired.len());
+    for user in expired {
+        let telemt_user = user
+            .telemt_username
+            .clone()
+            .unwrap_or_else(|| telemt_username(user.tg_user_id));
+        match remove_user_from_instances(state, &telemt_user).await {
+            Ok(instances) => {
+                for instance in instances {
+                    if !removed_from.iter().any(|known| known.name == instance.name) {
+                        removed_from.push(instance);
+                    }
+                }
+            }
+            Err(error) => {
+                // Оставляем пользователя approved: попробуем снова на следующем проходе.
+                tracing::warn!(
+                    tg_user_id = user.tg_user_id,
+                    error = %error,
+                    "Не удалось удалить пользователя с истёкшим доступом из конфига"
+                );
+                continue;
+            }
+        }
+        if !state.db.mark_expired(user.tg_user_id).await? {
+            continue;
+        }
+
+        record_audit(
+            state,
+            None,
+            AuditAction::UserExpire,
+            AuditTargetKind::User,
+            &user.tg_user_id.to_string(),
+            Some(&RequestStatus::Approved.to_string()),
+            Some(&RequestStatus::Expired.to_string()),
+        )
+        .await;
+        tracing::info!(tg_user_id = user.tg_user_id, "User access expired");
+        expired_ids.push(user.tg_user_id);
+
+        if let Err(error) = bot
+            .send_message(
+                ChatId(user.tg_user_id),
+                "⌛ Срок вашего доступа к прокси истёк.\n\
+                 Чтобы продолжить пользоваться прокси, получите новый пригласительный токен у администратора.",
+            )
+            .await
+        {
+            tracing::warn!(
+                tg_user_id = user.tg_user_id,
+                error = %error,
+                "Не удалось уведомить пользователя об истечении доступа"
+            );
+        }
+    }
+
+    state
+        .restart_scheduler
+        .schedule(&removed_from, "истечение доступа", None);
+
+    if !expired_ids.is_empty() {
+        let ids: Vec<String> = expired_ids.iter().map(|id| id.to_string()).collect();
+        let text = format!(
+            "⌛ Истёк доступ у пользователей: {}\n{}",
+            expired_ids.len(),
+            ids.join(", ")
+        );
+        for admin_id in &state.admins_with(Permission::ManageUsers) {
+            if let Err(error) = bot.send_message(ChatId(*admin_id), text.clone()).await {
+                tracing::warn!(
+                    admin_id = *admin_id,
+                    error = %error,
+                    "Не удалось уведомить админа об истечении доступа"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn warn_expiring_users(bot: &Bot, state: &BotState, now: i64) -> Result<(), anyhow::Error> {
+    let warn_days = state.config().expiry.warn_days;
+    if warn_days <= 0 {
+        return Ok(());
+    }
+    let deadline = now.saturating_add(warn_days.saturating_mul(86_400));
+
+    for user in state
+        .db
+        .list_users_to_warn_about_expiry(now, deadline)
+        .await?
+    {
+        let Some(expires_at) = user.access_expires_at else {
+            continue;
+        };
+        if let Err(error) = bot
+            .send_message(
+                ChatId(user.tg_user_id),
+                format!(
+                    "⏳ Ваш доступ к прокси истекает {}.\n\
+                     Для продления обратитесь к администратору.",
+                    format_timestamp(expires_at)
+                ),
+            )
+            .await
+        {
+            tracing::warn!(
+                tg_user_id = user.tg_user_id,
+                error = %error,
+                "Не удалось предупредить пользователя об окончании доступа"
+            );
+        }
+        // Помечаем даже при ошибке отправки, чтобы не повторять попытку каждый проход.
+        state.db.mark_expiry_warned(user.tg_user_id).await?;
+    }
+    Ok(())
+}
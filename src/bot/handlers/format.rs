@@ -1,4 +1,13 @@
-use crate::db::{InviteToken, RegistrationRequest};
+use crate::db::{
+    AdminGrant, AuditAction, AuditEntry, AuditTargetKind, InviteToken, InviteTokenUse,
+    RegistrationRequest, TokenStatus, UserLimits,
+};
+use crate::roles::{AdminAccess, AdminRole};
+use crate::config::ConfigChanges;
+use crate::link::UserLinks;
+use crate::service::{JournalEntry, LogPriority, LogWindow, ServiceStatus};
+use crate::sync::{ManagedUser, SyncReport};
+use crate::telemt_cfg::BackupInfo;
 use chrono::{DateTime, Local, Utc};
 
 pub fn format_date(ts: i64) -> String {
@@ -15,6 +24,44 @@ pub fn format_mode(auto_approve: bool) -> &'static str {
     }
 }
 
+pub fn format_access_days(access_days: Option<i64>) -> String {
+    access_days
+        .map(|days| format!("{} дн.", days))
+        .unwrap_or_else(|| "бессрочно".to_string())
+}
+
+/// Объём в байтах в человекочитаемом виде (двоичные единицы).
+pub fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 4] = ["КБ", "МБ", "ГБ", "ТБ"];
+    if bytes < 1024 {
+        return format!("{} Б", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+pub fn format_user_limits(limits: &UserLimits) -> String {
+    if limits.is_unlimited() {
+        return "без ограничений".to_string();
+    }
+    let mut parts = Vec::new();
+    if let Some(conns) = limits.max_tcp_conns {
+        parts.push(format!("соединений ≤ {}", conns));
+    }
+    if let Some(ips) = limits.max_unique_ips {
+        parts.push(format!("IP ≤ {}", ips));
+    }
+    if let Some(quota) = limits.data_quota_bytes {
+        parts.push(format!("трафик ≤ {}", format_bytes(quota)));
+    }
+    parts.join(", ")
+}
+
 pub fn format_timestamp(ts: i64) -> String {
     DateTime::<Utc>::from_timestamp(ts, 0)
         .map(|dt| {
@@ -25,6 +72,159 @@ pub fn format_timestamp(ts: i64) -> String {
         .unwrap_or_else(|| format!("Некорректный timestamp: {}", ts))
 }
 
+/// Короткая длительность: «2 д 3 ч», «4 ч 15 мин», «7 мин».
+pub fn format_duration_short(secs: i64) -> String {
+    let secs = secs.max(0);
+    let days = secs / 86_400;
+    let hours = secs % 86_400 / 3_600;
+    let minutes = secs % 3_600 / 60;
+    if days > 0 {
+        format!("{} д {} ч", days, hours)
+    } else if hours > 0 {
+        format!("{} ч {} мин", hours, minutes)
+    } else if minutes > 0 {
+        format!("{} мин", minutes)
+    } else {
+        "меньше минуты".to_string()
+    }
+}
+
+pub fn render_service_status(status: &ServiceStatus, now: i64) -> String {
+    let icon = match status.active_state.as_str() {
+        "active" => "🟢",
+        "activating" | "deactivating" | "reloading" => "🟡",
+        "failed" => "🔴",
+        _ => "⚪",
+    };
+    let mut text = format!("{} {} ({})", icon, status.active_state, status.sub_state);
+    if status.load_state != "loaded" && !status.load_state.is_empty() {
+        text.push_str(&format!("\nUnit: {}", status.load_state));
+    }
+    if let Some(pid) = status.main_pid {
+        text.push_str(&format!("\nPID: {}", pid));
+    }
+    if let Some(bytes) = status.memory_bytes {
+        text.push_str(&format!(
+            "\nПамять: {:.1} МБ",
+            bytes as f64 / (1024.0 * 1024.0)
+        ));
+    }
+    if status.is_active()
+        && let Some(since) = status.active_since
+    {
+        text.push_str(&format!(
+            "\nРаботает: {} (с {})",
+            format_duration_short(now - since),
+            format_timestamp(since)
+        ));
+    }
+    if let Some(code) = status.last_exit_code.filter(|code| *code != 0) {
+        text.push_str(&format!("\nПоследний код выхода: {}", code));
+    }
+    text
+}
+
+pub fn format_log_priority(priority: LogPriority) -> &'static str {
+    match priority {
+        LogPriority::All => "все",
+        LogPriority::Warning => "warning и выше",
+        LogPriority::Error => "error и выше",
+    }
+}
+
+pub fn format_log_window(window: LogWindow) -> &'static str {
+    match window {
+        LogWindow::Minutes15 => "15 минут",
+        LogWindow::Hour => "1 час",
+        LogWindow::Day => "24 часа",
+        LogWindow::All => "всё время",
+    }
+}
+
+fn format_journal_time(entry: &JournalEntry, pattern: &str) -> String {
+    entry
+        .timestamp
+        .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+        .map(|dt| dt.with_timezone(&Local).format(pattern).to_string())
+        .unwrap_or_else(|| "--".to_string())
+}
+
+/// Максимальная длина текста записи журнала в сообщении после экранирования.
+const JOURNAL_MESSAGE_MAX_CHARS: usize = 1_000;
+
+/// Экранирует текст для HTML, обрезая его до экранирования так, чтобы результат
+/// уместился в `max_chars` символов: сущности вроде `&amp;` не разрезаются.
+fn escape_html_truncated(text: &str, max_chars: usize) -> String {
+    let escaped = teloxide::utils::html::escape(text);
+    if escaped.chars().count() <= max_chars {
+        return escaped;
+    }
+    let budget = max_chars.saturating_sub(1);
+    let mut len = 0;
+    let mut end = 0;
+    for (index, ch) in text.char_indices() {
+        let next = index + ch.len_utf8();
+        len += teloxide::utils::html::escape(&text[index..next]).chars().count();
+        if len > budget {
+            break;
+        }
+        end = next;
+    }
+    format!("{}…", teloxide::utils::html::escape(&text[..end]))
+}
+
+/// Строка журнала для сообщения (HTML): ошибки выделены жирным и значком.
+pub fn render_journal_line_html(entry: &JournalEntry) -> String {
+    let time = format_journal_time(entry, "%H:%M:%S");
+    let message = escape_html_truncated(&entry.message, JOURNAL_MESSAGE_MAX_CHARS);
+    if entry.is_error() {
+        format!("<code>{}</code> ❌ <b>{}</b>", time, message)
+    } else if entry.is_warning() {
+        format!("<code>{}</code> ⚠️ {}", time, message)
+    } else {
+        format!("<code>{}</code> {}", time, message)
+    }
+}
+
+/// Строка журнала для файла .log.
+pub fn render_journal_line_plain(entry: &JournalEntry) -> String {
+    let level = match entry.priority {
+        Some(0..=3) => "ERR ",
+        Some(4) => "WARN",
+        Some(5..=6) => "INFO",
+        Some(_) => "DBG ",
+        None => "    ",
+    };
+    format!(
+        "{} {} {}",
+        format_journal_time(entry, "%Y-%m-%d %H:%M:%S"),
+        level,
+        entry.message
+    )
+}
+
+/// Разбивает строки на сообщения не длиннее `limit` символов, не разрывая строки.
+///
+/// Строки не обрезаются: в них может быть HTML-разметка, поэтому длину каждой строки
+/// ограничивает тот, кто её формирует.
+pub fn split_into_messages(header: &str, lines: &[String], limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = header.to_string();
+    for line in lines {
+        if !current.is_empty() && current.chars().count() + line.chars().count() + 1 > limit {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
 pub fn user_display_name(user: &RegistrationRequest) -> String {
     user.tg_display_name
         .clone()
@@ -37,8 +237,27 @@ pub fn user_display_name(user: &RegistrationRequest) -> String {
         .unwrap_or_else(|| format!("tg_{}", user.tg_user_id))
 }
 
-pub fn render_invite_token_line(token: &InviteToken) -> String {
+pub fn format_token_status(status: TokenStatus) -> &'static str {
+    match status {
+        TokenStatus::Active => "🟢 активен",
+        TokenStatus::Expired => "⌛ истёк",
+        TokenStatus::Exhausted => "📉 исчерпан",
+        TokenStatus::Revoked => "🚫 отозван",
+    }
+}
+
+pub fn render_invite_token_button_title(token: &InviteToken, now: i64) -> String {
+    let marker = match token.status_at(now) {
+        TokenStatus::Active => "🟢",
+        TokenStatus::Expired => "⌛",
+        TokenStatus::Exhausted => "📉",
+        TokenStatus::Revoked => "🚫",
+    };
     let mode = if token.auto_approve { "AUTO" } else { "MANUAL" };
+    format!("{} {} · {} · до {}", marker, token.token, mode, format_date(token.expires_at))
+}
+
+pub fn render_invite_token_card(token: &InviteToken, uses: &[InviteTokenUse], now: i64) -> String {
     let usage = token
         .max_usage
         .map(|max| format!("{}/{}", token.usage_count, max))
@@ -47,15 +266,263 @@ pub fn render_invite_token_line(token: &InviteToken) -> String {
         .created_by
         .map(|v| v.to_string())
         .unwrap_or_else(|| "—".to_string());
-    format!(
-        "• {} | {} | до {} | usage {} | creator {} | создан {}",
+
+    let mut text = format!(
+        "🔑 Токен {}\n\n\
+         Статус: {}\n\
+         Режим: {}\n\
+         Создан: {}\n\
+         Действует до: {}\n\
+         Использований: {}\n\
+         Срок доступа: {}\n\
+         Лимиты: {}\n\
+         Создатель: {}",
         token.token,
-        mode,
-        format_date(token.expires_at),
+        format_token_status(token.status_at(now)),
+        format_mode(token.auto_approve),
+        format_timestamp(token.created_at),
+        format_timestamp(token.expires_at),
         usage,
+        format_access_days(token.access_days),
+        format_user_limits(&token.limits),
         created_by,
-        format_date(token.created_at)
-    )
+    );
+    if let Some(revoked_at) = token.revoked_at {
+        text.push_str(&format!("\nОтозван: {}", format_timestamp(revoked_at)));
+    }
+
+    if uses.is_empty() {
+        text.push_str("\n\nТокен ещё никто не использовал.");
+    } else {
+        text.push_str("\n\nИспользовали:");
+        for usage in uses {
+            let name = usage
+                .tg_display_name
+                .clone()
+                .or_else(|| usage.tg_username.as_ref().map(|u| format!("@{}", u)))
+                .unwrap_or_else(|| "—".to_string());
+            let status = usage
+                .status
+                .map(|status| status.to_string())
+                .unwrap_or_else(|| "—".to_string());
+            text.push_str(&format!(
+                "\n• {} (id {}) — {} [{}]",
+                name,
+                usage.tg_user_id,
+                format_date(usage.used_at),
+                status
+            ));
+        }
+    }
+    text
+}
+
+pub fn format_audit_action(action: AuditAction) -> &'static str {
+    match action {
+        AuditAction::RequestApprove => "✅ одобрение заявки",
+        AuditAction::RequestReject => "❌ отклонение заявки",
+        AuditAction::UserCreate => "➕ создание пользователя",
+        AuditAction::UserAutoApprove => "🚀 автоподключение",
+        AuditAction::UserBan => "⛔ бан",
+        AuditAction::UserExpire => "⌛ истечение доступа",
+        AuditAction::UserLimits => "🚦 изменение лимитов",
+        AuditAction::UserRotateSecret => "♻️ перевыпуск ссылки",
+        AuditAction::UserInstances => "📡 назначение экземпляров",
+        AuditAction::TokenCreate => "🔑 создание токена",
+        AuditAction::TokenRevoke => "🚫 отзыв токена",
+        AuditAction::ServiceStart => "▶️ запуск сервиса",
+        AuditAction::ServiceStop => "⏹ остановка сервиса",
+        AuditAction::ServiceRestart => "♻️ рестарт сервиса",
+        AuditAction::ServiceReload => "🔄 reload сервиса",
+        AuditAction::ConfigSync => "🔁 синхронизация конфига",
+        AuditAction::ConfigRollback => "↩️ откат конфига",
+        AuditAction::ConfigReload => "🔄 перечитывание конфига бота",
+        AuditAction::AdminAdd => "🛡 выдача роли",
+        AuditAction::AdminRemove => "🛡 снятие роли",
+    }
+}
+
+pub fn render_audit_entry(entry: &AuditEntry) -> String {
+    let actor = entry
+        .actor_tg_id
+        .map(|id| format!("админ {}", id))
+        .unwrap_or_else(|| "система".to_string());
+    let target = match entry.target_kind {
+        AuditTargetKind::Request => format!("заявка #{}", entry.target),
+        AuditTargetKind::User => format!("пользователь {}", entry.target),
+        AuditTargetKind::Token => format!("токен {}", entry.target),
+        AuditTargetKind::Service => format!("сервис {}", entry.target),
+        AuditTargetKind::Config => format!("конфиг {}", entry.target),
+        AuditTargetKind::Admin => format!("админ {}", entry.target),
+    };
+    let mut line = format!(
+        "#{} {} · {} · {} · {}",
+        entry.id,
+        format_timestamp(entry.created_at),
+        actor,
+        format_audit_action(entry.action),
+        target
+    );
+    match (entry.status_before.as_deref(), entry.status_after.as_deref()) {
+        (Some(before), Some(after)) => line.push_str(&format!(" · {} → {}", before, after)),
+        (None, Some(after)) => line.push_str(&format!(" · {}", after)),
+        (Some(before), None) => line.push_str(&format!(" · было {}", before)),
+        (None, None) => {}
+    }
+    line
+}
+
+/// Сколько имён показывать в каждом разделе отчёта сверки.
+const SYNC_REPORT_MAX_ITEMS: usize = 20;
+
+fn push_sync_section(text: &mut String, title: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    text.push_str(&format!("\n\n{} ({}):", title, items.len()));
+    for item in items.iter().take(SYNC_REPORT_MAX_ITEMS) {
+        text.push_str(&format!("\n• {}", item));
+    }
+    if items.len() > SYNC_REPORT_MAX_ITEMS {
+        text.push_str(&format!("\n… и ещё {}", items.len() - SYNC_REPORT_MAX_ITEMS));
+    }
+}
+
+pub fn render_sync_report(report: &SyncReport) -> String {
+    if report.is_clean() {
+        let mut text = "✅ БД и конфиг telemt синхронизированы.".to_string();
+        push_sync_section(
+            &mut text,
+            "ℹ️ Записи конфига вне управления бота",
+            &report.unmanaged_in_config,
+        );
+        return text;
+    }
+
+    let names = |items: &[ManagedUser]| -> Vec<String> {
+        items.iter().map(|user| user.name.clone()).collect()
+    };
+    let mut text = "🔁 Расхождения БД и конфига telemt:".to_string();
+    push_sync_section(
+        &mut text,
+        "🗑 Лишние в конфиге (будут удалены)",
+        &report.orphans_in_config,
+    );
+    push_sync_section(
+        &mut text,
+        "➕ Нет в конфиге (будут добавлены)",
+        &names(&report.missing_in_config),
+    );
+    push_sync_section(
+        &mut text,
+        "🔑 Секрет отличается (будет взят из БД)",
+        &names(&report.secret_mismatches),
+    );
+    push_sync_section(
+        &mut text,
+        "🚦 Лимиты или срок отличаются (будут взяты из БД)",
+        &names(&report.policy_mismatches),
+    );
+    let without_secret: Vec<String> = report
+        .approved_without_secret
+        .iter()
+        .map(|id| format!("tg_{}", id))
+        .collect();
+    push_sync_section(
+        &mut text,
+        "⚠️ Одобрены без секрета в БД (нужно исправить вручную)",
+        &without_secret,
+    );
+    push_sync_section(
+        &mut text,
+        "ℹ️ Записи конфига вне управления бота (не изменяются)",
+        &report.unmanaged_in_config,
+    );
+    text
+}
+
+/// Отчёты сверки по экземплярам; при нескольких экземплярах каждый подписан.
+pub fn render_sync_reports(reports: &[SyncReport], labeled: bool) -> String {
+    reports
+        .iter()
+        .map(|report| {
+            if labeled {
+                format!("📡 {}\n{}", report.instance, render_sync_report(report))
+            } else {
+                render_sync_report(report)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Список резервных копий конфига экземпляра, от новых к старым.
+pub fn render_config_backups(instance: &str, location: &str, backups: &[BackupInfo]) -> String {
+    let mut text = format!("🗂 Копии конфига {} ({})", instance, location);
+    if backups.is_empty() {
+        text.push_str("\n\nКопий пока нет.");
+        return text;
+    }
+    text.push('\n');
+    for backup in backups {
+        text.push_str(&format!(
+            "\n• {} — {}, {}",
+            backup.id,
+            format_timestamp(backup.created_at),
+            format_bytes(backup.size as i64)
+        ));
+    }
+    text.push_str("\n\nОткат: /config rollback <id>");
+    text
+}
+
+/// Итог перечитывания конфига бота.
+pub fn render_config_reload(changes: &ConfigChanges) -> String {
+    let mut text = "🔄 Конфиг бота перечитан.".to_string();
+    if changes.is_empty() {
+        text.push_str("\nИзменений нет.");
+        return text;
+    }
+    if !changes.applied.is_empty() {
+        text.push_str(&format!("\n✅ Применено: {}", changes.applied.join(", ")));
+    }
+    if !changes.restart_required.is_empty() {
+        text.push_str(&format!(
+            "\n⚠️ Вступит в силу после перезапуска бота: {}",
+            changes.restart_required.join(", ")
+        ));
+    }
+    text
+}
+
+/// Список администраторов: роли из конфига и выданные из бота для каждого ID.
+pub fn render_admin_list(admins: &[(i64, AdminAccess, Vec<AdminGrant>)]) -> String {
+    let mut text = "🛡 Администраторы:".to_string();
+    for (tg_user_id, from_config, grants) in admins {
+        text.push_str(&format!("\n\n• {}", tg_user_id));
+        let config_roles: Vec<&str> = from_config.roles().map(AdminRole::label).collect();
+        if !config_roles.is_empty() {
+            text.push_str(&format!("\n  конфиг: {}", config_roles.join(", ")));
+        }
+        for grant in grants {
+            let added_by = grant
+                .added_by
+                .map(|id| format!(", выдал {}", id))
+                .unwrap_or_default();
+            text.push_str(&format!(
+                "\n  {} — с {}{}",
+                grant.role.label(),
+                format_timestamp(grant.added_at),
+                added_by
+            ));
+        }
+    }
+    text.push_str(
+        "\n\nРоли из конфига меняются только в telemt-admin.toml.\n\
+         Выдать: /admin add <tg_user_id | @username> <роль>\n\
+         Снять: /admin remove <tg_user_id | @username> [роль]",
+    );
+    text
 }
 
 pub fn render_user_card_text(user: &RegistrationRequest) -> String {
@@ -65,6 +532,10 @@ pub fn render_user_card_text(user: &RegistrationRequest) -> String {
         .map(|u| format!("@{}", u))
         .unwrap_or_else(|| "—".to_string());
     let telemt = user.telemt_username.as_deref().unwrap_or("—");
+    let access = user
+        .access_expires_at
+        .map(|ts| format!("до {}", format_timestamp(ts)))
+        .unwrap_or_else(|| "бессрочно".to_string());
 
     format!(
         "👤 {}\n\n\
@@ -72,17 +543,21 @@ pub fn render_user_card_text(user: &RegistrationRequest) -> String {
          📱 {}\n\
          📋 {}\n\
          🔗 {}\n\
-         📅 {}",
+         📅 {}\n\
+         ⏳ {}\n\
+         🚦 {}",
         user_display_name(user),
         user.tg_user_id,
         username,
         user.status,
         telemt,
         format_timestamp(user.created_at),
+        access,
+        format_user_limits(&user.limits),
     )
 }
 
-pub fn render_user_proxy_for_forward(user: &RegistrationRequest, link: &str) -> String {
+pub fn render_user_proxy_for_forward(user: &RegistrationRequest, link: &UserLinks) -> String {
     format!(
         "👤 {} ({})\n\n🔗 {}",
         user_display_name(user),
@@ -100,3 +575,61 @@ pub fn usage_guide_text() -> &'static str {
 
 Если не получается, обратитесь к администратору."#
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_entry(message: String) -> JournalEntry {
+        JournalEntry {
+            timestamp: Some(1_700_000_000),
+            priority: Some(3),
+            message,
+        }
+    }
+
+    /// Все сущности целые, а теги закрыты: Telegram примет разметку.
+    fn assert_valid_html(text: &str) {
+        assert_eq!(text.matches("<b>").count(), text.matches("</b>").count(), "{}", text);
+        assert_eq!(text.matches("<code>").count(), text.matches("</code>").count(), "{}", text);
+        for (index, _) in text.match_indices('&') {
+            let rest = &text[index..];
+            assert!(
+                ["&amp;", "&lt;", "&gt;", "&quot;"].iter().any(|entity| rest.starts_with(entity)),
+                "разрезанная сущность: {}",
+                &rest[..rest.len().min(8)]
+            );
+        }
+    }
+
+    #[test]
+    fn long_journal_line_is_truncated_before_escaping() {
+        let message = "a<b>&c ".repeat(1_000);
+        let line = render_journal_line_html(&error_entry(message));
+        assert_valid_html(&line);
+        assert!(line.ends_with("…</b>"), "{}", line);
+        assert!(line.contains("&lt;b&gt;&amp;c"));
+        assert!(line.chars().count() < JOURNAL_MESSAGE_MAX_CHARS + 40);
+    }
+
+    #[test]
+    fn short_journal_line_is_kept_whole() {
+        let line = render_journal_line_html(&error_entry("bind <0.0.0.0:443> & retry".to_string()));
+        assert!(line.ends_with("❌ <b>bind &lt;0.0.0.0:443&gt; &amp; retry</b>"), "{}", line);
+    }
+
+    #[test]
+    fn split_keeps_markup_of_long_lines() {
+        let limit = 3_500;
+        let lines: Vec<String> = (0..20)
+            .map(|_| render_journal_line_html(&error_entry("&<".repeat(2_000))))
+            .collect();
+        let chunks = split_into_messages("header\n", &lines, limit);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= limit);
+            assert_valid_html(chunk);
+        }
+        assert_eq!(chunks.iter().map(|chunk| chunk.matches("<b>").count()).sum::<usize>(), lines.len());
+    }
+}
@@ -1,10 +1,15 @@
 use super::commands::{
-    admin_show_pending_cmd, admin_show_service_cmd, admin_show_stats_cmd, admin_show_users_cmd,
-    cmd_help, try_process_waiting_invite,
+    admin_show_pending_cmd, admin_show_service_cmd, admin_show_stats_cmd, admin_show_tokens_cmd,
+    admin_show_users_cmd, cmd_help, try_process_waiting_invite,
 };
 use super::format::usage_guide_text;
-use super::shared::{send_user_link, HandlerResult};
+use super::navigation::{
+    admin_menu_back, current_admin_menu, open_admin_submenu, start_token_wizard,
+    try_process_token_wizard, AdminMenu,
+};
+use super::shared::{send_user_link, user_request_self_rotate, HandlerResult};
 use super::state::{sender_user_id, BotState};
+use crate::roles::Permission;
 use teloxide::prelude::*;
 
 pub async fn handle_menu_buttons(bot: Bot, msg: Message, state: BotState) -> HandlerResult {
@@ -14,34 +19,57 @@ pub async fn handle_menu_buttons(bot: Bot, msg: Message, state: BotState) -> Han
     let Some(user_id) = sender_user_id(&msg) else {
         return Ok(());
     };
-    let is_admin = state.config.is_admin(user_id);
+    let access = state.admin_access(user_id);
+    let is_admin = access.is_admin();
+    let can = |permission: Permission| access.allows(permission);
 
     if try_process_waiting_invite(&bot, &msg, &state, user_id).await? {
         return Ok(());
     }
+    if is_admin && try_process_token_wizard(&bot, &msg, &state, user_id).await? {
+        return Ok(());
+    }
 
     match text {
         crate::bot::keyboards::BTN_USER_LINK => {
             send_user_link(&bot, msg.chat.id, user_id, &state).await?;
         }
+        crate::bot::keyboards::BTN_USER_ROTATE => {
+            user_request_self_rotate(&bot, msg.chat.id, user_id, &state).await?;
+        }
         crate::bot::keyboards::BTN_USER_GUIDE => {
             bot.send_message(msg.chat.id, usage_guide_text())
                 .reply_markup(crate::bot::keyboards::user_menu())
                 .await?;
         }
-        crate::bot::keyboards::BTN_ADMIN_PENDING if is_admin => {
+        crate::bot::keyboards::BTN_ADMIN_REQUESTS if can(Permission::ModerateRequests) => {
+            open_admin_submenu(&bot, msg.chat.id, &state, AdminMenu::Requests).await?;
+        }
+        crate::bot::keyboards::BTN_ADMIN_TOKENS if can(Permission::IssueTokens) => {
+            open_admin_submenu(&bot, msg.chat.id, &state, AdminMenu::Tokens).await?;
+        }
+        crate::bot::keyboards::BTN_ADMIN_TOKEN_CREATE if can(Permission::IssueTokens) => {
+            start_token_wizard(&bot, msg.chat.id, &state).await?;
+        }
+        crate::bot::keyboards::BTN_BACK if is_admin => {
+            admin_menu_back(&bot, msg.chat.id, &state).await?;
+        }
+        crate::bot::keyboards::BTN_ADMIN_PENDING if can(Permission::ModerateRequests) => {
             admin_show_pending_cmd(&bot, msg.chat.id, &state).await?;
         }
-        crate::bot::keyboards::BTN_ADMIN_USERS if is_admin => {
+        crate::bot::keyboards::BTN_ADMIN_USERS if can(Permission::ViewUsers) => {
             admin_show_users_cmd(&bot, msg.chat.id, &state).await?;
         }
-        crate::bot::keyboards::BTN_ADMIN_SERVICE if is_admin => {
+        crate::bot::keyboards::BTN_ADMIN_TOKEN_LIST if can(Permission::IssueTokens) => {
+            admin_show_tokens_cmd(&bot, msg.chat.id, &state).await?;
+        }
+        crate::bot::keyboards::BTN_ADMIN_SERVICE if can(Permission::ManageService) => {
             admin_show_service_cmd(&bot, msg.chat.id, &state).await?;
         }
-        crate::bot::keyboards::BTN_ADMIN_STATS if is_admin => {
+        crate::bot::keyboards::BTN_ADMIN_STATS if can(Permission::ViewUsers) => {
             admin_show_stats_cmd(&bot, msg.chat.id, &state).await?;
         }
-        crate::bot::keyboards::BTN_ADMIN_CREATE_HINT if is_admin => {
+        crate::bot::keyboards::BTN_ADMIN_CREATE_HINT if can(Permission::ManageUsers) => {
             bot.send_message(
                 msg.chat.id,
                 "Создание пользователя:\n\
@@ -49,7 +77,7 @@ pub async fn handle_menu_buttons(bot: Bot, msg: Message, state: BotState) -> Han
                  /create @username\n\n\
                  Для варианта с @username пользователь должен ранее отправить боту /start.",
             )
-            .reply_markup(crate::bot::keyboards::admin_menu())
+            .reply_markup(crate::bot::keyboards::admin_menu(&access))
             .await?;
         }
         crate::bot::keyboards::BTN_ADMIN_HELP if is_admin => {
@@ -62,7 +90,7 @@ pub async fn handle_menu_buttons(bot: Bot, msg: Message, state: BotState) -> Han
                 "Не понял запрос. Используйте кнопки меню ниже."
             };
             let reply_markup = if is_admin {
-                crate::bot::keyboards::admin_menu()
+                current_admin_menu(&state, msg.chat.id).await.keyboard(&access)
             } else {
                 crate::bot::keyboards::user_menu()
             };
@@ -0,0 +1,330 @@
+//! Навигация по постоянному админ-меню: стек подменю на чат и мастер создания токена.
+
+use super::shared::{create_invite_token_for_admin, HandlerResult};
+use super::state::BotState;
+use crate::bot::keyboards;
+use crate::db::UserLimits;
+use crate::roles::{AdminAccess, Permission};
+use teloxide::prelude::*;
+use teloxide::types::{KeyboardMarkup, ParseMode};
+
+/// Экран постоянного reply-меню администратора.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminMenu {
+    Main,
+    Requests,
+    Tokens,
+}
+
+impl AdminMenu {
+    pub fn keyboard(self, access: &AdminAccess) -> KeyboardMarkup {
+        match self {
+            Self::Main => keyboards::admin_menu(access),
+            Self::Requests => keyboards::admin_requests_menu(),
+            Self::Tokens => keyboards::admin_tokens_menu(),
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Main => "Главное меню администратора.",
+            Self::Requests => "📋 Управление заявками.\nНовые заявки можно одобрить или отклонить кнопками под ними.",
+            Self::Tokens => "🔑 Управление токенами.\nСоздайте новый токен или откройте список существующих.",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenWizardStep {
+    Days,
+    Mode,
+    MaxUses,
+    AccessDays,
+}
+
+/// Черновик токена, который админ заполняет по шагам.
+#[derive(Debug, Clone)]
+struct TokenWizard {
+    step: TokenWizardStep,
+    days: i64,
+    auto_approve: bool,
+    max_uses: Option<i64>,
+}
+
+/// Состояние навигации одного админского чата.
+#[derive(Debug, Clone, Default)]
+pub struct AdminNavigation {
+    stack: Vec<AdminMenu>,
+    token_wizard: Option<TokenWizard>,
+}
+
+impl AdminNavigation {
+    fn current(&self) -> AdminMenu {
+        self.stack.last().copied().unwrap_or(AdminMenu::Main)
+    }
+}
+
+pub async fn current_admin_menu(state: &BotState, chat_id: ChatId) -> AdminMenu {
+    state
+        .admin_navigation
+        .lock()
+        .await
+        .get(&chat_id.0)
+        .map(AdminNavigation::current)
+        .unwrap_or(AdminMenu::Main)
+}
+
+/// Сбрасывает стек меню и незавершённый мастер (например, после /start).
+pub async fn reset_admin_menu(state: &BotState, chat_id: ChatId) {
+    state.admin_navigation.lock().await.remove(&chat_id.0);
+}
+
+pub async fn open_admin_submenu(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    menu: AdminMenu,
+) -> HandlerResult {
+    {
+        let mut navigation = state.admin_navigation.lock().await;
+        let entry = navigation.entry(chat_id.0).or_default();
+        entry.token_wizard = None;
+        if entry.current() != menu {
+            entry.stack.push(menu);
+        }
+    }
+    bot.send_message(chat_id, menu.title())
+        .reply_markup(menu.keyboard(&state.admin_access(chat_id.0)))
+        .await?;
+    Ok(())
+}
+
+/// Кнопка «Назад»: отменяет мастер, если он открыт, иначе поднимается на уровень выше.
+pub async fn admin_menu_back(bot: &Bot, chat_id: ChatId, state: &BotState) -> HandlerResult {
+    let (menu, wizard_cancelled) = {
+        let mut navigation = state.admin_navigation.lock().await;
+        let entry = navigation.entry(chat_id.0).or_default();
+        let wizard_cancelled = entry.token_wizard.take().is_some();
+        if !wizard_cancelled {
+            entry.stack.pop();
+        }
+        (entry.current(), wizard_cancelled)
+    };
+
+    let text = if wizard_cancelled {
+        format!("Создание токена отменено.\n\n{}", menu.title())
+    } else {
+        menu.title().to_string()
+    };
+    bot.send_message(chat_id, text)
+        .reply_markup(menu.keyboard(&state.admin_access(chat_id.0)))
+        .await?;
+    Ok(())
+}
+
+pub async fn start_token_wizard(bot: &Bot, chat_id: ChatId, state: &BotState) -> HandlerResult {
+    let config = state.config();
+    let security = &config.security;
+    {
+        let mut navigation = state.admin_navigation.lock().await;
+        let entry = navigation.entry(chat_id.0).or_default();
+        if entry.current() != AdminMenu::Tokens {
+            entry.stack.push(AdminMenu::Tokens);
+        }
+        entry.token_wizard = Some(TokenWizard {
+            step: TokenWizardStep::Days,
+            days: security.default_token_days,
+            auto_approve: false,
+            max_uses: None,
+        });
+    }
+
+    bot.send_message(
+        chat_id,
+        format!(
+            "➕ Новый токен.\n\
+             На сколько дней выдать токен? Выберите вариант или отправьте число от 1 до {}.",
+            security.max_token_days
+        ),
+    )
+    .reply_markup(keyboards::token_wizard_days_menu(
+        security.default_token_days,
+        security.max_token_days,
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Обрабатывает ответ на текущий шаг мастера. Возвращает `false`, если мастер не открыт
+/// или нажата кнопка меню.
+pub async fn try_process_token_wizard(
+    bot: &Bot,
+    msg: &Message,
+    state: &BotState,
+    admin_id: i64,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let chat_id = msg.chat.id;
+    let text = msg.text().unwrap_or("").trim();
+    // «Назад» отменяет мастер сам; любая другая кнопка меню закрывает мастер
+    // и обрабатывается как обычно.
+    if text == keyboards::BTN_BACK {
+        return Ok(false);
+    }
+    if keyboards::is_menu_button(text) {
+        if let Some(entry) = state.admin_navigation.lock().await.get_mut(&chat_id.0) {
+            entry.token_wizard = None;
+        }
+        return Ok(false);
+    }
+
+    let Some(mut wizard) = state
+        .admin_navigation
+        .lock()
+        .await
+        .get(&chat_id.0)
+        .and_then(|entry| entry.token_wizard.clone())
+    else {
+        return Ok(false);
+    };
+
+    let config = state.config();
+    let security = &config.security;
+    match wizard.step {
+        TokenWizardStep::Days => {
+            let parsed = text
+                .split_whitespace()
+                .next()
+                .and_then(|value| value.parse::<i64>().ok());
+            let Some(days) = parsed.filter(|days| (1..=security.max_token_days).contains(days))
+            else {
+                bot.send_message(
+                    chat_id,
+                    format!(
+                        "Нужно целое число дней от 1 до {}.",
+                        security.max_token_days
+                    ),
+                )
+                .await?;
+                return Ok(true);
+            };
+            wizard.days = days;
+
+            if security.allow_auto_approve_tokens
+                && state.has_permission(admin_id, Permission::AutoApproveTokens)
+            {
+                wizard.step = TokenWizardStep::Mode;
+                bot.send_message(
+                    chat_id,
+                    "Как подтверждать пользователей по этому токену?",
+                )
+                .reply_markup(keyboards::token_wizard_mode_menu())
+                .await?;
+            } else {
+                wizard.step = TokenWizardStep::MaxUses;
+                bot.send_message(
+                    chat_id,
+                    "Сколько раз можно использовать токен? \
+                     Выберите вариант или отправьте число.",
+                )
+                .reply_markup(keyboards::token_wizard_max_uses_menu())
+                .await?;
+            }
+        }
+        TokenWizardStep::Mode => {
+            wizard.auto_approve = match text {
+                keyboards::BTN_WIZARD_MODE_AUTO => true,
+                keyboards::BTN_WIZARD_MODE_MANUAL => false,
+                _ => {
+                    bot.send_message(chat_id, "Выберите режим кнопкой ниже.")
+                        .reply_markup(keyboards::token_wizard_mode_menu())
+                        .await?;
+                    return Ok(true);
+                }
+            };
+            wizard.step = TokenWizardStep::MaxUses;
+            bot.send_message(
+                chat_id,
+                "Сколько раз можно использовать токен? \
+                 Выберите вариант или отправьте число.",
+            )
+            .reply_markup(keyboards::token_wizard_max_uses_menu())
+            .await?;
+        }
+        TokenWizardStep::MaxUses => {
+            wizard.max_uses = if text == keyboards::BTN_WIZARD_UNLIMITED {
+                None
+            } else {
+                match text.parse::<i64>() {
+                    Ok(value) if value >= 1 => Some(value),
+                    _ => {
+                        bot.send_message(
+                            chat_id,
+                            "Лимит должен быть целым числом >= 1 или «без лимита».",
+                        )
+                        .await?;
+                        return Ok(true);
+                    }
+                }
+            };
+            wizard.step = TokenWizardStep::AccessDays;
+            bot.send_message(
+                chat_id,
+                "На какой срок выдавать доступ пользователям этого токена? \
+                 Выберите вариант или отправьте число дней.",
+            )
+            .reply_markup(keyboards::token_wizard_access_days_menu())
+            .await?;
+        }
+        TokenWizardStep::AccessDays => {
+            let access_days = if text == keyboards::BTN_WIZARD_PERMANENT {
+                None
+            } else {
+                match text.split_whitespace().next().map(str::parse::<i64>) {
+                    Some(Ok(value)) if value >= 1 => Some(value),
+                    _ => {
+                        bot.send_message(
+                            chat_id,
+                            "Срок доступа должен быть целым числом дней >= 1 или «бессрочно».",
+                        )
+                        .await?;
+                        return Ok(true);
+                    }
+                }
+            };
+
+            if let Some(entry) = state.admin_navigation.lock().await.get_mut(&chat_id.0) {
+                entry.token_wizard = None;
+            }
+
+            let result = create_invite_token_for_admin(
+                state,
+                Some(wizard.days),
+                wizard.auto_approve,
+                wizard.max_uses,
+                Some(admin_id),
+                access_days,
+                UserLimits::default(),
+            )
+            .await?;
+            match result {
+                Ok(response) => {
+                    bot.send_message(chat_id, response)
+                        .parse_mode(ParseMode::Html)
+                        .reply_markup(AdminMenu::Tokens.keyboard(&state.admin_access(admin_id)))
+                        .await?;
+                }
+                Err(reason) => {
+                    bot.send_message(chat_id, reason)
+                        .reply_markup(AdminMenu::Tokens.keyboard(&state.admin_access(admin_id)))
+                        .await?;
+                }
+            }
+            return Ok(true);
+        }
+    }
+
+    if let Some(entry) = state.admin_navigation.lock().await.get_mut(&chat_id.0) {
+        entry.token_wizard = Some(wizard);
+    }
+    Ok(true)
+}
@@ -0,0 +1,306 @@
+//! Отложенный перезапуск telemt: изменения конфига за короткое окно объединяются
+//! в один reload/restart каждого затронутого экземпляра, а результат сообщается
+//! админам, которые их вызвали.
+
+use super::shared::record_audit;
+use super::state::BotState;
+use crate::db::{AuditAction, AuditTargetKind};
+use crate::instance::TelemtInstance;
+use crate::roles::Permission;
+use crate::service::ServiceResult;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+use teloxide::prelude::*;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+#[derive(Debug)]
+struct RestartRequest {
+    instances: Vec<String>,
+    context: &'static str,
+    actor: Option<i64>,
+}
+
+/// Очередь запросов на перезапуск telemt. Дешёво клонируется вместе с `BotState`.
+#[derive(Debug, Clone)]
+pub struct RestartScheduler {
+    sender: mpsc::UnboundedSender<RestartRequest>,
+}
+
+/// Приёмник очереди; передаётся в [`spawn_restart_worker`].
+pub struct RestartQueue(mpsc::UnboundedReceiver<RestartRequest>);
+
+impl RestartScheduler {
+    pub fn new() -> (Self, RestartQueue) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { sender }, RestartQueue(receiver))
+    }
+
+    /// Запрашивает перезапуск экземпляров, чей конфиг изменён; `actor` — админ, которому сообщить итог.
+    pub fn schedule(
+        &self,
+        instances: &[Arc<TelemtInstance>],
+        context: &'static str,
+        actor: Option<i64>,
+    ) {
+        if instances.is_empty() {
+            return;
+        }
+        let instances: Vec<String> = instances
+            .iter()
+            .map(|instance| instance.name.clone())
+            .collect();
+        tracing::debug!(context = context, instances = ?instances, "telemt restart scheduled");
+        let request = RestartRequest {
+            instances,
+            context,
+            actor,
+        };
+        if self.sender.send(request).is_err() {
+            tracing::warn!(
+                context = context,
+                "Очередь перезапуска telemt закрыта, изменения применятся при следующем перезапуске"
+            );
+        }
+    }
+}
+
+/// Накопленные за окно причины перезапуска.
+#[derive(Debug, Default)]
+struct RestartBatch {
+    contexts: BTreeMap<&'static str, usize>,
+    instances: BTreeSet<String>,
+    actors: BTreeSet<i64>,
+}
+
+impl RestartBatch {
+    fn add(&mut self, request: RestartRequest) {
+        self.instances.extend(request.instances);
+        *self.contexts.entry(request.context).or_default() += 1;
+        if let Some(actor) = request.actor {
+            self.actors.insert(actor);
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.contexts
+            .iter()
+            .map(|(context, count)| {
+                if *count > 1 {
+                    format!("{} ×{}", context, count)
+                } else {
+                    context.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn total(&self) -> usize {
+        self.contexts.values().sum()
+    }
+}
+
+pub fn spawn_restart_worker(bot: Bot, state: BotState, queue: RestartQueue) {
+    tokio::spawn(async move {
+        let RestartQueue(mut receiver) = queue;
+        tracing::info!("telemt restart scheduler started");
+
+        while let Some(first) = receiver.recv().await {
+            let started = Instant::now();
+            // Задержки читаются на каждую пачку, чтобы перечитанный конфиг сразу действовал.
+            let restart = state.config().restart.clone();
+            let debounce = Duration::from_secs(restart.debounce_secs);
+            let max_delay = Duration::from_secs(restart.max_delay_secs).max(debounce);
+            let mut batch = RestartBatch::default();
+            batch.add(first);
+
+            loop {
+                let deadline = (Instant::now() + debounce).min(started + max_delay);
+                match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                    Ok(Some(request)) => batch.add(request),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+
+            run_batch(&bot, &state, batch).await;
+        }
+    });
+}
+
+async fn run_batch(bot: &Bot, state: &BotState, batch: RestartBatch) {
+    let (lines, all_success) = apply_batch(state, &batch).await;
+    if lines.is_empty() {
+        return;
+    }
+    let text = format!("{}\nПричины: {}", lines.join("\n"), batch.describe());
+
+    // Об успехе сообщаем только инициаторам; о сбое системных изменений — админам сервиса.
+    let recipients: Vec<i64> = if !batch.actors.is_empty() {
+        batch.actors.iter().copied().collect()
+    } else if !all_success {
+        state.admins_with(Permission::ManageService)
+    } else {
+        Vec::new()
+    };
+    for admin_id in recipients {
+        if let Err(error) = bot.send_message(ChatId(admin_id), text.clone()).await {
+            tracing::warn!(
+                admin_id = admin_id,
+                error = %error,
+                "Не удалось сообщить админу итог перезапуска telemt"
+            );
+        }
+    }
+}
+
+/// Сразу применяет всё, что накопилось в очереди, без окна ожидания: CLI
+/// завершается после одной команды. Возвращает строки отчёта и общий успех.
+pub async fn apply_queued_restarts(state: &BotState, queue: RestartQueue) -> (Vec<String>, bool) {
+    let RestartQueue(mut receiver) = queue;
+    let mut batch = RestartBatch::default();
+    while let Ok(request) = receiver.try_recv() {
+        batch.add(request);
+    }
+    if batch.instances.is_empty() {
+        return (Vec::new(), true);
+    }
+    apply_batch(state, &batch).await
+}
+
+/// Перезапускает экземпляры пачки; возвращает строки отчёта и общий успех.
+async fn apply_batch(state: &BotState, batch: &RestartBatch) -> (Vec<String>, bool) {
+    let reasons = batch.describe();
+    let mut lines = Vec::with_capacity(batch.instances.len());
+    let mut all_success = true;
+    for name in &batch.instances {
+        let Some(instance) = state.instances.get(name) else {
+            tracing::warn!(instance = %name, "Экземпляр telemt не найден, перезапуск пропущен");
+            continue;
+        };
+        let (action, result) = apply_changes(state, instance).await;
+
+        if result.success {
+            tracing::info!(
+                instance = %instance.name,
+                action = action,
+                changes = batch.total(),
+                reasons = %reasons,
+                "telemt restarted after batched config changes"
+            );
+        } else {
+            tracing::warn!(
+                instance = %instance.name,
+                action = action,
+                stderr = %result.stderr,
+                reasons = %reasons,
+                "Не удалось применить изменения конфига telemt"
+            );
+        }
+
+        record_audit(
+            state,
+            None,
+            if action == "reload" {
+                AuditAction::ServiceReload
+            } else {
+                AuditAction::ServiceRestart
+            },
+            AuditTargetKind::Service,
+            &instance.service_name,
+            Some(&reasons),
+            Some(if result.success { "ok" } else { "failed" }),
+        )
+        .await;
+
+        let target = if state.instances.is_multi() {
+            format!("telemt {}", instance.name)
+        } else {
+            "telemt".to_string()
+        };
+        lines.push(if result.success {
+            format!("✅ Изменения конфига применены ({} {}).", action, target)
+        } else {
+            format!(
+                "❌ Не удалось применить изменения конфига ({} {}).\n{}",
+                action, target, result.stderr
+            )
+        });
+        if result.success {
+            if let Err(error) = instance.telemt_cfg.mark_applied().await {
+                tracing::warn!(
+                    instance = %instance.name,
+                    error = %error,
+                    "Не удалось отметить конфиг telemt как применённый"
+                );
+            }
+        } else if state.config().backups.auto_rollback
+            && let Some(line) = rollback_after_failure(state, instance).await
+        {
+            lines.push(line);
+        }
+        all_success &= result.success;
+    }
+    (lines, all_success)
+}
+
+/// Возвращает конфиг экземпляра к копии, снятой перед первой неприменённой
+/// записью, и перезапускает telemt ещё раз; возвращает строку для отчёта.
+async fn rollback_after_failure(state: &BotState, instance: &TelemtInstance) -> Option<String> {
+    let id = match instance.telemt_cfg.rollback_pending().await {
+        Ok(Some(id)) => id,
+        Ok(None) => return None,
+        Err(error) => {
+            tracing::error!(
+                instance = %instance.name,
+                error = %error,
+                "Не удалось откатить конфиг telemt"
+            );
+            return Some(format!("⚠️ Автоматический откат конфига не удался: {}", error));
+        }
+    };
+    tracing::warn!(instance = %instance.name, backup = %id, "telemt config rolled back after failed restart");
+    record_audit(
+        state,
+        None,
+        AuditAction::ConfigRollback,
+        AuditTargetKind::Config,
+        &instance.telemt_cfg.location(),
+        None,
+        Some(&id),
+    )
+    .await;
+
+    let (action, result) = apply_changes(state, instance).await;
+    let status = if result.success {
+        format!("{} после отката прошёл успешно", action)
+    } else {
+        format!("{} после отката тоже не удался: {}", action, result.stderr)
+    };
+    Some(format!(
+        "↩️ Конфиг откатан к копии {}, {}.\nБД и конфиг могут расходиться — проверьте /sync.",
+        id, status
+    ))
+}
+
+/// Применяет изменения конфига экземпляра: reload, если возможен, иначе restart.
+async fn apply_changes(state: &BotState, instance: &TelemtInstance) -> (&'static str, ServiceResult) {
+    // Воркер один, поэтому перезапуски не пересекаются; ручные команды
+    // ждут общую блокировку ServiceController.
+    if let Some(failure) = instance.validate_config().await {
+        return ("restart", failure);
+    }
+    if state.config().restart.prefer_reload && instance.service.can_reload().await {
+        let reload = instance.service.reload().await;
+        if reload.success {
+            return ("reload", reload);
+        }
+        tracing::warn!(
+            instance = %instance.name,
+            stderr = %reload.stderr,
+            "reload telemt не удался, выполняю restart"
+        );
+    }
+    ("restart", instance.service.restart().await)
+}
@@ -1,18 +1,33 @@
-use super::format::{format_timestamp, user_display_name};
-use super::state::{sender_user_id, telemt_username, BotState};
+use super::format::{
+    format_access_days, format_date, format_mode, format_timestamp, format_user_limits,
+    render_audit_entry,
+    format_log_priority, format_log_window, render_invite_token_button_title,
+    render_journal_line_html, render_journal_line_plain, render_service_status, render_sync_reports,
+    split_into_messages, user_display_name,
+};
+use super::state::{admin_sender_id, sender_user_id, telemt_username, BotState};
 use crate::db::{
-    ConsumedInviteToken, RegisterResult, RegistrationRequest, TokenConsumeError, TokenMode,
+    AuditAction, AuditFilter, AuditTargetKind, ConsumedInviteToken, InviteToken, RegisterResult, RegistrationRequest,
+    RequestStatus, SecretRotation, TokenConsumeError, TokenMode, UserLimits,
 };
-use crate::link::{build_proxy_link, generate_user_secret};
+use crate::instance::TelemtInstance;
+use crate::link::{generate_user_secret, ProxyLinks, UserLinks};
+use crate::roles::Permission;
+use crate::service::{LogPriority, LogWindow, ServiceResult};
+use crate::telemt_cfg::UserPolicy;
 use anyhow::anyhow;
 use image::{DynamicImage, ImageFormat, Luma};
 use qrcode::QrCode;
 use std::io::Cursor;
+use std::sync::Arc;
 use teloxide::prelude::*;
-use teloxide::types::{InlineKeyboardMarkup, InputFile};
+use teloxide::types::{InlineKeyboardMarkup, InputFile, ParseMode};
 
 pub type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
+pub const AUDIT_DEFAULT_PAGE_SIZE: i64 = 10;
+pub const AUDIT_MAX_PAGE_SIZE: i64 = 20;
+
 pub enum CreateTarget {
     UserId(i64),
     Username(String),
@@ -36,6 +51,28 @@ pub fn parse_create_target(arg: &str) -> Option<CreateTarget> {
     Some(CreateTarget::Username(username.to_string()))
 }
 
+/// Разбирает объём трафика: байты или число с суффиксом K/M/G/T (двоичные единицы).
+pub fn parse_data_quota(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last()? {
+        (index, 'K' | 'k') => (&value[..index], 1_i64 << 10),
+        (index, 'M' | 'm') => (&value[..index], 1 << 20),
+        (index, 'G' | 'g') => (&value[..index], 1 << 30),
+        (index, 'T' | 't') => (&value[..index], 1 << 40),
+        _ => (value, 1),
+    };
+    number
+        .parse::<i64>()
+        .ok()
+        .filter(|number| *number >= 1)
+        .and_then(|number| number.checked_mul(multiplier))
+}
+
+/// Разбирает значение лимита соединений или IP: целое число >= 1.
+pub fn parse_limit_count(value: &str) -> Option<i64> {
+    value.parse::<i64>().ok().filter(|count| *count >= 1)
+}
+
 pub fn parse_start_token(text: &str) -> Option<String> {
     let mut parts = text.split_whitespace();
     let command = parts.next()?;
@@ -84,6 +121,71 @@ pub fn parse_callback_user_action(data: &str, prefix: &str) -> Result<(i64, i64)
     Ok((tg_user_id, page.max(1)))
 }
 
+pub fn parse_callback_token_action(
+    data: &str,
+    prefix: &str,
+) -> Result<(String, i64), anyhow::Error> {
+    let payload = data
+        .strip_prefix(prefix)
+        .ok_or_else(|| anyhow!("Некорректный callback payload"))?;
+    let (token, page) = payload
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("Не указан номер страницы"))?;
+    if token.is_empty() {
+        return Err(anyhow!("Не указан токен"));
+    }
+    let page = page
+        .parse::<i64>()
+        .map_err(|_| anyhow!("Некорректный номер страницы"))?;
+    Ok((token.to_string(), page.max(1)))
+}
+
+/// Разбирает `audit_page:<filter>:<page_size>:<page>`.
+pub fn parse_callback_audit_page(data: &str) -> Result<(AuditFilter, i64, i64), anyhow::Error> {
+    let payload = data
+        .strip_prefix("audit_page:")
+        .ok_or_else(|| anyhow!("Некорректный callback payload"))?;
+    let mut parts = payload.split(':');
+    let filter = parts
+        .next()
+        .and_then(AuditFilter::from_arg)
+        .ok_or_else(|| anyhow!("Некорректный фильтр журнала"))?;
+    let page_size = parts
+        .next()
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or_else(|| anyhow!("Некорректный размер страницы"))?;
+    let page = parts
+        .next()
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or_else(|| anyhow!("Некорректный номер страницы"))?;
+    Ok((filter, page_size, page.max(1)))
+}
+
+/// Разбирает `logs:<text|file>:<priority>:<window>[:<instance>]`;
+/// возвращает (как файл, приоритет, окно, экземпляр).
+pub fn parse_callback_logs(
+    data: &str,
+) -> Result<(bool, LogPriority, LogWindow, Option<&str>), anyhow::Error> {
+    let payload = data
+        .strip_prefix("logs:")
+        .ok_or_else(|| anyhow!("Некорректный callback payload"))?;
+    let mut parts = payload.split(':');
+    let as_file = match parts.next() {
+        Some("text") => false,
+        Some("file") => true,
+        _ => return Err(anyhow!("Некорректный формат логов")),
+    };
+    let priority = parts
+        .next()
+        .and_then(LogPriority::from_arg)
+        .ok_or_else(|| anyhow!("Некорректный приоритет логов"))?;
+    let window = parts
+        .next()
+        .and_then(LogWindow::from_arg)
+        .ok_or_else(|| anyhow!("Некорректное окно логов"))?;
+    Ok((as_file, priority, window, parts.next()))
+}
+
 pub fn parse_callback_page(data: &str, prefix: &str) -> Result<i64, anyhow::Error> {
     data.strip_prefix(prefix)
         .ok_or_else(|| anyhow!("Некорректный callback payload"))?
@@ -101,6 +203,145 @@ pub fn build_bot_start_link(bot_username: &str, token: &str) -> String {
     format!("https://t.me/{}?start={}", normalized, token)
 }
 
+/// Проверяет параметры по политике `[security]` и создаёт токен.
+///
+/// Внешний `Err` — ошибка БД, внутренний — причина отказа для админа.
+/// При успехе возвращает HTML-текст с кодом токена и ссылкой.
+pub async fn create_invite_token_for_admin(
+    state: &BotState,
+    days: Option<i64>,
+    auto_approve: bool,
+    max_uses: Option<i64>,
+    created_by: Option<i64>,
+    access_days: Option<i64>,
+    limits: UserLimits,
+) -> Result<Result<String, String>, anyhow::Error> {
+    let token = match create_invite_token(
+        state,
+        days,
+        auto_approve,
+        max_uses,
+        created_by,
+        access_days,
+        limits,
+    )
+    .await?
+    {
+        Ok(token) => token,
+        Err(reason) => return Ok(Err(reason)),
+    };
+
+    let link_line = state
+        .bot_username
+        .as_deref()
+        .map(|bot_username| {
+            let invite_link = build_bot_start_link(bot_username, &token.token);
+            format!("Ссылка: {}\n", invite_link)
+        })
+        .unwrap_or_else(|| "Ссылка: недоступна (у бота не задан username в Telegram).\n".to_string());
+
+    Ok(Ok(format!(
+        "✅ Токен создан:\n\
+         Код: <code>{}</code>\n\
+         {}\
+         Режим: {}\n\
+         Действует до: {}\n\
+         Лимит использований: {}\n\
+         Срок доступа: {}\n\
+         Лимиты: {}\n\
+         Используйте команду <code>/token revoke {}</code> для отзыва.",
+        token.token,
+        link_line,
+        format_mode(token.auto_approve),
+        format_date(token.expires_at),
+        token
+            .max_usage
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| "без лимита".to_string()),
+        format_access_days(token.access_days),
+        format_user_limits(&token.limits),
+        token.token
+    )))
+}
+
+/// Проверяет параметры по `[security]` и правам создателя и создаёт invite-токен.
+/// Во внутреннем `Err` — причина отказа для админа. `created_by` — `None` для CLI и HTTP API.
+pub async fn create_invite_token(
+    state: &BotState,
+    days: Option<i64>,
+    auto_approve: bool,
+    max_uses: Option<i64>,
+    created_by: Option<i64>,
+    access_days: Option<i64>,
+    limits: UserLimits,
+) -> Result<Result<InviteToken, String>, anyhow::Error> {
+    let config = state.config();
+    let security = &config.security;
+    let days = days.unwrap_or(security.default_token_days);
+    if days < 1 {
+        return Ok(Err("Срок действия должен быть не меньше 1 дня.".to_string()));
+    }
+    if days > security.max_token_days {
+        return Ok(Err(format!(
+            "Нельзя создать токен на срок больше {} дней.",
+            security.max_token_days
+        )));
+    }
+    if auto_approve && !security.allow_auto_approve_tokens {
+        return Ok(Err(
+            "Автоподтверждение токенов запрещено в конфигурации.".to_string()
+        ));
+    }
+    if auto_approve
+        && created_by.is_some_and(|admin_id| !state.has_permission(admin_id, Permission::AutoApproveTokens))
+    {
+        return Ok(Err(
+            "Токены с автоподтверждением может создавать только владелец.".to_string()
+        ));
+    }
+    if access_days.is_some_and(|value| value < 1) {
+        return Ok(Err("Срок доступа должен быть не меньше 1 дня.".to_string()));
+    }
+
+    let token = state
+        .db
+        .create_invite_token(days, auto_approve, max_uses, created_by, access_days, limits)
+        .await?;
+    record_audit(
+        state,
+        created_by,
+        AuditAction::TokenCreate,
+        AuditTargetKind::Token,
+        &token.token,
+        None,
+        Some(if auto_approve { "active/auto" } else { "active/manual" }),
+    )
+    .await;
+    Ok(Ok(token))
+}
+
+/// Отзывает invite-токен и пишет это в журнал; `false` — токен не найден или уже отозван.
+pub async fn revoke_invite_token(
+    state: &BotState,
+    token: &str,
+    actor: Option<i64>,
+) -> Result<bool, anyhow::Error> {
+    let revoked = state.db.revoke_invite_token(token).await?;
+    if revoked {
+        record_audit(
+            state,
+            actor,
+            AuditAction::TokenRevoke,
+            AuditTargetKind::Token,
+            token,
+            Some("active"),
+            Some("revoked"),
+        )
+        .await;
+    }
+    Ok(revoked)
+}
+
 pub async fn mark_user_waiting_for_invite(state: &BotState, tg_user_id: i64) {
     state.awaiting_invite_users.lock().await.insert(tg_user_id);
 }
@@ -158,7 +399,7 @@ pub async fn notify_auto_approve(
             .unwrap_or_else(|| "—".to_string())
     );
 
-    for admin_id in &state.config.admin_ids {
+    for admin_id in &state.admins_with(Permission::IssueTokens) {
         if let Err(error) = bot.send_message(ChatId(*admin_id), text.clone()).await {
             tracing::warn!(
                 admin_id = *admin_id,
@@ -189,7 +430,7 @@ pub async fn notify_admins(
 
     let kb = crate::bot::keyboards::approve_reject_buttons(req.id);
 
-    for admin_id in &state.config.admin_ids {
+    for admin_id in &state.admins_with(Permission::ModerateRequests) {
         if let Err(e) = bot
             .send_message(ChatId(*admin_id), text.clone())
             .reply_markup(kb.clone())
@@ -220,21 +461,53 @@ pub fn build_user_qr_png_bytes(payload: &str) -> Result<Vec<u8>, anyhow::Error>
     Ok(bytes)
 }
 
-pub fn restart_telemt_service(state: &BotState, context: &'static str) {
-    let restart_result = state.service.restart();
-    if !restart_result.success {
+/// Пишет запись в журнал аудита. Ошибка записи не прерывает основное действие.
+pub async fn record_audit(
+    state: &BotState,
+    actor_tg_id: Option<i64>,
+    action: AuditAction,
+    target_kind: AuditTargetKind,
+    target: &str,
+    status_before: Option<&str>,
+    status_after: Option<&str>,
+) {
+    if let Err(error) = state
+        .db
+        .record_audit(
+            actor_tg_id,
+            action,
+            target_kind,
+            target,
+            status_before,
+            status_after,
+        )
+        .await
+    {
         tracing::warn!(
-            stderr = %restart_result.stderr,
-            "Не удалось перезапустить telemt после {}",
-            context
+            action = ?action,
+            target = target,
+            error = %error,
+            "Не удалось записать событие в журнал аудита"
         );
     }
 }
 
+/// Действие аудита для команды сервиса; `None` для действий только на чтение.
+pub fn service_audit_action(action: &str) -> Option<AuditAction> {
+    match action {
+        "start" => Some(AuditAction::ServiceStart),
+        "stop" => Some(AuditAction::ServiceStop),
+        "restart" => Some(AuditAction::ServiceRestart),
+        "reload" => Some(AuditAction::ServiceReload),
+        _ => None,
+    }
+}
+
 pub async fn approve_request_and_build_link(
     state: &BotState,
     request_id: i64,
-) -> Result<Option<(RegistrationRequest, String)>, anyhow::Error> {
+    admin_id: Option<i64>,
+) -> Result<Option<(RegistrationRequest, UserLinks)>, anyhow::Error> {
     let request = match state.db.get_pending_by_id(request_id).await? {
         Some(request) => request,
         None => return Ok(None),
@@ -243,32 +516,156 @@ pub async fn approve_request_and_build_link(
     let telemt_user = telemt_username(request.tg_user_id);
     let user_secret = generate_user_secret();
 
-    state.telemt_cfg.upsert_user(&telemt_user, &user_secret)?;
+    let access_expires_at = request
+        .access_days
+        .map(access_expires_at_from_now)
+        .transpose()?;
+    let policy = UserPolicy {
+        limits: request.limits,
+        expires_at: access_expires_at,
+    };
+    let instances =
+        upsert_user_in_instances(state, request.tg_user_id, &telemt_user, &user_secret, &policy)
+            .await?;
     if state
         .db
-        .approve(request_id, &telemt_user, &user_secret)
+        .approve(request_id, &telemt_user, &user_secret, access_expires_at)
         .await?
         .is_none()
     {
         return Ok(None);
     }
+    record_audit(
+        state,
+        admin_id,
+        AuditAction::RequestApprove,
+        AuditTargetKind::Request,
+        &request_id.to_string(),
+        Some(&request.status.to_string()),
+        Some(&RequestStatus::Approved.to_string()),
+    )
+    .await;
 
-    restart_telemt_service(state, "одобрения заявки");
+    state
+        .restart_scheduler
+        .schedule(&instances, "одобрение заявки", admin_id);
 
-    let link_params = state.telemt_cfg.read_link_params()?;
-    let proxy_link = build_proxy_link(&link_params, &user_secret)?;
+    let proxy_link = build_user_links(state, request.tg_user_id, &user_secret).await?;
     Ok(Some((request, proxy_link)))
 }
 
+/// Отклоняет ожидающую заявку и пишет это в журнал; `None` — заявка уже обработана.
+pub async fn reject_request(
+    state: &BotState,
+    request_id: i64,
+    admin_id: Option<i64>,
+) -> Result<Option<RegistrationRequest>, anyhow::Error> {
+    let request = state.db.reject(request_id).await?;
+    if let Some(request) = request.as_ref() {
+        record_audit(
+            state,
+            admin_id,
+            AuditAction::RequestReject,
+            AuditTargetKind::Request,
+            &request_id.to_string(),
+            Some(&request.status.to_string()),
+            Some(&RequestStatus::Rejected.to_string()),
+        )
+        .await;
+    }
+    Ok(request)
+}
+
+pub fn access_expires_at_from_now(days: i64) -> Result<i64, anyhow::Error> {
+    days.checked_mul(86_400)
+        .and_then(|ttl| chrono::Utc::now().timestamp().checked_add(ttl))
+        .ok_or_else(|| anyhow!("Срок доступа слишком большой"))
+}
+
+/// Экземпляры telemt, на которых у пользователя должен быть доступ.
+pub async fn user_instances(
+    state: &BotState,
+    tg_user_id: i64,
+) -> Result<Vec<Arc<TelemtInstance>>, anyhow::Error> {
+    let assigned = state.db.get_user_instances(tg_user_id).await?;
+    Ok(state.instances.select(&assigned))
+}
+
+/// Собирает ссылки пользователя на всех его экземплярах telemt.
+pub async fn build_user_links(
+    state: &BotState,
+    tg_user_id: i64,
+    secret: &str,
+) -> Result<UserLinks, anyhow::Error> {
+    let mut links = UserLinks::new(state.instances.is_multi());
+    for instance in user_instances(state, tg_user_id).await? {
+        let params = instance.telemt_cfg.read_link_params().await?;
+        links.push(
+            instance.name.clone(),
+            ProxyLinks::build(&params, secret, &state.config().links.forms)?,
+        );
+    }
+    Ok(links)
+}
+
+/// Записывает пользователя в конфиги его экземпляров; возвращает изменённые экземпляры.
+pub async fn upsert_user_in_instances(
+    state: &BotState,
+    tg_user_id: i64,
+    telemt_user: &str,
+    secret: &str,
+    policy: &UserPolicy,
+) -> Result<Vec<Arc<TelemtInstance>>, anyhow::Error> {
+    let instances = user_instances(state, tg_user_id).await?;
+    for instance in &instances {
+        instance.telemt_cfg.upsert_user(telemt_user, secret, policy).await?;
+    }
+    Ok(instances)
+}
+
+/// Удаляет пользователя из конфигов всех экземпляров; возвращает те, где он был.
+pub async fn remove_user_from_instances(
+    state: &BotState,
+    telemt_user: &str,
+) -> Result<Vec<Arc<TelemtInstance>>, anyhow::Error> {
+    let mut removed_from = Vec::new();
+    for instance in state.instances.all() {
+        if instance.telemt_cfg.remove_user(telemt_user).await? {
+            removed_from.push(instance.clone());
+        }
+    }
+    Ok(removed_from)
+}
+
+/// Выдаёт доступ без заявки. `actor` — админ для /create, `None` — автоподтверждение по токену
+/// или CLI; `action` — запись журнала: создание вручную или автоподключение.
+/// `access_days` — срок доступа, `None` — бессрочно.
+#[allow(clippy::too_many_arguments)]
 pub async fn approve_user_direct_and_build_link(
     state: &BotState,
     tg_user_id: i64,
     tg_username: Option<&str>,
     tg_display_name: Option<&str>,
-) -> Result<String, anyhow::Error> {
+    actor: Option<i64>,
+    action: AuditAction,
+    access_days: Option<i64>,
+    limits: UserLimits,
+) -> Result<UserLinks, anyhow::Error> {
+    let access_expires_at = access_days
+        .map(access_expires_at_from_now)
+        .transpose()?;
+    let status_before = state
+        .db
+        .get_request_by_tg_user(tg_user_id)
+        .await?
+        .map(|request| request.status.to_string());
     let telemt_user = telemt_username(tg_user_id);
     let secret = generate_user_secret();
-    state.telemt_cfg.upsert_user(&telemt_user, &secret)?;
+    let policy = UserPolicy {
+        limits,
+        expires_at: access_expires_at,
+    };
+    let instances = upsert_user_in_instances(state, tg_user_id, &telemt_user, &secret, &policy).await?;
     state
         .db
         .set_approved(
@@ -277,13 +674,26 @@ pub async fn approve_user_direct_and_build_link(
             tg_display_name,
             &telemt_user,
             &secret,
+            access_expires_at,
+            limits,
         )
         .await?;
+    record_audit(
+        state,
+        actor,
+        action,
+        AuditTargetKind::User,
+        &tg_user_id.to_string(),
+        status_before.as_deref(),
+        Some(&RequestStatus::Approved.to_string()),
+    )
+    .await;
 
-    restart_telemt_service(state, "выдачи доступа");
+    state
+        .restart_scheduler
+        .schedule(&instances, "выдача доступа", actor);
 
-    let params = state.telemt_cfg.read_link_params()?;
-    build_proxy_link(&params, &secret).map_err(anyhow::Error::from)
+    build_user_links(state, tg_user_id, &secret).await
 }
 
 pub async fn process_invite_token(
@@ -295,7 +705,7 @@ pub async fn process_invite_token(
     tg_display_name: Option<&str>,
     token: &str,
 ) -> HandlerResult {
-    let consumed = match state.db.consume_invite_token(token).await {
+    let consumed = match state.db.consume_invite_token(token, tg_user_id).await {
         Ok(token_payload) => token_payload,
         Err(TokenConsumeError::NotFound) => {
             bot.send_message(
@@ -337,12 +747,17 @@ pub async fn process_invite_token(
         TokenMode::Manual => {
             let result = state
                 .db
-                .register_or_get(tg_user_id, tg_username, tg_display_name)
+                .register_or_get(
+                    tg_user_id,
+                    tg_username,
+                    tg_display_name,
+                    consumed.access_days,
+                    consumed.limits,
+                )
                 .await?;
             match result {
                 RegisterResult::Approved(secret) => {
-                    let params = state.telemt_cfg.read_link_params()?;
-                    let link = build_proxy_link(&params, &secret)?;
+                    let link = build_user_links(state, tg_user_id, &secret).await?;
                     bot.send_message(msg.chat.id, format!("Ваша ссылка на прокси:\n\n{}", link))
                         .reply_markup(crate::bot::keyboards::user_menu())
                         .await?;
@@ -376,9 +791,17 @@ pub async fn process_invite_token(
             }
         }
         TokenMode::AutoApprove => {
-            let link =
-                approve_user_direct_and_build_link(state, tg_user_id, tg_username, tg_display_name)
-                    .await?;
+            let link = approve_user_direct_and_build_link(
+                state,
+                tg_user_id,
+                tg_username,
+                tg_display_name,
+                None,
+                AuditAction::UserAutoApprove,
+                consumed.access_days,
+                consumed.limits,
+            )
+            .await?;
             bot.send_message(
                 msg.chat.id,
                 format!("Доступ одобрен! Ваша ссылка для подключения:\n\n{}", link),
@@ -410,8 +833,7 @@ pub async fn send_user_link(
     let maybe = state.db.get_approved(tg_user_id).await?;
     match maybe {
         Some((_, secret)) => {
-            let params = state.telemt_cfg.read_link_params()?;
-            let link = build_proxy_link(&params, &secret)?;
+            let link = build_user_links(state, tg_user_id, &secret).await?;
             bot.send_message(chat_id, format!("Ваша ссылка на прокси:\n\n{}", link))
                 .reply_markup(crate::bot::keyboards::user_menu())
                 .await?;
@@ -432,9 +854,10 @@ pub async fn require_admin_callback(
     bot: &Bot,
     q: &CallbackQuery,
     state: &BotState,
+    permission: Permission,
 ) -> Result<Option<i64>, anyhow::Error> {
     let admin_id = q.from.id.0 as i64;
-    if !state.config.is_admin(admin_id) {
+    if !state.has_permission(admin_id, permission) {
         bot.answer_callback_query(q.id.clone())
             .text("Недостаточно прав")
             .show_alert(true)
@@ -444,33 +867,89 @@ pub async fn require_admin_callback(
     Ok(Some(admin_id))
 }
 
-pub async fn perform_hard_ban(state: &BotState, tg_user_id: i64) -> Result<String, anyhow::Error> {
-    let telemt_user = telemt_username(tg_user_id);
-    let removed_from_cfg = state.telemt_cfg.remove_user(&telemt_user)?;
-    let removed_from_db = state.db.deactivate_user(tg_user_id).await?;
-
-    if removed_from_cfg {
-        restart_telemt_service(state, "удаления пользователя");
+/// Проверяет право отправителя команды. Не-админам бот не отвечает, как и раньше,
+/// а админу без нужной роли сообщает об отказе.
+pub async fn require_permission(
+    bot: &Bot,
+    msg: &Message,
+    state: &BotState,
+    permission: Permission,
+) -> Result<Option<i64>, anyhow::Error> {
+    let Some(admin_id) = admin_sender_id(msg, state) else {
+        return Ok(None);
+    };
+    if !state.has_permission(admin_id, permission) {
+        tracing::info!(admin_id = admin_id, permission = ?permission, "Admin lacks permission");
+        bot.send_message(msg.chat.id, "⛔ Недостаточно прав для этого действия.")
+            .await?;
+        return Ok(None);
     }
+    Ok(Some(admin_id))
+}
 
-    if removed_from_cfg || removed_from_db {
+pub async fn perform_hard_ban(
+    state: &BotState,
+    tg_user_id: i64,
+    admin_id: i64,
+) -> Result<String, anyhow::Error> {
+    let telemt_user = telemt_username(tg_user_id);
+    if hard_ban_user(state, tg_user_id, Some(admin_id)).await? {
         Ok(format!("Пользователь {} удалён", telemt_user))
     } else {
         Ok(format!("Пользователь {} не найден", telemt_user))
     }
 }
 
+/// Удаляет пользователя из конфигов и деактивирует в БД; `false`, если удалять было нечего.
+/// `actor` — админ, `None` — CLI.
+pub async fn hard_ban_user(
+    state: &BotState,
+    tg_user_id: i64,
+    actor: Option<i64>,
+) -> Result<bool, anyhow::Error> {
+    let telemt_user = telemt_username(tg_user_id);
+    let status_before = state
+        .db
+        .get_request_by_tg_user(tg_user_id)
+        .await?
+        .map(|request| request.status.to_string());
+    let removed_from = remove_user_from_instances(state, &telemt_user).await?;
+    let removed_from_cfg = !removed_from.is_empty();
+    let removed_from_db = state.db.deactivate_user(tg_user_id).await?;
+
+    if removed_from_cfg || removed_from_db {
+        record_audit(
+            state,
+            actor,
+            AuditAction::UserBan,
+            AuditTargetKind::User,
+            &tg_user_id.to_string(),
+            status_before.as_deref(),
+            Some(&RequestStatus::Deleted.to_string()),
+        )
+        .await;
+    }
+
+    if removed_from_cfg {
+        state
+            .restart_scheduler
+            .schedule(&removed_from, "удаление пользователя", actor);
+    }
+
+    Ok(removed_from_cfg || removed_from_db)
+}
+
 pub async fn admin_show_pending(bot: &Bot, chat_id: ChatId, state: &BotState) -> HandlerResult {
     let pending = state.db.list_pending_requests(10).await?;
     if pending.is_empty() {
         bot.send_message(chat_id, "Новых заявок нет.")
-            .reply_markup(crate::bot::keyboards::admin_menu())
+            .reply_markup(crate::bot::keyboards::admin_requests_menu())
             .await?;
         return Ok(());
     }
 
     bot.send_message(chat_id, format!("Найдено новых заявок: {}", pending.len()))
-        .reply_markup(crate::bot::keyboards::admin_menu())
+        .reply_markup(crate::bot::keyboards::admin_requests_menu())
         .await?;
 
     for req in pending {
@@ -501,7 +980,7 @@ pub async fn admin_show_users_page(
     message_id: Option<teloxide::types::MessageId>,
 ) -> HandlerResult {
     let total_users = state.db.count_active_users().await?;
-    let users_page_size = state.config.users_page_size.max(1);
+    let users_page_size = state.config().users_page_size.max(1);
     if total_users <= 0 {
         let text = "Активных пользователей нет.";
         if let Some(message_id) = message_id {
@@ -510,7 +989,7 @@ pub async fn admin_show_users_page(
                 .await?;
         } else {
             bot.send_message(chat_id, text)
-                .reply_markup(crate::bot::keyboards::admin_menu())
+                .reply_markup(crate::bot::keyboards::admin_menu(&state.admin_access(chat_id.0)))
                 .await?;
         }
         return Ok(());
@@ -553,35 +1032,638 @@ pub async fn admin_show_users_page(
     Ok(())
 }
 
+pub async fn admin_show_tokens_page(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    requested_page: i64,
+    message_id: Option<teloxide::types::MessageId>,
+) -> HandlerResult {
+    let total_tokens = state.db.count_invite_tokens().await?;
+    let page_size = state.config().users_page_size.max(1);
+    if total_tokens <= 0 {
+        let text = "Invite-токенов пока нет. Создайте первый через /token create.";
+        if let Some(message_id) = message_id {
+            bot.edit_message_text(chat_id, message_id, text)
+                .reply_markup(InlineKeyboardMarkup::default())
+                .await?;
+        } else {
+            bot.send_message(chat_id, text).await?;
+        }
+        return Ok(());
+    }
+
+    let total_pages = ((total_tokens + page_size - 1) / page_size).max(1);
+    let page = requested_page.clamp(1, total_pages);
+    let offset = (page - 1) * page_size;
+    let tokens = state.db.list_invite_tokens_page(page_size, offset).await?;
+    let now = chrono::Utc::now().timestamp();
+
+    let titles: Vec<(String, String)> = tokens
+        .iter()
+        .map(|token| {
+            (
+                token.token.clone(),
+                render_invite_token_button_title(token, now),
+            )
+        })
+        .collect();
+
+    let text = format!(
+        "🔑 Invite-токены\nВсего: {}\nСтраница: {}/{}\n\n\
+         🟢 активен · ⌛ истёк · 📉 исчерпан · 🚫 отозван\n\
+         Нажмите на токен, чтобы открыть карточку.",
+        total_tokens, page, total_pages
+    );
+    let keyboard = crate::bot::keyboards::token_list_keyboard(&titles, page, total_pages);
+
+    if let Some(message_id) = message_id {
+        bot.edit_message_text(chat_id, message_id, text)
+            .reply_markup(keyboard)
+            .await?;
+    } else {
+        bot.send_message(chat_id, text).reply_markup(keyboard).await?;
+    }
+    Ok(())
+}
+
+pub async fn admin_show_audit_page(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    filter: AuditFilter,
+    page_size: i64,
+    requested_page: i64,
+    message_id: Option<teloxide::types::MessageId>,
+) -> HandlerResult {
+    let page_size = page_size.clamp(1, AUDIT_MAX_PAGE_SIZE);
+    let total = state.db.count_audit_entries(filter).await?;
+    if total <= 0 {
+        let text = "Журнал аудита пуст.";
+        if let Some(message_id) = message_id {
+            bot.edit_message_text(chat_id, message_id, text)
+                .reply_markup(InlineKeyboardMarkup::default())
+                .await?;
+        } else {
+            bot.send_message(chat_id, text).await?;
+        }
+        return Ok(());
+    }
+
+    let total_pages = ((total + page_size - 1) / page_size).max(1);
+    let page = requested_page.clamp(1, total_pages);
+    let offset = (page - 1) * page_size;
+    let entries = state.db.list_audit_page(filter, page_size, offset).await?;
+    let lines: Vec<String> = entries.iter().map(render_audit_entry).collect();
+
+    let text = format!(
+        "🧾 Журнал аудита ({})\nВсего: {}\nСтраница: {}/{}\n\n{}",
+        filter.as_arg(),
+        total,
+        page,
+        total_pages,
+        lines.join("\n")
+    );
+    let keyboard =
+        crate::bot::keyboards::audit_page_keyboard(filter.as_arg(), page_size, page, total_pages);
+
+    if let Some(message_id) = message_id {
+        bot.edit_message_text(chat_id, message_id, text)
+            .reply_markup(keyboard)
+            .await?;
+    } else {
+        bot.send_message(chat_id, text).reply_markup(keyboard).await?;
+    }
+    Ok(())
+}
+
+/// Пересчитывает расхождения и применяет исправления с одним рестартом на экземпляр.
+pub async fn apply_config_sync(state: &BotState, admin_id: Option<i64>) -> Result<String, anyhow::Error> {
+    let reports = crate::sync::build_reports(&state.db, &state.instances).await?;
+    let labeled = state.instances.is_multi();
+    if !reports.iter().any(|report| report.has_fixes()) {
+        return Ok(render_sync_reports(&reports, labeled));
+    }
+
+    let mut changed_instances = Vec::new();
+    let mut sections = Vec::new();
+    for report in reports.iter().filter(|report| report.has_fixes()) {
+        let Some(instance) = state.instances.get(&report.instance) else {
+            continue;
+        };
+        if crate::sync::apply_report(&instance.telemt_cfg, report).await? {
+            changed_instances.push(instance.clone());
+        }
+        record_audit(
+            state,
+            admin_id,
+            AuditAction::ConfigSync,
+            AuditTargetKind::Config,
+            &instance.telemt_cfg.location(),
+            Some(&format!(
+                "orphans={} missing={} mismatches={} policies={}",
+                report.orphans_in_config.len(),
+                report.missing_in_config.len(),
+                report.secret_mismatches.len(),
+                report.policy_mismatches.len()
+            )),
+            Some("synced"),
+        )
+        .await;
+
+        let counts = format!(
+            "Удалено из конфига: {}\n\
+             Добавлено в конфиг: {}\n\
+             Обновлено секретов: {}\n\
+             Обновлено лимитов: {}",
+            report.orphans_in_config.len(),
+            report.missing_in_config.len(),
+            report.secret_mismatches.len(),
+            report.policy_mismatches.len()
+        );
+        sections.push(if labeled {
+            format!("📡 {}\n{}", report.instance, counts)
+        } else {
+            counts
+        });
+    }
+    state
+        .restart_scheduler
+        .schedule(&changed_instances, "синхронизация конфига", admin_id);
+
+    Ok(format!("✅ Синхронизация выполнена.\n{}", sections.join("\n\n")))
+}
+
+/// Сверяет БД и конфиги экземпляров при старте и предлагает админам исправить расхождения.
+pub fn spawn_startup_sync_check(bot: Bot, state: BotState) {
+    tokio::spawn(async move {
+        let reports = match crate::sync::build_reports(&state.db, &state.instances).await {
+            Ok(reports) => reports,
+            Err(error) => {
+                tracing::warn!(error = %error, "Не удалось сверить БД с конфигом telemt при старте");
+                return;
+            }
+        };
+        let drifted: Vec<_> = reports
+            .into_iter()
+            .filter(|report| !report.is_clean())
+            .collect();
+        if drifted.is_empty() {
+            tracing::info!("Database and telemt config are in sync");
+            return;
+        }
+
+        for report in &drifted {
+            tracing::warn!(
+                instance = %report.instance,
+                orphans = ?report.orphans_in_config,
+                missing = report.missing_in_config.len(),
+                mismatches = report.secret_mismatches.len(),
+                policies = report.policy_mismatches.len(),
+                without_secret = ?report.approved_without_secret,
+                "Database and telemt config have drifted"
+            );
+        }
+        let has_fixes = drifted.iter().any(|report| report.has_fixes());
+        let text = format!(
+            "{}\n\nОбнаружено при запуске бота. Применить исправления можно кнопкой ниже или командой /sync.",
+            render_sync_reports(&drifted, state.instances.is_multi())
+        );
+        for admin_id in &state.admins_with(Permission::ManageService) {
+            let mut request = bot.send_message(ChatId(*admin_id), text.clone());
+            if has_fixes {
+                request = request.reply_markup(crate::bot::keyboards::sync_confirm_keyboard());
+            }
+            if let Err(error) = request.await {
+                tracing::warn!(
+                    admin_id = *admin_id,
+                    error = %error,
+                    "Не удалось отправить админу отчёт о расхождениях"
+                );
+            }
+        }
+    });
+}
+
 pub async fn admin_show_stats(bot: &Bot, chat_id: ChatId, state: &BotState) -> HandlerResult {
     let stats = state.db.admin_stats().await?;
-    let text = format!(
+    let mut text = format!(
         "📊 Статистика:\n\
          Всего записей: {}\n\
          Ожидают: {}\n\
          Активные: {}\n\
          Отклонённые: {}\n\
-         Удалённые: {}",
-        stats.total, stats.pending, stats.approved, stats.rejected, stats.deleted
+         Удалённые: {}\n\
+         Истёкшие: {}",
+        stats.total,
+        stats.pending,
+        stats.approved,
+        stats.rejected,
+        stats.deleted,
+        stats.expired
     );
+    if state.instances.is_multi() {
+        text.push_str("\n\n📡 Экземпляры telemt:");
+        for instance in state.instances.all() {
+            let users = match instance.telemt_cfg.read_users().await {
+                Ok(users) => format!("пользователей в конфиге: {}", users.len()),
+                Err(error) => format!("не удалось прочитать конфиг: {}", error),
+            };
+            let status = match instance.service.status().await {
+                Ok(status) if status.is_active() => "🟢",
+                Ok(_) => "🔴",
+                Err(_) => "⚪",
+            };
+            text.push_str(&format!("\n{} {} — {}", status, instance.name, users));
+        }
+    }
     bot.send_message(chat_id, text)
-        .reply_markup(crate::bot::keyboards::admin_menu())
+        .reply_markup(crate::bot::keyboards::admin_menu(&state.admin_access(chat_id.0)))
         .await?;
     Ok(())
 }
 
-pub async fn admin_show_service_panel(bot: &Bot, chat_id: ChatId, state: &BotState) -> HandlerResult {
-    let result = state.service.status();
-    let text = format!(
-        "⚙️ Сервис telemt\n\n{}",
-        state.service.format_result("status", &result)
+/// Текст панели сервиса: итог последнего действия (если было) и текущее состояние.
+pub async fn render_service_panel_text(
+    state: &BotState,
+    instance: &TelemtInstance,
+    action: Option<(&str, &ServiceResult)>,
+) -> String {
+    let title = if state.instances.is_multi() {
+        format!("⚙️ Сервис telemt «{}»", instance.name)
+    } else {
+        "⚙️ Сервис telemt".to_string()
+    };
+    let mut text = format!(
+        "{} ({}: {})\n\n",
+        title,
+        instance.service.backend_kind(),
+        instance.service_name
     );
+    if let Some((action_name, result)) = action {
+        text.push_str(&instance.service.format_result(action_name, result));
+        text.push_str("\n\n");
+    }
+    match instance.service.status().await {
+        Ok(status) => text.push_str(&render_service_status(
+            &status,
+            chrono::Utc::now().timestamp(),
+        )),
+        Err(error) => text.push_str(&format!("Не удалось получить статус: {}", error)),
+    }
+    text
+}
+
+pub async fn admin_show_service_panel(bot: &Bot, chat_id: ChatId, state: &BotState) -> HandlerResult {
+    let instance = state.instances.primary();
+    let text = render_service_panel_text(state, instance, None).await;
     bot.send_message(chat_id, text)
-        .reply_markup(crate::bot::keyboards::service_control_buttons())
+        .reply_markup(service_panel_keyboard(state, instance))
         .await?;
     Ok(())
 }
 
+/// Кнопки панели сервиса; при нескольких экземплярах — с выбором экземпляра.
+pub fn service_panel_keyboard(state: &BotState, instance: &TelemtInstance) -> InlineKeyboardMarkup {
+    let others = if state.instances.is_multi() {
+        state.instances.names()
+    } else {
+        Vec::new()
+    };
+    crate::bot::keyboards::service_control_buttons(&instance.name, &others)
+}
+
+/// Запас под HTML-разметку и заголовок до лимита Telegram в 4096 символов.
+const LOG_MESSAGE_LIMIT: usize = 3_500;
+
+/// Показывает последние строки журнала telemt: текстом (с разбиением) или файлом .log.
+#[allow(clippy::too_many_arguments)]
+pub async fn admin_show_service_logs(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    instance: &TelemtInstance,
+    priority: LogPriority,
+    window: LogWindow,
+    as_file: bool,
+    message_id: Option<teloxide::types::MessageId>,
+) -> HandlerResult {
+    let keyboard = crate::bot::keyboards::service_logs_keyboard(&instance.name, priority, window);
+    let header = format!(
+        "📜 Логи {} — последние {} строк\nПриоритет: {}, за {}",
+        instance.service_name,
+        state.config().logs.lines,
+        format_log_priority(priority),
+        format_log_window(window)
+    );
+
+    let entries = match instance
+        .service
+        .journal(state.config().logs.lines, priority, window)
+        .await
+    {
+        Ok(entries) => entries,
+        Err(error) => {
+            let text = format!("{}\n\n❌ Не удалось прочитать журнал: {}", header, error);
+            send_or_edit(bot, chat_id, message_id, text, keyboard, None).await?;
+            return Ok(());
+        }
+    };
+    if entries.is_empty() {
+        let text = format!("{}\n\nЗаписей нет.", header);
+        send_or_edit(bot, chat_id, message_id, text, keyboard, None).await?;
+        return Ok(());
+    }
+
+    let header_html = teloxide::utils::html::escape(&header);
+    let lines: Vec<String> = entries.iter().map(render_journal_line_html).collect();
+    let chunks = split_into_messages(&format!("{}\n", header_html), &lines, LOG_MESSAGE_LIMIT);
+
+    if !as_file && chunks.len() == 1 {
+        let text = chunks.into_iter().next().unwrap_or_default();
+        send_or_edit(bot, chat_id, message_id, text, keyboard, Some(ParseMode::Html)).await?;
+        return Ok(());
+    }
+
+    if !as_file && chunks.len() <= state.config().logs.max_messages {
+        let last = chunks.len() - 1;
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let request = bot.send_message(chat_id, chunk).parse_mode(ParseMode::Html);
+            if index == last {
+                request.reply_markup(keyboard.clone()).await?;
+            } else {
+                request.await?;
+            }
+        }
+        return Ok(());
+    }
+
+    let errors = entries.iter().filter(|entry| entry.is_error()).count();
+    let content: Vec<String> = entries.iter().map(render_journal_line_plain).collect();
+    let file_name = format!(
+        "{}-{}.log",
+        instance.service_name.trim_end_matches(".service"),
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    bot.send_document(
+        chat_id,
+        InputFile::memory(content.join("\n").into_bytes()).file_name(file_name),
+    )
+    .caption(format!(
+        "{}\nСтрок: {}, из них ошибок: {}",
+        header,
+        entries.len(),
+        errors
+    ))
+    .reply_markup(keyboard)
+    .await?;
+    Ok(())
+}
+
+async fn send_or_edit(
+    bot: &Bot,
+    chat_id: ChatId,
+    message_id: Option<teloxide::types::MessageId>,
+    text: String,
+    keyboard: InlineKeyboardMarkup,
+    parse_mode: Option<ParseMode>,
+) -> HandlerResult {
+    if let Some(message_id) = message_id {
+        let mut request = bot.edit_message_text(chat_id, message_id, text).reply_markup(keyboard);
+        if let Some(parse_mode) = parse_mode {
+            request = request.parse_mode(parse_mode);
+        }
+        request.await?;
+    } else {
+        let mut request = bot.send_message(chat_id, text).reply_markup(keyboard);
+        if let Some(parse_mode) = parse_mode {
+            request = request.parse_mode(parse_mode);
+        }
+        request.await?;
+    }
+    Ok(())
+}
+
+/// Итог [`rotate_user_secret`].
+pub enum SecretRotateOutcome {
+    /// Новая ссылка пользователя
+    Rotated(UserLinks),
+    /// Активного пользователя нет
+    Inactive,
+    /// Лимит самостоятельного перевыпуска: следующая попытка не раньше этого момента
+    Cooldown(i64),
+}
+
+/// Перевыпускает секрет активного пользователя, не отзывая доступ.
+///
+/// `actor` — админ из карточки пользователя или сам пользователь; `cooldown_secs` —
+/// лимит самостоятельного перевыпуска. Сначала секрет меняется в БД (с проверкой
+/// лимита в той же транзакции), затем в конфигах telemt; если запись конфига не
+/// удалась, прежний секрет возвращается и в БД, и в конфиги.
+pub async fn rotate_user_secret(
+    state: &BotState,
+    tg_user_id: i64,
+    actor: i64,
+    cooldown_secs: Option<i64>,
+) -> Result<SecretRotateOutcome, anyhow::Error> {
+    let Some(user) = state.db.get_active_user_by_tg_user(tg_user_id).await? else {
+        return Ok(SecretRotateOutcome::Inactive);
+    };
+    let telemt_user = user
+        .telemt_username
+        .clone()
+        .unwrap_or_else(|| telemt_username(tg_user_id));
+    let secret = generate_user_secret();
+    let policy = UserPolicy {
+        limits: user.limits,
+        expires_at: user.access_expires_at,
+    };
+    let (old_secret, history_id) = match state
+        .db
+        .rotate_secret(tg_user_id, &secret, actor, cooldown_secs)
+        .await?
+    {
+        SecretRotation::Rotated {
+            old_secret,
+            history_id,
+        } => (old_secret, history_id),
+        SecretRotation::NotFound => return Ok(SecretRotateOutcome::Inactive),
+        SecretRotation::Cooldown(next_allowed) => {
+            return Ok(SecretRotateOutcome::Cooldown(next_allowed));
+        }
+    };
+
+    let instances =
+        match upsert_user_in_instances(state, tg_user_id, &telemt_user, &secret, &policy).await {
+            Ok(instances) => instances,
+            Err(error) => {
+                tracing::warn!(
+                    tg_user_id = tg_user_id,
+                    error = %error,
+                    "Новый секрет не записан в конфиг telemt, возвращаем прежний"
+                );
+                if let Err(revert_error) = state
+                    .db
+                    .revert_secret_rotation(tg_user_id, &secret, &old_secret, history_id)
+                    .await
+                {
+                    tracing::error!(
+                        tg_user_id = tg_user_id,
+                        error = %revert_error,
+                        "Не удалось вернуть прежний секрет в БД"
+                    );
+                }
+                // Часть экземпляров могла уже получить новый секрет.
+                if let Err(restore_error) =
+                    upsert_user_in_instances(state, tg_user_id, &telemt_user, &old_secret, &policy).await
+                {
+                    tracing::error!(
+                        tg_user_id = tg_user_id,
+                        error = %restore_error,
+                        "Не удалось вернуть прежний секрет в конфиг telemt"
+                    );
+                }
+                return Err(error);
+            }
+        };
+    record_audit(
+        state,
+        Some(actor),
+        AuditAction::UserRotateSecret,
+        AuditTargetKind::User,
+        &tg_user_id.to_string(),
+        None,
+        Some("rotated"),
+    )
+    .await;
+
+    // Итог перезапуска сообщаем только админам, не пользователю.
+    let notify = state.is_admin(actor).then_some(actor);
+    state
+        .restart_scheduler
+        .schedule(&instances, "перевыпуск ссылки", notify);
+
+    Ok(SecretRotateOutcome::Rotated(
+        build_user_links(state, tg_user_id, &secret).await?,
+    ))
+}
+
+/// Отправляет пользователю новую ссылку с QR-кодом после перевыпуска.
+pub async fn send_rotated_link_to_user(
+    bot: &Bot,
+    tg_user_id: i64,
+    link: &UserLinks,
+) -> Result<(), anyhow::Error> {
+    send_qr_with_caption(
+        bot,
+        ChatId(tg_user_id),
+        tg_user_id,
+        link,
+        format!(
+            "♻️ Ваша ссылка на прокси перевыпущена, прежняя больше не работает.\n\n{}",
+            link
+        ),
+        Some(crate::bot::keyboards::user_menu()),
+    )
+    .await
+}
+
+/// Подпись к фото в Telegram ограничена 1024 символами.
+const PHOTO_CAPTION_LIMIT: usize = 1024;
+
+/// Отправляет QR-код ссылки; длинный текст со всеми адресами уходит отдельным сообщением.
+async fn send_qr_with_caption(
+    bot: &Bot,
+    chat_id: ChatId,
+    tg_user_id: i64,
+    link: &UserLinks,
+    caption: String,
+    keyboard: Option<teloxide::types::KeyboardMarkup>,
+) -> Result<(), anyhow::Error> {
+    let qr_png = build_user_qr_png_bytes(link.primary())?;
+    let photo = InputFile::memory(qr_png).file_name(format!("telemt-proxy-{}.png", tg_user_id));
+    if caption.chars().count() <= PHOTO_CAPTION_LIMIT {
+        let mut request = bot.send_photo(chat_id, photo).caption(caption);
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard);
+        }
+        request.await?;
+        return Ok(());
+    }
+
+    bot.send_photo(chat_id, photo).await?;
+    let mut request = bot.send_message(chat_id, caption);
+    if let Some(keyboard) = keyboard {
+        request = request.reply_markup(keyboard);
+    }
+    request.await?;
+    Ok(())
+}
+
+/// Проверяет, может ли пользователь сам перевыпустить ссылку.
+///
+/// `Err` — причина отказа для пользователя.
+pub async fn check_self_rotate_allowed(
+    state: &BotState,
+    tg_user_id: i64,
+) -> Result<Result<(), String>, anyhow::Error> {
+    let cooldown_hours = state.config().security.self_rotate_cooldown_hours;
+    if cooldown_hours <= 0 {
+        return Ok(Err(
+            "Перевыпуск ссылки отключён. Обратитесь к администратору.".to_string()
+        ));
+    }
+    if state.db.get_active_user_by_tg_user(tg_user_id).await?.is_none() {
+        return Ok(Err(
+            "У вас нет доступа к прокси. Отправьте /start для регистрации.".to_string()
+        ));
+    }
+    let Some(last) = state
+        .db
+        .last_secret_rotation_by(tg_user_id, tg_user_id)
+        .await?
+    else {
+        return Ok(Ok(()));
+    };
+    let next_allowed = last.saturating_add(cooldown_hours.saturating_mul(3_600));
+    if chrono::Utc::now().timestamp() < next_allowed {
+        return Ok(Err(self_rotate_cooldown_text(cooldown_hours, next_allowed)));
+    }
+    Ok(Ok(()))
+}
+
+pub fn self_rotate_cooldown_text(cooldown_hours: i64, next_allowed: i64) -> String {
+    format!(
+        "Перевыпускать ссылку можно не чаще раза в {} ч.\nСледующая попытка: {}",
+        cooldown_hours,
+        format_timestamp(next_allowed)
+    )
+}
+
+pub async fn user_request_self_rotate(
+    bot: &Bot,
+    chat_id: ChatId,
+    tg_user_id: i64,
+    state: &BotState,
+) -> HandlerResult {
+    match check_self_rotate_allowed(state, tg_user_id).await? {
+        Ok(()) => {
+            bot.send_message(
+                chat_id,
+                "Перевыпустить ссылку? Прежняя ссылка перестанет работать на всех устройствах.",
+            )
+            .reply_markup(crate::bot::keyboards::self_rotate_confirm_keyboard())
+            .await?;
+        }
+        Err(reason) => {
+            bot.send_message(chat_id, reason)
+                .reply_markup(crate::bot::keyboards::user_menu())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
 pub async fn send_user_qr_to_admin(
     bot: &Bot,
     q: &CallbackQuery,
@@ -592,18 +1674,11 @@ pub async fn send_user_qr_to_admin(
         return Err(anyhow!("Не найден секрет пользователя"));
     };
 
-    let params = state.telemt_cfg.read_link_params()?;
-    let link = build_proxy_link(&params, secret)?;
-    let qr_png = build_user_qr_png_bytes(&link)?;
+    let link = build_user_links(state, user.tg_user_id, secret).await?;
     let caption = super::format::render_user_proxy_for_forward(user, &link);
 
     if let Some((chat_id, _)) = callback_message_target(q) {
-        bot.send_photo(
-            chat_id,
-            InputFile::memory(qr_png).file_name(format!("telemt-proxy-{}.png", user.tg_user_id)),
-        )
-        .caption(caption)
-        .await?;
+        send_qr_with_caption(bot, chat_id, user.tg_user_id, &link, caption, None).await?;
     }
     Ok(())
 }
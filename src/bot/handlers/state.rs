@@ -1,20 +1,82 @@
+use super::navigation::AdminNavigation;
+use super::restart::RestartScheduler;
 use crate::config::Config;
-use crate::db::Db;
-use crate::service::ServiceController;
-use crate::telemt_cfg::TelemtConfig;
-use std::collections::HashSet;
-use std::sync::Arc;
+use crate::db::{AdminGrant, Db};
+use crate::instance::Instances;
+use crate::roles::{AdminAccess, Permission};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use teloxide::types::Message;
 use tokio::sync::Mutex;
 
 #[derive(Clone)]
 pub struct BotState {
-    pub config: Arc<Config>,
+    /// Текущий конфиг бота; заменяется целиком при перечитывании (SIGHUP, `/reload_config`).
+    pub config: Arc<RwLock<Arc<Config>>>,
+    /// Путь к telemt-admin.toml для перечитывания.
+    pub config_path: PathBuf,
     pub db: Arc<Db>,
-    pub telemt_cfg: Arc<TelemtConfig>,
-    pub service: ServiceController,
+    /// Экземпляры telemt со своими конфигами и сервисами.
+    pub instances: Arc<Instances>,
     pub bot_username: Option<String>,
     pub awaiting_invite_users: Arc<Mutex<HashSet<i64>>>,
+    /// Стек подменю и мастер создания токена для каждого админского чата.
+    pub admin_navigation: Arc<Mutex<HashMap<i64, AdminNavigation>>>,
+    /// Отложенный перезапуск telemt после изменений конфига.
+    pub restart_scheduler: RestartScheduler,
+    /// Роли, выданные командой `/admin`: копия таблицы `admins`, чтобы проверять права без запроса к БД.
+    pub admin_grants: Arc<RwLock<Vec<AdminGrant>>>,
+}
+
+impl BotState {
+    /// Снимок конфига: действия внутри одного обработчика видят одни и те же настройки.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Роли пользователя в админке; пустые, если он не администратор.
+    pub fn admin_access(&self, user_id: i64) -> AdminAccess {
+        let mut access = self.config().admin_access(user_id);
+        let grants = self.admin_grants.read().unwrap_or_else(|e| e.into_inner());
+        for grant in grants.iter().filter(|grant| grant.tg_user_id == user_id) {
+            access.grant(grant.role);
+        }
+        access
+    }
+
+    /// Перечитывает роли из таблицы `admins` после их изменения.
+    pub async fn reload_admin_grants(&self) -> Result<(), anyhow::Error> {
+        let grants = self.db.list_admin_grants().await?;
+        *self.admin_grants.write().unwrap_or_else(|e| e.into_inner()) = grants;
+        Ok(())
+    }
+
+    /// Все пользователи с какой-либо ролью: из конфига и выданные из бота.
+    pub fn admin_user_ids(&self) -> Vec<i64> {
+        let mut ids = self.config().admin_user_ids();
+        let grants = self.admin_grants.read().unwrap_or_else(|e| e.into_inner());
+        ids.extend(grants.iter().map(|grant| grant.tg_user_id));
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    pub fn is_admin(&self, user_id: i64) -> bool {
+        self.admin_access(user_id).is_admin()
+    }
+
+    pub fn has_permission(&self, user_id: i64, permission: Permission) -> bool {
+        self.admin_access(user_id).allows(permission)
+    }
+
+    /// Админы с правом `permission`: получатели связанных с ним уведомлений.
+    pub fn admins_with(&self, permission: Permission) -> Vec<i64> {
+        self.admin_user_ids()
+            .into_iter()
+            .filter(|user_id| self.has_permission(*user_id, permission))
+            .collect()
+    }
 }
 
 pub fn telemt_username(tg_user_id: i64) -> String {
@@ -38,6 +100,7 @@ pub fn sender_display_name(msg: &Message) -> Option<String> {
     })
 }
 
-pub fn is_admin_message(msg: &Message, state: &BotState) -> bool {
-    sender_user_id(msg).is_some_and(|user_id| state.config.is_admin(user_id))
+/// Возвращает id отправителя, если у него есть хотя бы одна роль администратора.
+pub fn admin_sender_id(msg: &Message, state: &BotState) -> Option<i64> {
+    sender_user_id(msg).filter(|user_id| state.is_admin(*user_id))
 }
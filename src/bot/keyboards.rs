@@ -1,9 +1,12 @@
 //! Клавиатуры бота: inline и постоянные reply-кнопки.
 
+use crate::roles::{AdminAccess, Permission};
+use crate::service::{LogPriority, LogWindow};
 use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton, KeyboardMarkup};
 
 pub const BTN_USER_LINK: &str = "🔗 Моя ссылка";
 pub const BTN_USER_GUIDE: &str = "❓ Инструкция";
+pub const BTN_USER_ROTATE: &str = "♻️ Новая ссылка";
 
 pub const BTN_ADMIN_REQUESTS: &str = "📋 Управление заявками";
 pub const BTN_ADMIN_TOKENS: &str = "🔑 Управление токенами";
@@ -20,37 +23,68 @@ pub const BTN_ADMIN_PENDING: &str = "📥 Новые заявки";
 pub const BTN_ADMIN_TOKEN_CREATE: &str = "➕ Создать токен";
 pub const BTN_ADMIN_TOKEN_LIST: &str = "📋 Список токенов";
 
-pub fn user_menu() -> KeyboardMarkup {
-    KeyboardMarkup::new(vec![vec![
-        KeyboardButton::new(BTN_USER_LINK),
-        KeyboardButton::new(BTN_USER_GUIDE),
-    ]])
-    .resize_keyboard()
-    .persistent()
+pub const BTN_BACK: &str = "◀️ Назад";
+
+// Мастер создания токена
+pub const BTN_WIZARD_MODE_MANUAL: &str = "✅ Ручное подтверждение";
+pub const BTN_WIZARD_MODE_AUTO: &str = "🚀 Автоподтверждение";
+pub const BTN_WIZARD_UNLIMITED: &str = "♾ Без лимита";
+pub const BTN_WIZARD_PERMANENT: &str = "♾ Бессрочно";
+
+/// Кнопки постоянных меню пользователя и админа (без кнопок мастера токена).
+const MENU_BUTTONS: &[&str] = &[
+    BTN_USER_LINK,
+    BTN_USER_GUIDE,
+    BTN_USER_ROTATE,
+    BTN_ADMIN_REQUESTS,
+    BTN_ADMIN_TOKENS,
+    BTN_ADMIN_USERS,
+    BTN_ADMIN_SERVICE,
+    BTN_ADMIN_STATS,
+    BTN_ADMIN_CREATE_HINT,
+    BTN_ADMIN_HELP,
+    BTN_ADMIN_PENDING,
+    BTN_ADMIN_TOKEN_CREATE,
+    BTN_ADMIN_TOKEN_LIST,
+    BTN_BACK,
+];
+
+/// Текст совпадает с кнопкой меню, а не с ответом на шаг мастера.
+pub fn is_menu_button(text: &str) -> bool {
+    MENU_BUTTONS.contains(&text)
 }
 
-pub fn admin_menu() -> KeyboardMarkup {
+pub fn user_menu() -> KeyboardMarkup {
     KeyboardMarkup::new(vec![
         vec![
-            KeyboardButton::new(BTN_ADMIN_REQUESTS),
-            KeyboardButton::new(BTN_ADMIN_TOKENS),
-        ],
-        vec![
-            KeyboardButton::new(BTN_ADMIN_USERS),
-            KeyboardButton::new(BTN_ADMIN_SERVICE),
-        ],
-        vec![
-            KeyboardButton::new(BTN_ADMIN_STATS),
-            KeyboardButton::new(BTN_ADMIN_CREATE_HINT),
-        ],
-        vec![
-            KeyboardButton::new(BTN_ADMIN_HELP),
+            KeyboardButton::new(BTN_USER_LINK),
+            KeyboardButton::new(BTN_USER_GUIDE),
         ],
+        vec![KeyboardButton::new(BTN_USER_ROTATE)],
     ])
     .resize_keyboard()
     .persistent()
 }
 
+/// Главное админ-меню: только кнопки, доступные ролям админа.
+pub fn admin_menu(access: &AdminAccess) -> KeyboardMarkup {
+    let buttons: Vec<KeyboardButton> = [
+        (BTN_ADMIN_REQUESTS, Permission::ModerateRequests),
+        (BTN_ADMIN_TOKENS, Permission::IssueTokens),
+        (BTN_ADMIN_USERS, Permission::ViewUsers),
+        (BTN_ADMIN_SERVICE, Permission::ManageService),
+        (BTN_ADMIN_STATS, Permission::ViewUsers),
+        (BTN_ADMIN_CREATE_HINT, Permission::ManageUsers),
+    ]
+    .into_iter()
+    .filter(|(_, permission)| access.allows(*permission))
+    .map(|(label, _)| KeyboardButton::new(label))
+    .collect();
+    let mut rows: Vec<Vec<KeyboardButton>> = buttons.chunks(2).map(<[_]>::to_vec).collect();
+    rows.push(vec![KeyboardButton::new(BTN_ADMIN_HELP)]);
+    KeyboardMarkup::new(rows).resize_keyboard().persistent()
+}
+
 // Подменю для управления заявками
 pub fn admin_requests_menu() -> KeyboardMarkup {
     KeyboardMarkup::new(vec![
@@ -58,7 +92,7 @@ pub fn admin_requests_menu() -> KeyboardMarkup {
             KeyboardButton::new(BTN_ADMIN_PENDING),
         ],
         vec![
-            KeyboardButton::new("◀️ Назад"),
+            KeyboardButton::new(BTN_BACK),
         ],
     ])
     .resize_keyboard()
@@ -73,35 +107,136 @@ pub fn admin_tokens_menu() -> KeyboardMarkup {
             KeyboardButton::new(BTN_ADMIN_TOKEN_LIST),
         ],
         vec![
-            KeyboardButton::new("◀️ Назад"),
+            KeyboardButton::new(BTN_BACK),
+        ],
+    ])
+    .resize_keyboard()
+    .persistent()
+}
+
+pub fn token_wizard_days_menu(default_days: i64, max_days: i64) -> KeyboardMarkup {
+    let mut options: Vec<i64> = [1, 7, 14, 30, 90, default_days]
+        .into_iter()
+        .filter(|days| (1..=max_days).contains(days))
+        .collect();
+    options.sort_unstable();
+    options.dedup();
+
+    KeyboardMarkup::new(vec![
+        options
+            .into_iter()
+            .map(|days| KeyboardButton::new(days.to_string()))
+            .collect(),
+        vec![KeyboardButton::new(BTN_BACK)],
+    ])
+    .resize_keyboard()
+    .persistent()
+}
+
+pub fn token_wizard_mode_menu() -> KeyboardMarkup {
+    KeyboardMarkup::new(vec![
+        vec![
+            KeyboardButton::new(BTN_WIZARD_MODE_MANUAL),
+            KeyboardButton::new(BTN_WIZARD_MODE_AUTO),
+        ],
+        vec![KeyboardButton::new(BTN_BACK)],
+    ])
+    .resize_keyboard()
+    .persistent()
+}
+
+pub fn token_wizard_max_uses_menu() -> KeyboardMarkup {
+    KeyboardMarkup::new(vec![
+        vec![
+            KeyboardButton::new(BTN_WIZARD_UNLIMITED),
+            KeyboardButton::new("1"),
+            KeyboardButton::new("5"),
+            KeyboardButton::new("10"),
+        ],
+        vec![KeyboardButton::new(BTN_BACK)],
+    ])
+    .resize_keyboard()
+    .persistent()
+}
+
+pub fn token_wizard_access_days_menu() -> KeyboardMarkup {
+    KeyboardMarkup::new(vec![
+        vec![
+            KeyboardButton::new(BTN_WIZARD_PERMANENT),
+            KeyboardButton::new("7"),
+            KeyboardButton::new("30"),
+            KeyboardButton::new("90"),
+            KeyboardButton::new("365"),
         ],
+        vec![KeyboardButton::new(BTN_BACK)],
     ])
     .resize_keyboard()
     .persistent()
 }
 
+pub fn users_page_keyboard(
+    users: &[(i64, String)], // (tg_user_id, подпись кнопки)
+    page: i64,
+    total_pages: i64,
+) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = Vec::new();
+
+    for (tg_user_id, title) in users {
+        rows.push(vec![InlineKeyboardButton::callback(
+            format!("👤 {}", title),
+            format!("user_open:{}:{}", tg_user_id, page),
+        )]);
+    }
+
+    rows.push(page_navigation_row("users_page", page, total_pages));
+
+    InlineKeyboardMarkup::new(rows)
+}
+
 pub fn token_list_keyboard(
-    tokens: &[String], // Список токенов
+    tokens: &[(String, String)], // (token, подпись кнопки)
     page: i64,
     total_pages: i64,
 ) -> InlineKeyboardMarkup {
     let mut rows: Vec<Vec<InlineKeyboardButton>> = Vec::new();
-    
+
     // Добавляем токены с возможностью отозвать
-    for token in tokens {
+    for (token, title) in tokens {
         rows.push(vec![
             InlineKeyboardButton::callback(
-                format!("🔑 {}", token),
-                format!("token:view:{}", token), // Просмотр информации о токене
+                title.clone(),
+                format!("token:view:{}:{}", token, page), // Просмотр информации о токене
             ),
             InlineKeyboardButton::callback(
                 "🚫 Отозвать".to_string(),
-                format!("token:revoke:{}", token), // Отзыв токена
+                format!("token:revoke:{}:{}", token, page), // Отзыв токена
             ),
         ]);
     }
 
     // Навигация по страницам
+    rows.push(page_navigation_row("tokens_page", page, total_pages));
+
+    // Кнопка обновления списка
+    rows.push(vec![InlineKeyboardButton::callback(
+        "🔄 Обновить".to_string(),
+        format!("tokens_page:{}", page),
+    )]);
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn audit_page_keyboard(
+    filter: &str,
+    page_size: i64,
+    page: i64,
+    total_pages: i64,
+) -> InlineKeyboardMarkup {
+    let prefix = format!("audit_page:{}:{}", filter, page_size);
+    InlineKeyboardMarkup::new(vec![page_navigation_row(&prefix, page, total_pages)])
+}
+
+fn page_navigation_row(prefix: &str, page: i64, total_pages: i64) -> Vec<InlineKeyboardButton> {
     let prev_page = if page > 1 { page - 1 } else { 1 };
     let next_page = if page < total_pages {
         page + 1
@@ -109,38 +244,147 @@ pub fn token_list_keyboard(
         total_pages
     };
 
-    rows.push(vec![
-        InlineKeyboardButton::callback(
-            "⬅️".to_string(),
-            format!("tokens_page:{}", prev_page),
-        ),
+    vec![
+        InlineKeyboardButton::callback("⬅️".to_string(), format!("{}:{}", prefix, prev_page)),
         InlineKeyboardButton::callback(
             format!("📄 {}/{}", page, total_pages.max(1)),
-            format!("tokens_page:{}", page),
+            format!("{}:{}", prefix, page),
         ),
+        InlineKeyboardButton::callback("➡️".to_string(), format!("{}:{}", prefix, next_page)),
+    ]
+}
+
+pub fn token_card_keyboard(token: &str, page: i64, can_revoke: bool) -> InlineKeyboardMarkup {
+    let mut keyboard = InlineKeyboardMarkup::default();
+    if can_revoke {
+        keyboard = keyboard.append_row(vec![InlineKeyboardButton::callback(
+            "🚫 Отозвать",
+            format!("token:revoke:{}:{}", token, page),
+        )]);
+    }
+    keyboard.append_row(vec![InlineKeyboardButton::callback(
+        "⬅️ Назад к списку",
+        format!("tokens_page:{}", page),
+    )])
+}
+
+pub fn token_revoke_confirm_keyboard(token: &str, page: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::default().append_row(vec![
         InlineKeyboardButton::callback(
-            "➡️".to_string(),
-            format!("tokens_page:{}", next_page),
+            "✅ Да, отозвать",
+            format!("token:revoke_confirm:{}:{}", token, page),
         ),
-    ]);
-    
-    // Кнопка обновления списка
-    rows.push(vec![InlineKeyboardButton::callback(
-        "🔄 Обновить".to_string(),
-        format!("tokens_page:{}", page),
-    )]);
+        InlineKeyboardButton::callback("↩️ Отмена", format!("token:view:{}:{}", token, page)),
+    ])
+}
 
-    InlineKeyboardMarkup::new(rows)
+/// Кнопки панели экземпляра `instance`; `instances` — все экземпляры для переключения
+/// (пусто, если экземпляр один).
+pub fn service_control_buttons(instance: &str, instances: &[&str]) -> InlineKeyboardMarkup {
+    let mut keyboard = InlineKeyboardMarkup::default()
+        .append_row(vec![
+            InlineKeyboardButton::callback("🔄 Обновить", format!("service:status:{}", instance)),
+            InlineKeyboardButton::callback("♻️ Рестарт", format!("service:restart:{}", instance)),
+        ])
+        .append_row(vec![InlineKeyboardButton::callback(
+            "📜 Логи",
+            format!("logs:text:all:1h:{}", instance),
+        )]);
+    if !instances.is_empty() {
+        keyboard = keyboard.append_row(
+            instances
+                .iter()
+                .map(|name| {
+                    let title = if *name == instance {
+                        format!("• {}", name)
+                    } else {
+                        name.to_string()
+                    };
+                    InlineKeyboardButton::callback(title, format!("service:status:{}", name))
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+    keyboard
 }
 
-pub fn service_control_buttons() -> InlineKeyboardMarkup {
+pub fn service_logs_keyboard(
+    instance: &str,
+    priority: LogPriority,
+    window: LogWindow,
+) -> InlineKeyboardMarkup {
+    let mark = |selected: bool, title: &str| {
+        if selected {
+            format!("• {}", title)
+        } else {
+            title.to_string()
+        }
+    };
+    let priority_row = [
+        (LogPriority::All, "Все"),
+        (LogPriority::Warning, "⚠️ warning+"),
+        (LogPriority::Error, "❌ error+"),
+    ]
+    .into_iter()
+    .map(|(value, title)| {
+        InlineKeyboardButton::callback(
+            mark(value == priority, title),
+            format!("logs:text:{}:{}:{}", value.as_arg(), window.as_arg(), instance),
+        )
+    })
+    .collect::<Vec<_>>();
+    let window_row = [
+        (LogWindow::Minutes15, "15 мин"),
+        (LogWindow::Hour, "1 ч"),
+        (LogWindow::Day, "24 ч"),
+        (LogWindow::All, "Всё"),
+    ]
+    .into_iter()
+    .map(|(value, title)| {
+        InlineKeyboardButton::callback(
+            mark(value == window, title),
+            format!("logs:text:{}:{}:{}", priority.as_arg(), value.as_arg(), instance),
+        )
+    })
+    .collect::<Vec<_>>();
+
     InlineKeyboardMarkup::default()
+        .append_row(priority_row)
+        .append_row(window_row)
         .append_row(vec![
-            InlineKeyboardButton::callback("🔄 Обновить", "service:status"),
-            InlineKeyboardButton::callback("♻️ Рестарт", "service:restart"),
+            InlineKeyboardButton::callback(
+                "📄 Файлом",
+                format!("logs:file:{}:{}:{}", priority.as_arg(), window.as_arg(), instance),
+            ),
+            InlineKeyboardButton::callback("⚙️ К сервису", format!("service:status:{}", instance)),
         ])
 }
 
+pub fn sync_confirm_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::default().append_row(vec![
+        InlineKeyboardButton::callback("✅ Применить", "sync:apply"),
+        InlineKeyboardButton::callback("↩️ Отмена", "sync:cancel"),
+    ])
+}
+
+/// Подтверждение `/admin add|remove`; `role` — роль или `all` для снятия всех ролей.
+pub fn admin_change_confirm_keyboard(action: &str, tg_user_id: i64, role: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::default().append_row(vec![
+        InlineKeyboardButton::callback(
+            "✅ Подтвердить",
+            format!("admin:{}:{}:{}", action, tg_user_id, role),
+        ),
+        InlineKeyboardButton::callback("↩️ Отмена", "admin:cancel"),
+    ])
+}
+
+pub fn self_rotate_confirm_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::default().append_row(vec![
+        InlineKeyboardButton::callback("♻️ Перевыпустить", "self_rotate:confirm"),
+        InlineKeyboardButton::callback("↩️ Отмена", "self_rotate:cancel"),
+    ])
+}
+
 pub fn approve_reject_buttons(request_id: i64) -> InlineKeyboardMarkup {
     InlineKeyboardMarkup::default().append_row(vec![
         InlineKeyboardButton::callback("✅ Одобрить", format!("approve:{}", request_id)),
@@ -148,18 +392,26 @@ pub fn approve_reject_buttons(request_id: i64) -> InlineKeyboardMarkup {
     ])
 }
 
-pub fn user_card_keyboard(tg_user_id: i64, page: i64) -> InlineKeyboardMarkup {
-    InlineKeyboardMarkup::default()
-        .append_row(vec![InlineKeyboardButton::callback(
-            "🔗 Данные + QR",
-            format!("user_view:{}:{}", tg_user_id, page),
-        )])
-        .append_row(vec![InlineKeyboardButton::callback(
-            "⛔ Забанить (удалить)",
-            format!("user_ban:{}:{}", tg_user_id, page),
-        )])
-        .append_row(vec![InlineKeyboardButton::callback(
-            "⬅️ Назад к списку",
-            format!("users_page:{}", page),
-        )])
+/// Карточка пользователя; действия над ним видны только с правом управления пользователями.
+pub fn user_card_keyboard(tg_user_id: i64, page: i64, can_manage: bool) -> InlineKeyboardMarkup {
+    let mut keyboard = InlineKeyboardMarkup::default();
+    if can_manage {
+        keyboard = keyboard
+            .append_row(vec![InlineKeyboardButton::callback(
+                "🔗 Данные + QR",
+                format!("user_view:{}:{}", tg_user_id, page),
+            )])
+            .append_row(vec![InlineKeyboardButton::callback(
+                "♻️ Перевыпустить ссылку",
+                format!("user_rotate:{}:{}", tg_user_id, page),
+            )])
+            .append_row(vec![InlineKeyboardButton::callback(
+                "⛔ Забанить (удалить)",
+                format!("user_ban:{}:{}", tg_user_id, page),
+            )]);
+    }
+    keyboard.append_row(vec![InlineKeyboardButton::callback(
+        "⬅️ Назад к списку",
+        format!("users_page:{}", page),
+    )])
 }
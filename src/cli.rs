@@ -0,0 +1,531 @@
+//! Командная строка для администрирования без Telegram: по SSH, из скриптов
+//! и когда Telegram недоступен.
+//!
+//! Команды работают с той же БД, конфигами telemt и сервисами, что и бот, и
+//! пишут в журнал действий как система. Изменения конфига применяются сразу,
+//! без окна ожидания: перезапуск выполняется до выхода из команды. С `--json`
+//! результат и ошибки выводятся одним JSON-объектом в stdout.
+
+use crate::bot::handlers::{
+    apply_config_sync, apply_queued_restarts, approve_user_direct_and_build_link,
+    create_invite_token, format_timestamp, format_user_limits, hard_ban_user, parse_data_quota,
+    parse_limit_count, render_sync_reports, revoke_invite_token, BotState, RestartQueue,
+    RestartScheduler,
+};
+use crate::config::Config;
+use crate::db::{AuditAction, Db, UserLimits};
+use crate::json::{links_json, sync_report_json, token_json, user_json};
+use crate::instance::Instances;
+use crate::sync::SyncReport;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+const DEFAULT_CONFIG_PATH: &str = "/etc/telemt-admin.toml";
+const DEFAULT_AGENT_CONFIG_PATH: &str = "/etc/telemt-agent.toml";
+
+/// Первые слова команд; одиночный аргумент не из этого списка — путь к конфигу бота.
+const SUBCOMMANDS: &[&str] = &["run", "agent", "help", "user", "token", "sync", "db", "config"];
+
+pub const USAGE: &str = "Использование: telemt-admin [--config <путь>] [--json] <команда>
+
+  run [<конфиг>]                      запустить бота (по умолчанию)
+  agent [<конфиг агента>]             запустить агент для удалённого сервера
+  user list                           одобренные пользователи
+  user add <tg_user_id> [--days N] [--conns N] [--ips N] [--quota 10G]
+                                      выдать доступ без заявки
+  user ban <tg_user_id>               удалить пользователя из telemt и БД
+  token create [days] [--auto] [--max-uses N] [--access-days N] [--conns N] [--ips N] [--quota 10G]
+                                      создать invite-токен
+  token list                          все invite-токены
+  token revoke <token>                отозвать invite-токен
+  sync [--apply]                      сверить БД с конфигами telemt и исправить расхождения
+  db migrate                          применить миграции БД
+  config check                        проверить конфиг бота и конфиги telemt
+
+Конфиг бота по умолчанию: /etc/telemt-admin.toml.";
+
+/// Разобранная командная строка.
+pub struct Invocation {
+    pub command: Command,
+    pub config_path: PathBuf,
+    pub json: bool,
+}
+
+pub enum Command {
+    Run,
+    Agent(PathBuf),
+    Help,
+    UserList,
+    UserAdd {
+        tg_user_id: i64,
+        access_days: Option<i64>,
+        limits: Option<UserLimits>,
+    },
+    UserBan {
+        tg_user_id: i64,
+    },
+    TokenCreate(TokenOptions),
+    TokenList,
+    TokenRevoke {
+        token: String,
+    },
+    Sync {
+        apply: bool,
+    },
+    DbMigrate,
+    ConfigCheck,
+}
+
+#[derive(Default)]
+pub struct TokenOptions {
+    days: Option<i64>,
+    auto_approve: bool,
+    max_uses: Option<i64>,
+    access_days: Option<i64>,
+    limits: UserLimits,
+}
+
+impl Invocation {
+    /// Запускает бота или агента: им нужны подробные логи в stdout.
+    pub fn is_daemon(&self) -> bool {
+        matches!(self.command, Command::Run | Command::Agent(_))
+    }
+}
+
+/// Разбирает аргументы без имени программы. Прежний вызов `telemt-admin <конфиг>`
+/// по-прежнему запускает бота.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Invocation, anyhow::Error> {
+    let mut config_path: Option<PathBuf> = None;
+    let mut json = false;
+    let mut help = false;
+    let mut words: Vec<String> = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--config" | "-c" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--config требует путь к конфигу"))?;
+                config_path = Some(PathBuf::from(path));
+            }
+            "--help" | "-h" => help = true,
+            _ => words.push(arg),
+        }
+    }
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+    let command = match words.as_slice() {
+        _ if help => Command::Help,
+        [] => Command::Run,
+        ["help", ..] => Command::Help,
+        ["run"] => Command::Run,
+        ["run", path] => {
+            config_path = Some(PathBuf::from(path));
+            Command::Run
+        }
+        ["agent"] => Command::Agent(PathBuf::from(DEFAULT_AGENT_CONFIG_PATH)),
+        ["agent", path] => Command::Agent(PathBuf::from(path)),
+        ["user", "list"] => Command::UserList,
+        ["user", "add", tg_user_id, options @ ..] => {
+            let tg_user_id = parse_tg_user_id(tg_user_id)?;
+            let mut access_days = None;
+            let mut limits: Option<UserLimits> = None;
+            let mut options = options.iter();
+            while let Some(option) = options.next() {
+                let value = options
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("{} требует значение", option))?;
+                match *option {
+                    "--days" => access_days = Some(parse_positive(option, value)?),
+                    _ => apply_limit_option(limits.get_or_insert_with(UserLimits::default), option, value)?,
+                }
+            }
+            Command::UserAdd {
+                tg_user_id,
+                access_days,
+                limits,
+            }
+        }
+        ["user", "ban", tg_user_id] => Command::UserBan {
+            tg_user_id: parse_tg_user_id(tg_user_id)?,
+        },
+        ["token", "create", options @ ..] => Command::TokenCreate(parse_token_options(options)?),
+        ["token", "list"] => Command::TokenList,
+        ["token", "revoke", token] => Command::TokenRevoke {
+            token: token.to_string(),
+        },
+        ["sync"] => Command::Sync { apply: false },
+        ["sync", "--apply"] | ["sync", "apply"] => Command::Sync { apply: true },
+        ["db", "migrate"] => Command::DbMigrate,
+        ["config", "check"] => Command::ConfigCheck,
+        // Совместимость: `telemt-admin /etc/telemt-admin.toml`.
+        [path] if !path.starts_with('-') && !SUBCOMMANDS.contains(path) => {
+            config_path = Some(PathBuf::from(path));
+            Command::Run
+        }
+        _ => return Err(anyhow::anyhow!("Неизвестная команда: {}\n\n{}", words.join(" "), USAGE)),
+    };
+    Ok(Invocation {
+        command,
+        config_path: config_path.unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH)),
+        json,
+    })
+}
+
+fn parse_tg_user_id(value: &str) -> Result<i64, anyhow::Error> {
+    value
+        .parse::<i64>()
+        .map_err(|_| anyhow::anyhow!("tg_user_id должен быть числом, получено \"{}\"", value))
+}
+
+fn parse_positive(option: &str, value: &str) -> Result<i64, anyhow::Error> {
+    value
+        .parse::<i64>()
+        .ok()
+        .filter(|value| *value >= 1)
+        .ok_or_else(|| anyhow::anyhow!("{} должен быть целым числом >= 1", option))
+}
+
+fn apply_limit_option(limits: &mut UserLimits, option: &str, value: &str) -> Result<(), anyhow::Error> {
+    match option {
+        "--conns" | "--ips" => {
+            let count = parse_limit_count(value)
+                .ok_or_else(|| anyhow::anyhow!("{} должен быть целым числом >= 1", option))?;
+            if option == "--conns" {
+                limits.max_tcp_conns = Some(count);
+            } else {
+                limits.max_unique_ips = Some(count);
+            }
+        }
+        "--quota" => {
+            limits.data_quota_bytes = Some(parse_data_quota(value).ok_or_else(|| {
+                anyhow::anyhow!("--quota задаётся в байтах или с суффиксом K/M/G/T, например 10G")
+            })?);
+        }
+        _ => return Err(anyhow::anyhow!("Неизвестный параметр {}", option)),
+    }
+    Ok(())
+}
+
+fn parse_token_options(options: &[&str]) -> Result<TokenOptions, anyhow::Error> {
+    let mut parsed = TokenOptions::default();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match *option {
+            "--auto" | "-a" => parsed.auto_approve = true,
+            "--max-uses" | "--access-days" | "--conns" | "--ips" | "--quota" => {
+                let value = options
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("{} требует значение", option))?;
+                match *option {
+                    "--max-uses" => parsed.max_uses = Some(parse_positive(option, value)?),
+                    "--access-days" => parsed.access_days = Some(parse_positive(option, value)?),
+                    _ => apply_limit_option(&mut parsed.limits, option, value)?,
+                }
+            }
+            value => match value.parse::<i64>() {
+                Ok(days) if parsed.days.is_none() => parsed.days = Some(days),
+                _ => return Err(anyhow::anyhow!("Неизвестный параметр {}", value)),
+            },
+        }
+    }
+    Ok(parsed)
+}
+
+/// Выполняет команду администрирования и печатает результат.
+pub async fn execute(invocation: Invocation) -> Result<(), anyhow::Error> {
+    let json = invocation.json;
+    match run_command(invocation).await {
+        Ok(output) => {
+            print_output(&output, json);
+            if output.success {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("Команда выполнена с ошибками"))
+            }
+        }
+        Err(error) if json => {
+            println!("{}", json!({ "ok": false, "error": error.to_string() }));
+            Err(error)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Результат команды: текст для человека и те же данные для `--json`.
+struct Output {
+    text: String,
+    data: Value,
+    success: bool,
+}
+
+impl Output {
+    fn new(text: String, data: Value) -> Self {
+        Self {
+            text,
+            data,
+            success: true,
+        }
+    }
+}
+
+fn print_output(output: &Output, json: bool) {
+    if json {
+        let mut data = output.data.clone();
+        if let Value::Object(map) = &mut data {
+            map.insert("ok".to_string(), Value::Bool(output.success));
+        }
+        println!("{}", data);
+    } else {
+        println!("{}", output.text);
+    }
+}
+
+async fn run_command(invocation: Invocation) -> Result<Output, anyhow::Error> {
+    let path = invocation.config_path;
+    match invocation.command {
+        Command::Help => Ok(Output::new(USAGE.to_string(), json!({ "usage": USAGE }))),
+        Command::ConfigCheck => config_check(&path).await,
+        Command::DbMigrate => {
+            let config = Config::load(&path)?;
+            let db = Db::open(&config.db_path).await?;
+            let version = db.schema_version().await?;
+            Ok(Output::new(
+                format!("БД {} на версии схемы {}.", config.db_path.display(), version),
+                json!({ "db_path": config.db_path, "schema_version": version }),
+            ))
+        }
+        Command::Run | Command::Agent(_) => Err(anyhow::anyhow!("Команда не относится к CLI")),
+        command => {
+            let (state, queue) = open_state(path).await?;
+            let mut output = run_state_command(&state, command).await?;
+            let (lines, restarted) = apply_queued_restarts(&state, queue).await;
+            if !lines.is_empty() {
+                output.text.push_str("\n\n");
+                output.text.push_str(&lines.join("\n"));
+            }
+            if let Value::Object(map) = &mut output.data {
+                map.insert("restarts".to_string(), json!(lines));
+            }
+            output.success &= restarted;
+            Ok(output)
+        }
+    }
+}
+
+/// Состояние бота без Telegram: те же БД, экземпляры и очередь перезапуска.
+async fn open_state(config_path: PathBuf) -> Result<(BotState, RestartQueue), anyhow::Error> {
+    let config = Arc::new(Config::load(&config_path)?);
+    let db = Arc::new(Db::open(&config.db_path).await?);
+    let instances = Arc::new(Instances::from_config(&config)?);
+    let (restart_scheduler, restart_queue) = RestartScheduler::new();
+    let state = BotState {
+        config: Arc::new(RwLock::new(config)),
+        config_path,
+        db,
+        instances,
+        bot_username: None,
+        awaiting_invite_users: Default::default(),
+        admin_navigation: Default::default(),
+        restart_scheduler,
+        admin_grants: Arc::new(RwLock::new(Vec::new())),
+    };
+    state.reload_admin_grants().await?;
+    Ok((state, restart_queue))
+}
+
+async fn run_state_command(state: &BotState, command: Command) -> Result<Output, anyhow::Error> {
+    match command {
+        Command::UserList => user_list(state).await,
+        Command::UserAdd {
+            tg_user_id,
+            access_days,
+            limits,
+        } => {
+            // Как /create: без новых лимитов сохраняются прежние лимиты пользователя.
+            let existing = state.db.get_request_by_tg_user(tg_user_id).await?;
+            let limits = limits
+                .or(existing.as_ref().map(|request| request.limits))
+                .unwrap_or_default();
+            let links = approve_user_direct_and_build_link(
+                state,
+                tg_user_id,
+                existing.as_ref().and_then(|request| request.tg_username.as_deref()),
+                existing.as_ref().and_then(|request| request.tg_display_name.as_deref()),
+                None,
+                AuditAction::UserCreate,
+                access_days,
+                limits,
+            )
+            .await?;
+            Ok(Output::new(
+                format!(
+                    "✅ Пользователь {} создан. Пользователь не уведомлён — передайте ссылку сами.\n\n{}",
+                    tg_user_id, links
+                ),
+                json!({ "tg_user_id": tg_user_id, "instances": links_json(&links) }),
+            ))
+        }
+        Command::UserBan { tg_user_id } => {
+            let removed = hard_ban_user(state, tg_user_id, None).await?;
+            let text = if removed {
+                format!("Пользователь {} удалён.", tg_user_id)
+            } else {
+                format!("Пользователь {} не найден.", tg_user_id)
+            };
+            Ok(Output::new(text, json!({ "tg_user_id": tg_user_id, "removed": removed })))
+        }
+        Command::TokenCreate(options) => {
+            let token = create_invite_token(
+                state,
+                options.days,
+                options.auto_approve,
+                options.max_uses,
+                None,
+                options.access_days,
+                options.limits,
+            )
+            .await?
+            .map_err(|reason| anyhow::anyhow!(reason))?;
+            Ok(Output::new(
+                format!(
+                    "✅ Токен создан: {}\nДействует до: {}\nПользователь отправляет боту: /start {}",
+                    token.token,
+                    format_timestamp(token.expires_at),
+                    token.token
+                ),
+                token_json(&token, chrono::Utc::now().timestamp()),
+            ))
+        }
+        Command::TokenList => token_list(state).await,
+        Command::TokenRevoke { token } => {
+            let revoked = revoke_invite_token(state, &token, None).await?;
+            let text = if revoked {
+                format!("Токен {} отозван.", token)
+            } else {
+                "Токен не найден или уже отозван.".to_string()
+            };
+            Ok(Output::new(text, json!({ "token": token, "revoked": revoked })))
+        }
+        Command::Sync { apply } => {
+            let reports = crate::sync::build_reports(&state.db, &state.instances).await?;
+            let has_fixes = reports.iter().any(SyncReport::has_fixes);
+            let mut text = render_sync_reports(&reports, state.instances.is_multi());
+            if apply && has_fixes {
+                text = apply_config_sync(state, None).await?;
+            } else if has_fixes {
+                text.push_str("\n\nИсправить: telemt-admin sync --apply");
+            }
+            let data: Vec<Value> = reports.iter().map(sync_report_json).collect();
+            Ok(Output::new(
+                text,
+                json!({ "reports": data, "applied": apply && has_fixes }),
+            ))
+        }
+        Command::Run
+        | Command::Agent(_)
+        | Command::Help
+        | Command::DbMigrate
+        | Command::ConfigCheck => unreachable!("обрабатывается в run_command"),
+    }
+}
+
+async fn user_list(state: &BotState) -> Result<Output, anyhow::Error> {
+    let users = state.db.list_approved_users().await?;
+    let assignments = state.db.list_user_instances().await?;
+    let mut lines = Vec::with_capacity(users.len());
+    let mut data = Vec::with_capacity(users.len());
+    for user in &users {
+        let instances = assignments.get(&user.tg_user_id).cloned().unwrap_or_default();
+        let access = user
+            .access_expires_at
+            .map(|ts| format!("до {}", format_timestamp(ts)))
+            .unwrap_or_else(|| "бессрочно".to_string());
+        lines.push(format!(
+            "{}\t@{}\t{}\t{}\t{}\t{}",
+            user.tg_user_id,
+            user.tg_username.as_deref().unwrap_or("—"),
+            user.telemt_username.as_deref().unwrap_or("—"),
+            access,
+            format_user_limits(&user.limits),
+            if instances.is_empty() {
+                "все".to_string()
+            } else {
+                instances.join(",")
+            }
+        ));
+        data.push(user_json(user, &instances));
+    }
+    let text = if lines.is_empty() {
+        "Одобренных пользователей нет.".to_string()
+    } else {
+        format!("Одобренные пользователи: {}\n{}", users.len(), lines.join("\n"))
+    };
+    Ok(Output::new(text, json!({ "users": data })))
+}
+
+async fn token_list(state: &BotState) -> Result<Output, anyhow::Error> {
+    let total = state.db.count_invite_tokens().await?;
+    let tokens = state.db.list_invite_tokens_page(total.max(1), 0).await?;
+    let now = chrono::Utc::now().timestamp();
+    let lines: Vec<String> = tokens
+        .iter()
+        .map(|token| {
+            format!(
+                "{}\t{}\t{}\tдо {}\t{}/{}",
+                token.token,
+                token.status_at(now).as_str(),
+                if token.auto_approve { "auto" } else { "manual" },
+                format_timestamp(token.expires_at),
+                token.usage_count,
+                token
+                    .max_usage
+                    .map(|value| value.to_string())
+                    .unwrap_or_else(|| "∞".to_string())
+            )
+        })
+        .collect();
+    let text = if lines.is_empty() {
+        "Токенов нет.".to_string()
+    } else {
+        format!("Invite-токены: {}\n{}", tokens.len(), lines.join("\n"))
+    };
+    let data: Vec<Value> = tokens.iter().map(|token| token_json(token, now)).collect();
+    Ok(Output::new(text, json!({ "tokens": data })))
+}
+
+/// Проверяет конфиг бота и конфиги telemt всех экземпляров, не открывая БД.
+async fn config_check(path: &std::path::Path) -> Result<Output, anyhow::Error> {
+    let config = Config::load(path)?;
+    let instances = Instances::from_config(&config)?;
+    let mut success = true;
+    let mut lines = vec![format!("✅ Конфиг бота {} корректен.", path.display())];
+    if let Err(error) = config.bot_token() {
+        success = false;
+        lines.push(format!("❌ {}", error));
+    }
+    let mut results = Vec::new();
+    for instance in instances.all() {
+        let location = instance.telemt_cfg.location();
+        let error = instance.telemt_cfg.validate().await.err();
+        match &error {
+            None => lines.push(format!("✅ telemt {} ({}) — конфиг корректен.", instance.name, location)),
+            Some(error) => {
+                success = false;
+                lines.push(format!("❌ telemt {} ({}):\n{}", instance.name, location, error));
+            }
+        }
+        results.push(json!({
+            "instance": instance.name,
+            "config": location,
+            "error": error.map(|error| error.to_string()),
+        }));
+    }
+    Ok(Output {
+        text: lines.join("\n"),
+        data: json!({ "config": path, "bot_token": config.bot_token().is_ok(), "instances": results }),
+        success,
+    })
+}
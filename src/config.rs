@@ -1,5 +1,7 @@
 //! Конфигурация telemt-admin бота.
 
+use crate::link::ProxyLinkForm;
+use crate::roles::{AdminAccess, AdminRole};
 use serde::Deserialize;
 use std::path::PathBuf;
 
@@ -7,26 +9,240 @@ use std::path::PathBuf;
 pub struct Config {
     /// Токен Telegram бота (или через TELOXIDE_TOKEN)
     pub bot_token: Option<String>,
-    /// Список Telegram user_id администраторов
+    /// Telegram user_id владельцев бота: администраторы с полным доступом
     pub admin_ids: Vec<i64>,
+    /// Администраторы с ограниченными правами
+    #[serde(default)]
+    pub roles: RolesConfig,
     /// Путь к конфигу telemt (по умолчанию /etc/telemt.toml)
     #[serde(default = "default_telemt_config_path")]
     pub telemt_config_path: PathBuf,
     /// Путь к SQLite БД (по умолчанию /var/lib/telemt-admin/state.db)
     #[serde(default = "default_db_path")]
     pub db_path: PathBuf,
-    /// Имя systemd-сервиса telemt
+    /// Имя сервиса telemt: unit systemd, контейнер, сервис OpenRC/runit
     #[serde(default = "default_service_name")]
     pub service_name: String,
+    /// Как управлять сервисом telemt
+    #[serde(default)]
+    pub service: ServiceBackendConfig,
+    /// Проверка конфига самим telemt перед записью и перезапуском, `{config}` — путь к файлу
+    #[serde(default)]
+    pub telemt_check_command: Vec<String>,
     /// Размер страницы в списке активных пользователей
     #[serde(default = "default_users_page_size")]
     pub users_page_size: i64,
     /// Политики безопасности invite-токенов
     #[serde(default)]
     pub security: SecurityConfig,
+    /// Контроль срока доступа пользователей
+    #[serde(default)]
+    pub expiry: ExpiryConfig,
+    /// Отложенный перезапуск telemt после изменений конфига
+    #[serde(default)]
+    pub restart: RestartConfig,
+    /// Просмотр журнала telemt из панели сервиса
+    #[serde(default)]
+    pub logs: LogsConfig,
+    /// Какие виды ссылок на прокси отправлять пользователям
+    #[serde(default)]
+    pub links: LinksConfig,
+    /// Резервные копии конфига telemt перед каждой записью
+    #[serde(default)]
+    pub backups: BackupConfig,
+    /// Несколько экземпляров telemt; пусто — один экземпляр из полей верхнего уровня
+    #[serde(default)]
+    pub instances: Vec<InstanceConfig>,
+    /// Локальный HTTP API для внутренних инструментов; без секции API выключен
+    #[serde(default)]
+    pub api: Option<ApiConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Экземпляр telemt со своим конфигом и сервисом (`[[instances]]`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct InstanceConfig {
+    /// Короткое имя экземпляра: латиница, цифры, `-` и `_`
+    pub name: String,
+    #[serde(default = "default_telemt_config_path")]
+    pub telemt_config_path: PathBuf,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    #[serde(default)]
+    pub service: ServiceBackendConfig,
+    #[serde(default)]
+    pub telemt_check_command: Vec<String>,
+    /// Удалённый экземпляр: конфигом и сервисом управляет агент на другом сервере
+    #[serde(default)]
+    pub agent: Option<AgentClientConfig>,
+}
+
+/// Подключение к агенту telemt-admin (`[instances.agent]`).
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct AgentClientConfig {
+    /// Адрес агента, например `http://10.0.0.2:8765`
+    pub url: String,
+    /// Общий секрет, совпадающий с `token` в конфиге агента
+    pub token: String,
+    /// Таймаут запроса к агенту, в секундах
+    #[serde(default = "default_agent_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl std::fmt::Debug for AgentClientConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentClientConfig")
+            .field("url", &self.url)
+            .field("timeout_secs", &self.timeout_secs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Локальный HTTP API администрирования (`[api]`): слушает loopback-адрес или unix-сокет.
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct ApiConfig {
+    /// Адрес и порт на loopback-интерфейсе, например `127.0.0.1:8780`
+    #[serde(default)]
+    pub listen: Option<std::net::SocketAddr>,
+    /// Путь к unix-сокету вместо TCP
+    #[serde(default)]
+    pub socket: Option<PathBuf>,
+    /// Bearer-токен (или через TELEMT_ADMIN_API_TOKEN)
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl ApiConfig {
+    pub fn token(&self) -> &str {
+        self.token.as_deref().unwrap_or_default()
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        match (self.listen, &self.socket) {
+            (Some(_), Some(_)) | (None, None) => {
+                return Err(anyhow::anyhow!("В [api] нужно задать ровно одно из listen и socket"));
+            }
+            (Some(listen), None) if !listen.ip().is_loopback() => {
+                return Err(anyhow::anyhow!(
+                    "api.listen должен быть loopback-адресом (127.0.0.1 или ::1), получено {}",
+                    listen
+                ));
+            }
+            _ => {}
+        }
+        if self.token().len() < MIN_API_TOKEN_LEN {
+            return Err(anyhow::anyhow!(
+                "Не задан api.token (или TELEMT_ADMIN_API_TOKEN) длиной от {} символов",
+                MIN_API_TOKEN_LEN
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ApiConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiConfig")
+            .field("listen", &self.listen)
+            .field("socket", &self.socket)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Конфиг агента (`telemt-admin agent <path>`): локальный telemt, которым
+/// агент управляет по запросам бота.
+#[derive(Clone, Deserialize)]
+pub struct AgentConfig {
+    /// Адрес и порт HTTP API агента
+    #[serde(default = "default_agent_listen")]
+    pub listen: std::net::SocketAddr,
+    /// Общий секрет (или через TELEMT_AGENT_TOKEN)
+    pub token: Option<String>,
+    #[serde(default = "default_telemt_config_path")]
+    pub telemt_config_path: PathBuf,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    #[serde(default)]
+    pub service: ServiceBackendConfig,
+    #[serde(default)]
+    pub telemt_check_command: Vec<String>,
+    #[serde(default)]
+    pub backups: BackupConfig,
+}
+
+/// Минимальная длина секрета агента.
+const MIN_AGENT_TOKEN_LEN: usize = 16;
+
+/// Минимальная длина токена HTTP API.
+const MIN_API_TOKEN_LEN: usize = 16;
+
+impl AgentConfig {
+    pub fn load(path: &std::path::Path) -> Result<Self, anyhow::Error> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!("Не удалось прочитать конфиг агента {}: {}", path.display(), e)
+        })?;
+        let mut config: AgentConfig = toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Ошибка парсинга конфига агента: {}", e))?;
+        config.token = config
+            .token
+            .take()
+            .or_else(|| std::env::var("TELEMT_AGENT_TOKEN").ok());
+        match config.token.as_deref() {
+            Some(token) if token.len() >= MIN_AGENT_TOKEN_LEN => {}
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Не задан token агента (или TELEMT_AGENT_TOKEN) длиной от {} символов",
+                    MIN_AGENT_TOKEN_LEN
+                ));
+            }
+        }
+        tracing::info!(
+            listen = %config.listen,
+            telemt_config_path = %config.telemt_config_path.display(),
+            service_name = %config.service_name,
+            "Agent config parsed successfully"
+        );
+        Ok(config)
+    }
+
+    pub fn token(&self) -> &str {
+        self.token.as_deref().unwrap_or_default()
+    }
+
+    /// Локальный экземпляр telemt, которым управляет агент.
+    pub fn instance(&self) -> InstanceConfig {
+        InstanceConfig {
+            name: DEFAULT_INSTANCE_NAME.to_string(),
+            telemt_config_path: self.telemt_config_path.clone(),
+            service_name: self.service_name.clone(),
+            service: self.service.clone(),
+            telemt_check_command: self.telemt_check_command.clone(),
+            agent: None,
+        }
+    }
+}
+
+/// Имя неявного экземпляра, когда `[[instances]]` не заданы.
+pub const DEFAULT_INSTANCE_NAME: &str = "default";
+
+/// Максимальная длина имени экземпляра: имя входит в callback data (до 64 байт).
+const MAX_INSTANCE_NAME_LEN: usize = 24;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LinksConfig {
+    /// Виды ссылок в порядке вывода; первая кодируется в QR
+    #[serde(default = "default_link_forms")]
+    pub forms: Vec<ProxyLinkForm>,
+}
+
+impl Default for LinksConfig {
+    fn default() -> Self {
+        Self {
+            forms: default_link_forms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct SecurityConfig {
     #[serde(default = "default_token_days")]
     pub default_token_days: i64,
@@ -34,6 +250,9 @@ pub struct SecurityConfig {
     pub max_token_days: i64,
     #[serde(default = "default_allow_auto_approve_tokens")]
     pub allow_auto_approve_tokens: bool,
+    /// Как часто пользователь может сам перевыпускать ссылку, в часах (0 — запрещено)
+    #[serde(default = "default_self_rotate_cooldown_hours")]
+    pub self_rotate_cooldown_hours: i64,
 }
 
 impl Default for SecurityConfig {
@@ -42,10 +261,144 @@ impl Default for SecurityConfig {
             default_token_days: default_token_days(),
             max_token_days: default_max_token_days(),
             allow_auto_approve_tokens: default_allow_auto_approve_tokens(),
+            self_rotate_cooldown_hours: default_self_rotate_cooldown_hours(),
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExpiryConfig {
+    /// За сколько дней до окончания доступа предупредить пользователя (0 — не предупреждать)
+    #[serde(default = "default_expiry_warn_days")]
+    pub warn_days: i64,
+    /// Период фоновой проверки истёкших пользователей, в секундах
+    #[serde(default = "default_expiry_check_interval_secs")]
+    pub check_interval_secs: u64,
+}
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        Self {
+            warn_days: default_expiry_warn_days(),
+            check_interval_secs: default_expiry_check_interval_secs(),
+        }
+    }
+}
+
+/// Бэкенд управления telemt (`[service] backend = "..."`).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum ServiceBackendConfig {
+    #[default]
+    Systemd,
+    Docker {
+        /// Сигнал для reload (например, "HUP"); без него используется restart
+        #[serde(default)]
+        reload_signal: Option<String>,
+    },
+    Podman {
+        #[serde(default)]
+        reload_signal: Option<String>,
+    },
+    OpenRc,
+    Runit,
+    PidFile {
+        pid_file: PathBuf,
+        /// Команда запуска telemt, например ["/usr/local/bin/telemt", "/etc/telemt.toml"]
+        #[serde(default)]
+        start_command: Vec<String>,
+        #[serde(default)]
+        reload_signal: Option<String>,
+    },
+    Noop,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RestartConfig {
+    /// Сколько секунд ждать новых изменений после последнего, прежде чем перезапустить telemt
+    #[serde(default = "default_restart_debounce_secs")]
+    pub debounce_secs: u64,
+    /// Максимальная задержка перезапуска от первого изменения в пачке, в секундах
+    #[serde(default = "default_restart_max_delay_secs")]
+    pub max_delay_secs: u64,
+    /// Использовать `reload`, если unit его поддерживает
+    #[serde(default = "default_restart_prefer_reload")]
+    pub prefer_reload: bool,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            debounce_secs: default_restart_debounce_secs(),
+            max_delay_secs: default_restart_max_delay_secs(),
+            prefer_reload: default_restart_prefer_reload(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LogsConfig {
+    /// Сколько последних строк журнала показывать
+    #[serde(default = "default_logs_lines")]
+    pub lines: usize,
+    /// Если логи не помещаются в столько сообщений, они отправляются файлом .log
+    #[serde(default = "default_logs_max_messages")]
+    pub max_messages: usize,
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        Self {
+            lines: default_logs_lines(),
+            max_messages: default_logs_max_messages(),
+        }
+    }
+}
+
+/// Роли администраторов помимо владельцев из `admin_ids` (`[roles]`).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct RolesConfig {
+    /// Одобряют и отклоняют заявки
+    #[serde(default)]
+    pub moderators: Vec<i64>,
+    /// Видят список пользователей и статистику
+    #[serde(default)]
+    pub viewers: Vec<i64>,
+    /// Выпускают invite-токены с ручным подтверждением
+    #[serde(default)]
+    pub token_issuers: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BackupConfig {
+    /// Каталог копий; для каждого экземпляра создаётся подкаталог с его именем
+    #[serde(default = "default_backups_dir")]
+    pub dir: PathBuf,
+    /// Сколько последних копий хранить (0 — не делать копии)
+    #[serde(default = "default_backups_keep")]
+    pub keep: usize,
+    /// Откатывать конфиг к копии, если перезапуск после изменений не удался
+    #[serde(default = "default_backups_auto_rollback")]
+    pub auto_rollback: bool,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_backups_dir(),
+            keep: default_backups_keep(),
+            auto_rollback: default_backups_auto_rollback(),
+        }
+    }
+}
+
+impl BackupConfig {
+    /// Каталог копий экземпляра `instance`.
+    pub fn instance_dir(&self, instance: &str) -> PathBuf {
+        self.dir.join(instance)
+    }
+}
+
 fn default_telemt_config_path() -> PathBuf {
     PathBuf::from("/etc/telemt.toml")
 }
@@ -54,10 +407,30 @@ fn default_db_path() -> PathBuf {
     PathBuf::from("/var/lib/telemt-admin/state.db")
 }
 
+fn default_backups_dir() -> PathBuf {
+    PathBuf::from("/var/lib/telemt-admin/backups")
+}
+
+fn default_backups_keep() -> usize {
+    20
+}
+
+fn default_backups_auto_rollback() -> bool {
+    true
+}
+
 fn default_service_name() -> String {
     "telemt.service".to_string()
 }
 
+fn default_agent_listen() -> std::net::SocketAddr {
+    std::net::SocketAddr::from(([127, 0, 0, 1], 8765))
+}
+
+fn default_agent_timeout_secs() -> u64 {
+    15
+}
+
 fn default_users_page_size() -> i64 {
     10
 }
@@ -74,16 +447,66 @@ fn default_allow_auto_approve_tokens() -> bool {
     true
 }
 
+fn default_link_forms() -> Vec<ProxyLinkForm> {
+    vec![ProxyLinkForm::Https, ProxyLinkForm::Tg]
+}
+
+fn default_self_rotate_cooldown_hours() -> i64 {
+    24
+}
+
+fn default_expiry_warn_days() -> i64 {
+    3
+}
+
+fn default_expiry_check_interval_secs() -> u64 {
+    300
+}
+
+fn default_restart_debounce_secs() -> u64 {
+    5
+}
+
+fn default_restart_max_delay_secs() -> u64 {
+    30
+}
+
+fn default_restart_prefer_reload() -> bool {
+    true
+}
+
+fn default_logs_lines() -> usize {
+    50
+}
+
+fn default_logs_max_messages() -> usize {
+    3
+}
+
 impl Config {
     pub fn load(path: &std::path::Path) -> Result<Self, anyhow::Error> {
         tracing::debug!("Loading config from {}", path.display());
         let content = std::fs::read_to_string(path).map_err(|e| {
             anyhow::anyhow!("Не удалось прочитать конфиг {}: {}", path.display(), e)
         })?;
-        let config: Config = toml::from_str(&content)
+        let mut config: Config = toml::from_str(&content)
             .map_err(|e| anyhow::anyhow!("Ошибка парсинга конфига: {}", e))?;
+        if config.links.forms.is_empty() {
+            return Err(anyhow::anyhow!("links.forms должен содержать хотя бы один вид ссылки"));
+        }
+        config.validate_instances()?;
+        if let Some(api) = config.api.as_mut() {
+            api.token = api
+                .token
+                .take()
+                .or_else(|| std::env::var("TELEMT_ADMIN_API_TOKEN").ok());
+            api.validate()?;
+        }
         tracing::info!(
             admin_count = config.admin_ids.len(),
+            moderators = config.roles.moderators.len(),
+            viewers = config.roles.viewers.len(),
+            token_issuers = config.roles.token_issuers.len(),
             telemt_config_path = %config.telemt_config_path.display(),
             db_path = %config.db_path.display(),
             service_name = %config.service_name,
@@ -91,6 +514,23 @@ impl Config {
             security_default_days = config.security.default_token_days,
             security_max_days = config.security.max_token_days,
             allow_auto_approve_tokens = config.security.allow_auto_approve_tokens,
+            self_rotate_cooldown_hours = config.security.self_rotate_cooldown_hours,
+            expiry_warn_days = config.expiry.warn_days,
+            expiry_check_interval_secs = config.expiry.check_interval_secs,
+            restart_debounce_secs = config.restart.debounce_secs,
+            restart_max_delay_secs = config.restart.max_delay_secs,
+            restart_prefer_reload = config.restart.prefer_reload,
+            logs_lines = config.logs.lines,
+            link_forms = ?config.links.forms,
+            backups_dir = %config.backups.dir.display(),
+            backups_keep = config.backups.keep,
+            backups_auto_rollback = config.backups.auto_rollback,
+            api = ?config.api,
+            instances = ?config
+                .telemt_instances()
+                .iter()
+                .map(|instance| instance.name.as_str())
+                .collect::<Vec<_>>(),
             "Config parsed successfully"
         );
         Ok(config)
@@ -105,7 +545,150 @@ impl Config {
             })
     }
 
-    pub fn is_admin(&self, user_id: i64) -> bool {
-        self.admin_ids.contains(&user_id)
+    /// Экземпляры telemt: из `[[instances]]` или один `default` из полей верхнего уровня.
+    pub fn telemt_instances(&self) -> Vec<InstanceConfig> {
+        if !self.instances.is_empty() {
+            return self.instances.clone();
+        }
+        vec![InstanceConfig {
+            name: DEFAULT_INSTANCE_NAME.to_string(),
+            telemt_config_path: self.telemt_config_path.clone(),
+            service_name: self.service_name.clone(),
+            service: self.service.clone(),
+            telemt_check_command: self.telemt_check_command.clone(),
+            agent: None,
+        }]
+    }
+
+    fn validate_instances(&self) -> Result<(), anyhow::Error> {
+        let mut seen = std::collections::HashSet::new();
+        for instance in &self.instances {
+            let name = instance.name.as_str();
+            if name.is_empty()
+                || name.len() > MAX_INSTANCE_NAME_LEN
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(anyhow::anyhow!(
+                    "Некорректное имя экземпляра \"{}\": нужны латиница, цифры, - или _, до {} символов",
+                    name,
+                    MAX_INSTANCE_NAME_LEN
+                ));
+            }
+            if name == "all" {
+                return Err(anyhow::anyhow!("Имя экземпляра \"all\" зарезервировано"));
+            }
+            if !seen.insert(name) {
+                return Err(anyhow::anyhow!("Имя экземпляра \"{}\" повторяется", name));
+            }
+            if let Some(agent) = &instance.agent
+                && (agent.url.trim().is_empty() || agent.token.len() < MIN_AGENT_TOKEN_LEN)
+            {
+                return Err(anyhow::anyhow!(
+                    "У экземпляра \"{}\" нужны agent.url и agent.token длиной от {} символов",
+                    name,
+                    MIN_AGENT_TOKEN_LEN
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Роли пользователя по конфигу бота.
+    pub fn admin_access(&self, user_id: i64) -> AdminAccess {
+        let lists = [
+            (AdminRole::Owner, &self.admin_ids),
+            (AdminRole::Moderator, &self.roles.moderators),
+            (AdminRole::Viewer, &self.roles.viewers),
+            (AdminRole::TokenIssuer, &self.roles.token_issuers),
+        ];
+        AdminAccess::new(
+            lists
+                .into_iter()
+                .filter(|(_, ids)| ids.contains(&user_id))
+                .map(|(role, _)| role),
+        )
+    }
+
+    /// Все пользователи с какой-либо ролью.
+    pub fn admin_user_ids(&self) -> Vec<i64> {
+        let mut ids: Vec<i64> = self
+            .admin_ids
+            .iter()
+            .chain(&self.roles.moderators)
+            .chain(&self.roles.viewers)
+            .chain(&self.roles.token_issuers)
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Чем конфиг отличается от `previous`: что применится сразу, а что — после перезапуска бота.
+    pub fn changes_from(&self, previous: &Config) -> ConfigChanges {
+        let mut changes = ConfigChanges::default();
+        let applied = [
+            ("admin_ids", self.admin_ids != previous.admin_ids),
+            ("roles", self.roles != previous.roles),
+            ("users_page_size", self.users_page_size != previous.users_page_size),
+            ("security", self.security != previous.security),
+            ("expiry.warn_days", self.expiry.warn_days != previous.expiry.warn_days),
+            ("restart", self.restart != previous.restart),
+            ("logs", self.logs != previous.logs),
+            ("links", self.links != previous.links),
+            (
+                "backups.auto_rollback",
+                self.backups.auto_rollback != previous.backups.auto_rollback,
+            ),
+        ];
+        // Эти настройки используются при запуске: подключение к БД и Telegram,
+        // экземпляры telemt с их сервисами, каталоги копий, наблюдение за конфигами и HTTP API.
+        let restart_required = [
+            ("bot_token", self.bot_token != previous.bot_token),
+            ("db_path", self.db_path != previous.db_path),
+            ("telemt_config_path", self.telemt_config_path != previous.telemt_config_path),
+            ("service_name", self.service_name != previous.service_name),
+            ("service", self.service != previous.service),
+            (
+                "telemt_check_command",
+                self.telemt_check_command != previous.telemt_check_command,
+            ),
+            ("instances", self.instances != previous.instances),
+            ("api", self.api != previous.api),
+            ("backups.dir", self.backups.dir != previous.backups.dir),
+            ("backups.keep", self.backups.keep != previous.backups.keep),
+            (
+                "expiry.check_interval_secs",
+                self.expiry.check_interval_secs != previous.expiry.check_interval_secs,
+            ),
+        ];
+        changes.applied = applied
+            .into_iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(name, _)| name)
+            .collect();
+        changes.restart_required = restart_required
+            .into_iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(name, _)| name)
+            .collect();
+        changes
+    }
+}
+
+/// Изменённые настройки после перечитывания конфига бота.
+#[derive(Debug, Default)]
+pub struct ConfigChanges {
+    /// Уже действуют
+    pub applied: Vec<&'static str>,
+    /// Вступят в силу после перезапуска бота
+    pub restart_required: Vec<&'static str>,
+}
+
+impl ConfigChanges {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty()
     }
 }
@@ -1,8 +1,11 @@
 //! SQLite-слой для заявок на регистрацию и связей tg_user_id -> telemt_user.
 
+use crate::roles::AdminRole;
 use rand::distr::{Alphanumeric, SampleString};
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use std::collections::HashMap;
 use std::fmt;
 use std::path::Path;
 use std::str::FromStr;
@@ -31,6 +34,29 @@ pub struct RegistrationRequest {
     pub telemt_username: Option<String>,
     pub secret: Option<String>,
     pub created_at: i64,
+    /// Момент окончания доступа; `None` — бессрочный доступ
+    pub access_expires_at: Option<i64>,
+    /// Срок доступа в днях, который будет выдан при одобрении заявки
+    pub access_days: Option<i64>,
+    #[sqlx(flatten)]
+    pub limits: UserLimits,
+}
+
+/// Персональные ограничения пользователя telemt; `None` — без ограничения.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+pub struct UserLimits {
+    /// Максимум одновременных TCP-соединений
+    pub max_tcp_conns: Option<i64>,
+    /// Максимум уникальных IP-адресов
+    pub max_unique_ips: Option<i64>,
+    /// Квота трафика в байтах
+    pub data_quota_bytes: Option<i64>,
+}
+
+impl UserLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tcp_conns.is_none() && self.max_unique_ips.is_none() && self.data_quota_bytes.is_none()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
@@ -40,6 +66,7 @@ pub enum RequestStatus {
     Approved,
     Rejected,
     Deleted,
+    Expired,
 }
 
 impl fmt::Display for RequestStatus {
@@ -49,6 +76,7 @@ impl fmt::Display for RequestStatus {
             Self::Approved => STATUS_APPROVED,
             Self::Rejected => STATUS_REJECTED,
             Self::Deleted => STATUS_DELETED,
+            Self::Expired => STATUS_EXPIRED,
         };
         f.write_str(value)
     }
@@ -65,6 +93,67 @@ pub struct InviteToken {
     pub usage_count: i64,
     pub max_usage: Option<i64>,
     pub is_active: bool,
+    pub revoked_at: Option<i64>,
+    /// Срок доступа в днях для пользователей этого токена; `None` — бессрочно
+    pub access_days: Option<i64>,
+    /// Лимиты, которые получат пользователи этого токена
+    #[sqlx(flatten)]
+    pub limits: UserLimits,
+}
+
+/// Итоговое состояние invite-токена на момент запроса.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStatus {
+    Active,
+    Expired,
+    Exhausted,
+    Revoked,
+}
+
+impl TokenStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Expired => "expired",
+            Self::Exhausted => "exhausted",
+            Self::Revoked => "revoked",
+        }
+    }
+}
+
+impl InviteToken {
+    pub fn status_at(&self, now: i64) -> TokenStatus {
+        if !self.is_active {
+            TokenStatus::Revoked
+        } else if self.expires_at <= now {
+            TokenStatus::Expired
+        } else if self.max_usage.is_some_and(|max| self.usage_count >= max) {
+            TokenStatus::Exhausted
+        } else {
+            TokenStatus::Active
+        }
+    }
+}
+
+/// Факт использования invite-токена конкретным пользователем.
+#[derive(Debug, Clone, FromRow)]
+pub struct InviteTokenUse {
+    pub tg_user_id: i64,
+    pub tg_username: Option<String>,
+    pub tg_display_name: Option<String>,
+    pub status: Option<RequestStatus>,
+    pub used_at: i64,
+}
+
+/// Итог [`Db::rotate_secret`].
+#[derive(Debug)]
+pub enum SecretRotation {
+    /// Секрет заменён; `history_id` — запись с прежним секретом в `secret_history`
+    Rotated { old_secret: String, history_id: i64 },
+    /// Активного пользователя нет
+    NotFound,
+    /// Лимит перевыпусков: следующая попытка не раньше этого момента (unix-время)
+    Cooldown(i64),
 }
 
 #[derive(Debug, Clone)]
@@ -82,6 +171,17 @@ pub struct ConsumedInviteToken {
     pub created_by: Option<i64>,
     pub usage_count: i64,
     pub max_usage: Option<i64>,
+    pub access_days: Option<i64>,
+    pub limits: UserLimits,
+}
+
+/// Роль, выданная из бота командой `/admin add` (в дополнение к ролям из конфига).
+#[derive(Debug, Clone, FromRow)]
+pub struct AdminGrant {
+    pub tg_user_id: i64,
+    pub role: AdminRole,
+    pub added_by: Option<i64>,
+    pub added_at: i64,
 }
 
 #[derive(Debug, Error)]
@@ -100,7 +200,122 @@ const STATUS_APPROVED: &str = "approved";
 const STATUS_PENDING: &str = "pending";
 const STATUS_REJECTED: &str = "rejected";
 const STATUS_DELETED: &str = "deleted";
-const SELECT_REQUEST: &str = "SELECT id, tg_user_id, tg_username, tg_display_name, status, telemt_username, secret, created_at FROM registration_requests";
+const STATUS_EXPIRED: &str = "expired";
+const SELECT_REQUEST: &str = "SELECT id, tg_user_id, tg_username, tg_display_name, status, telemt_username, secret, created_at, access_expires_at, access_days, max_tcp_conns, max_unique_ips, data_quota_bytes FROM registration_requests";
+const SELECT_INVITE_TOKEN: &str = "SELECT id, token, created_at, expires_at, auto_approve, created_by, usage_count, max_usage, is_active, revoked_at, access_days, max_tcp_conns, max_unique_ips, data_quota_bytes FROM invite_tokens";
+
+/// Действие, попадающее в журнал аудита.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "TEXT")]
+pub enum AuditAction {
+    #[sqlx(rename = "request.approve")]
+    RequestApprove,
+    #[sqlx(rename = "request.reject")]
+    RequestReject,
+    #[sqlx(rename = "user.create")]
+    UserCreate,
+    #[sqlx(rename = "user.auto_approve")]
+    UserAutoApprove,
+    #[sqlx(rename = "user.ban")]
+    UserBan,
+    #[sqlx(rename = "user.expire")]
+    UserExpire,
+    #[sqlx(rename = "user.limits")]
+    UserLimits,
+    #[sqlx(rename = "user.rotate_secret")]
+    UserRotateSecret,
+    #[sqlx(rename = "user.instances")]
+    UserInstances,
+    #[sqlx(rename = "token.create")]
+    TokenCreate,
+    #[sqlx(rename = "token.revoke")]
+    TokenRevoke,
+    #[sqlx(rename = "service.start")]
+    ServiceStart,
+    #[sqlx(rename = "service.stop")]
+    ServiceStop,
+    #[sqlx(rename = "service.restart")]
+    ServiceRestart,
+    #[sqlx(rename = "service.reload")]
+    ServiceReload,
+    #[sqlx(rename = "config.sync")]
+    ConfigSync,
+    #[sqlx(rename = "config.rollback")]
+    ConfigRollback,
+    #[sqlx(rename = "config.reload")]
+    ConfigReload,
+    #[sqlx(rename = "admin.add")]
+    AdminAdd,
+    #[sqlx(rename = "admin.remove")]
+    AdminRemove,
+}
+
+/// Тип объекта, над которым выполнено действие.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum AuditTargetKind {
+    Request,
+    User,
+    Token,
+    Service,
+    Config,
+    Admin,
+}
+
+/// Фильтр для просмотра журнала аудита.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditFilter {
+    All,
+    /// Заявки и пользователи
+    User,
+    Token,
+    /// Только действия, выполненные админами (без системных)
+    Admin,
+}
+
+impl AuditFilter {
+    /// Разбирает аргумент команды `/audit`.
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value {
+            "all" => Some(Self::All),
+            "user" => Some(Self::User),
+            "token" => Some(Self::Token),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::User => "user",
+            Self::Token => "token",
+            Self::Admin => "admin",
+        }
+    }
+
+    fn where_clause(self) -> &'static str {
+        match self {
+            Self::All => "1 = 1",
+            Self::User => "target_kind IN ('request', 'user')",
+            Self::Token => "target_kind = 'token'",
+            Self::Admin => "actor_tg_id IS NOT NULL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// `None` — системное действие (автоподтверждение, автоматический рестарт)
+    pub actor_tg_id: Option<i64>,
+    pub action: AuditAction,
+    pub target_kind: AuditTargetKind,
+    pub target: String,
+    pub status_before: Option<String>,
+    pub status_after: Option<String>,
+    pub created_at: i64,
+}
 
 #[derive(Debug, Clone)]
 pub struct AdminStats {
@@ -109,41 +324,36 @@ pub struct AdminStats {
     pub approved: i64,
     pub rejected: i64,
     pub deleted: i64,
+    pub expired: i64,
 }
 
 pub struct Db {
     pool: SqlitePool,
 }
 
-fn current_unix_timestamp() -> Result<i64, anyhow::Error> {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|duration| duration.as_secs() as i64)
-        .map_err(|err| anyhow::anyhow!("Системное время меньше UNIX_EPOCH: {}", err))
+/// Шаг миграции схемы. Все шаги должны быть безопасны для баз 0.1.x,
+/// где часть таблиц и колонок уже создана без учёта версий.
+enum MigrationStep {
+    Sql(&'static str),
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        sql_type: &'static str,
+    },
 }
 
-impl Db {
-    pub async fn open(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
-        let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| anyhow::anyhow!("Не удалось создать директорию для БД: {}", e))?;
-        }
-
-        let opts = SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))?
-            .create_if_missing(true);
-
-        let pool = SqlitePool::connect_with(opts)
-            .await
-            .map_err(|e| anyhow::anyhow!("Не удалось подключиться к SQLite: {}", e))?;
-
-        let db = Self { pool };
-        db.migrate().await?;
-        Ok(db)
-    }
+struct Migration {
+    version: i64,
+    description: &'static str,
+    steps: &'static [MigrationStep],
+}
 
-    async fn migrate(&self) -> Result<(), anyhow::Error> {
-        sqlx::query(
+/// Реестр миграций. Новые миграции добавляются только в конец с очередным номером.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "registration_requests",
+        steps: &[MigrationStep::Sql(
             r#"
             CREATE TABLE IF NOT EXISTS registration_requests (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -160,24 +370,21 @@ impl Db {
             CREATE INDEX IF NOT EXISTS idx_requests_status ON registration_requests(status);
             CREATE INDEX IF NOT EXISTS idx_requests_tg_user ON registration_requests(tg_user_id);
             "#,
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(|e| anyhow::anyhow!("Миграция БД: {}", e))?;
-
-        let has_display_name_column = sqlx::query_scalar::<_, i64>(
-            "SELECT COUNT(*) FROM pragma_table_info('registration_requests') WHERE name = 'tg_display_name'",
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        if has_display_name_column == 0 {
-            sqlx::query("ALTER TABLE registration_requests ADD COLUMN tg_display_name TEXT")
-                .execute(&self.pool)
-                .await?;
-        }
-
-        sqlx::query(
+        )],
+    },
+    Migration {
+        version: 2,
+        description: "registration_requests.tg_display_name",
+        steps: &[MigrationStep::AddColumn {
+            table: "registration_requests",
+            column: "tg_display_name",
+            sql_type: "TEXT",
+        }],
+    },
+    Migration {
+        version: 3,
+        description: "invite_tokens",
+        steps: &[MigrationStep::Sql(
             r#"
             CREATE TABLE IF NOT EXISTS invite_tokens (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -192,45 +399,302 @@ impl Db {
                 revoked_at INTEGER
             );
             CREATE INDEX IF NOT EXISTS idx_invite_tokens_token ON invite_tokens(token);
-            CREATE INDEX IF NOT EXISTS idx_invite_tokens_active ON invite_tokens(is_active);
             CREATE INDEX IF NOT EXISTS idx_invite_tokens_expires_at ON invite_tokens(expires_at);
             "#,
+        )],
+    },
+    Migration {
+        version: 4,
+        description: "invite_tokens usage limits and revocation",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "invite_tokens",
+                column: "max_usage",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "invite_tokens",
+                column: "is_active",
+                sql_type: "INTEGER NOT NULL DEFAULT 1",
+            },
+            MigrationStep::AddColumn {
+                table: "invite_tokens",
+                column: "revoked_at",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS idx_invite_tokens_active ON invite_tokens(is_active);",
+            ),
+        ],
+    },
+    Migration {
+        version: 5,
+        description: "invite_token_uses",
+        steps: &[MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS invite_token_uses (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                token_id INTEGER NOT NULL,
+                tg_user_id INTEGER NOT NULL,
+                used_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_invite_token_uses_token ON invite_token_uses(token_id);
+            "#,
+        )],
+    },
+    Migration {
+        version: 6,
+        description: "audit_log",
+        steps: &[MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                actor_tg_id INTEGER,
+                action TEXT NOT NULL,
+                target_kind TEXT NOT NULL,
+                target TEXT NOT NULL,
+                status_before TEXT,
+                status_after TEXT,
+                created_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at);
+            CREATE INDEX IF NOT EXISTS idx_audit_log_target ON audit_log(target_kind, target);
+            CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log(actor_tg_id);
+            "#,
+        )],
+    },
+    Migration {
+        version: 7,
+        description: "time-limited access",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "registration_requests",
+                column: "access_days",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "registration_requests",
+                column: "access_expires_at",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "registration_requests",
+                column: "expiry_warned_at",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "invite_tokens",
+                column: "access_days",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS idx_requests_access_expires_at ON registration_requests(access_expires_at);",
+            ),
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "per-user limits",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "registration_requests",
+                column: "max_tcp_conns",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "registration_requests",
+                column: "max_unique_ips",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "registration_requests",
+                column: "data_quota_bytes",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "invite_tokens",
+                column: "max_tcp_conns",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "invite_tokens",
+                column: "max_unique_ips",
+                sql_type: "INTEGER",
+            },
+            MigrationStep::AddColumn {
+                table: "invite_tokens",
+                column: "data_quota_bytes",
+                sql_type: "INTEGER",
+            },
+        ],
+    },
+    Migration {
+        version: 9,
+        description: "secret_history",
+        steps: &[MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS secret_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                tg_user_id INTEGER NOT NULL,
+                secret TEXT NOT NULL,
+                replaced_at INTEGER NOT NULL,
+                replaced_by INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS idx_secret_history_user ON secret_history(tg_user_id, replaced_at);
+            "#,
+        )],
+    },
+    Migration {
+        version: 10,
+        description: "user_instances",
+        steps: &[MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_instances (
+                tg_user_id INTEGER NOT NULL,
+                instance TEXT NOT NULL,
+                PRIMARY KEY (tg_user_id, instance)
+            );
+            "#,
+        )],
+    },
+    Migration {
+        version: 11,
+        description: "admins",
+        steps: &[MigrationStep::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS admins (
+                tg_user_id INTEGER NOT NULL,
+                role TEXT NOT NULL,
+                added_by INTEGER,
+                added_at INTEGER NOT NULL,
+                PRIMARY KEY (tg_user_id, role)
+            );
+            "#,
+        )],
+    },
+];
+
+fn latest_schema_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+fn current_unix_timestamp() -> Result<i64, anyhow::Error> {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .map_err(|err| anyhow::anyhow!("Системное время меньше UNIX_EPOCH: {}", err))
+}
+
+impl Db {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| anyhow::anyhow!("Не удалось создать директорию для БД: {}", e))?;
+        }
+
+        let opts = SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))?
+            .create_if_missing(true);
+
+        let pool = SqlitePool::connect_with(opts)
+            .await
+            .map_err(|e| anyhow::anyhow!("Не удалось подключиться к SQLite: {}", e))?;
+
+        let db = Self { pool };
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    /// Применяет все миграции из [`MIGRATIONS`], которых ещё нет в `schema_version`.
+    ///
+    /// Базы версий 0.1.x не имеют таблицы `schema_version` и считаются версией 0:
+    /// шаги миграций идемпотентны, поэтому уже существующие таблицы и колонки
+    /// просто пропускаются.
+    pub async fn migrate(&self) -> Result<(), anyhow::Error> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                applied_at INTEGER NOT NULL
+            );
+            "#,
         )
         .execute(&self.pool)
         .await
-        .map_err(|e| anyhow::anyhow!("Миграция invite_tokens: {}", e))?;
+        .map_err(|e| anyhow::anyhow!("Миграция schema_version: {}", e))?;
+
+        let current = self.schema_version().await?;
+        let latest = latest_schema_version();
+        if current > latest {
+            return Err(anyhow::anyhow!(
+                "Версия схемы БД ({}) новее поддерживаемой этой сборкой ({}). \
+                 Обновите telemt-admin или используйте резервную копию БД.",
+                current,
+                latest
+            ));
+        }
 
-        self.ensure_column_exists("invite_tokens", "max_usage", "INTEGER")
-            .await?;
-        self.ensure_column_exists("invite_tokens", "is_active", "INTEGER NOT NULL DEFAULT 1")
-            .await?;
-        self.ensure_column_exists("invite_tokens", "revoked_at", "INTEGER")
+        for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+            tracing::info!(
+                version = migration.version,
+                description = migration.description,
+                "Applying database migration"
+            );
+            let mut tx = self.pool.begin().await?;
+            for step in migration.steps {
+                match step {
+                    MigrationStep::Sql(sql) => {
+                        sqlx::query(sql).execute(&mut *tx).await.map_err(|e| {
+                            anyhow::anyhow!("Миграция {}: {}", migration.version, e)
+                        })?;
+                    }
+                    MigrationStep::AddColumn {
+                        table,
+                        column,
+                        sql_type,
+                    } => {
+                        let count = sqlx::query_scalar::<_, i64>(&format!(
+                            "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = '{}'",
+                            table, column
+                        ))
+                        .fetch_one(&mut *tx)
+                        .await?;
+                        if count == 0 {
+                            sqlx::query(&format!(
+                                "ALTER TABLE {} ADD COLUMN {} {}",
+                                table, column, sql_type
+                            ))
+                            .execute(&mut *tx)
+                            .await
+                            .map_err(|e| {
+                                anyhow::anyhow!("Миграция {}: {}", migration.version, e)
+                            })?;
+                        }
+                    }
+                }
+            }
+            sqlx::query(
+                "INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)",
+            )
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(current_unix_timestamp()?)
+            .execute(&mut *tx)
             .await?;
+            tx.commit().await?;
+        }
 
         Ok(())
     }
 
-    async fn ensure_column_exists(
-        &self,
-        table: &str,
-        column: &str,
-        sql_type: &str,
-    ) -> Result<(), anyhow::Error> {
-        let count = sqlx::query_scalar::<_, i64>(&format!(
-            "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = '{}'",
-            table, column
-        ))
-        .fetch_one(&self.pool)
-        .await?;
-        if count == 0 {
-            sqlx::query(&format!(
-                "ALTER TABLE {} ADD COLUMN {} {}",
-                table, column, sql_type
-            ))
-            .execute(&self.pool)
-            .await?;
-        }
-        Ok(())
+    /// Текущая версия схемы (0 — база без применённых миграций).
+    pub async fn schema_version(&self) -> Result<i64, anyhow::Error> {
+        let version =
+            sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(version) FROM schema_version")
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(version.unwrap_or(0))
     }
 
     fn generate_invite_token() -> String {
@@ -238,11 +702,15 @@ impl Db {
     }
 
     /// Создаёт или возвращает существующую pending-заявку.
+    ///
+    /// `access_days` и `limits` — срок доступа и лимиты, которые будут выданы при одобрении заявки.
     pub async fn register_or_get(
         &self,
         tg_user_id: i64,
         tg_username: Option<&str>,
         tg_display_name: Option<&str>,
+        access_days: Option<i64>,
+        limits: UserLimits,
     ) -> Result<RegisterResult, anyhow::Error> {
         let now = current_unix_timestamp()?;
 
@@ -262,13 +730,45 @@ impl Db {
                     }
                 }
                 RequestStatus::Rejected => Ok(RegisterResult::Rejected),
+                RequestStatus::Expired => {
+                    // Доступ истёк: новый токен открывает новую заявку.
+                    sqlx::query(
+                        "UPDATE registration_requests
+                         SET status = 'pending', tg_username = ?, tg_display_name = ?, created_at = ?,
+                             access_days = ?, access_expires_at = NULL, expiry_warned_at = NULL, resolved_at = NULL,
+                             max_tcp_conns = ?, max_unique_ips = ?, data_quota_bytes = ?
+                         WHERE tg_user_id = ?",
+                    )
+                    .bind(tg_username)
+                    .bind(tg_display_name)
+                    .bind(now)
+                    .bind(access_days)
+                    .bind(limits.max_tcp_conns)
+                    .bind(limits.max_unique_ips)
+                    .bind(limits.data_quota_bytes)
+                    .bind(tg_user_id)
+                    .execute(&self.pool)
+                    .await?;
+                    let req = self
+                        .get_pending_by_tg_user(tg_user_id)
+                        .await?
+                        .ok_or_else(|| anyhow::anyhow!("только что обновили заявку"))?;
+                    Ok(RegisterResult::NewPending(req))
+                }
                 _ => {
                     sqlx::query(
-                        "UPDATE registration_requests SET tg_username = ?, tg_display_name = ?, created_at = ? WHERE tg_user_id = ?",
+                        "UPDATE registration_requests
+                         SET tg_username = ?, tg_display_name = ?, created_at = ?, access_days = ?,
+                             max_tcp_conns = ?, max_unique_ips = ?, data_quota_bytes = ?
+                         WHERE tg_user_id = ?",
                     )
                         .bind(tg_username)
                         .bind(tg_display_name)
                         .bind(now)
+                        .bind(access_days)
+                        .bind(limits.max_tcp_conns)
+                        .bind(limits.max_unique_ips)
+                        .bind(limits.data_quota_bytes)
                         .bind(tg_user_id)
                         .execute(&self.pool)
                         .await?;
@@ -278,12 +778,18 @@ impl Db {
         }
 
         sqlx::query(
-            "INSERT INTO registration_requests (tg_user_id, tg_username, tg_display_name, status, created_at) VALUES (?, ?, ?, 'pending', ?)",
+            "INSERT INTO registration_requests
+             (tg_user_id, tg_username, tg_display_name, status, created_at, access_days, max_tcp_conns, max_unique_ips, data_quota_bytes)
+             VALUES (?, ?, ?, 'pending', ?, ?, ?, ?, ?)",
         )
         .bind(tg_user_id)
         .bind(tg_username)
         .bind(tg_display_name)
         .bind(now)
+        .bind(access_days)
+        .bind(limits.max_tcp_conns)
+        .bind(limits.max_unique_ips)
+        .bind(limits.data_quota_bytes)
         .execute(&self.pool)
         .await?;
 
@@ -326,6 +832,7 @@ impl Db {
         id: i64,
         telemt_username: &str,
         secret: &str,
+        access_expires_at: Option<i64>,
     ) -> Result<Option<RegistrationRequest>, anyhow::Error> {
         let now = current_unix_timestamp()?;
 
@@ -341,11 +848,15 @@ impl Db {
         };
 
         sqlx::query(
-            "UPDATE registration_requests SET status = 'approved', telemt_username = ?, secret = ?, resolved_at = ? WHERE id = ?",
+            "UPDATE registration_requests
+             SET status = 'approved', telemt_username = ?, secret = ?, resolved_at = ?,
+                 access_expires_at = ?, expiry_warned_at = NULL
+             WHERE id = ?",
         )
         .bind(telemt_username)
         .bind(secret)
         .bind(now)
+        .bind(access_expires_at)
         .bind(id)
         .execute(&self.pool)
         .await?;
@@ -390,6 +901,9 @@ impl Db {
     }
 
     /// Устанавливает пользователя как approved (для /create без предварительной заявки).
+    ///
+    /// `access_expires_at` — момент окончания доступа, `None` — бессрочно.
+    #[allow(clippy::too_many_arguments)]
     pub async fn set_approved(
         &self,
         tg_user_id: i64,
@@ -397,6 +911,8 @@ impl Db {
         tg_display_name: Option<&str>,
         telemt_username: &str,
         secret: &str,
+        access_expires_at: Option<i64>,
+        limits: UserLimits,
     ) -> Result<(), anyhow::Error> {
         let now = current_unix_timestamp()?;
 
@@ -415,7 +931,12 @@ impl Db {
                      tg_display_name = ?,
                      telemt_username = ?,
                      secret = ?,
-                     resolved_at = ?
+                     resolved_at = ?,
+                     access_expires_at = ?,
+                     expiry_warned_at = NULL,
+                     max_tcp_conns = ?,
+                     max_unique_ips = ?,
+                     data_quota_bytes = ?
                  WHERE tg_user_id = ?",
             )
             .bind(tg_username)
@@ -423,14 +944,19 @@ impl Db {
             .bind(telemt_username)
             .bind(secret)
             .bind(now)
+            .bind(access_expires_at)
+            .bind(limits.max_tcp_conns)
+            .bind(limits.max_unique_ips)
+            .bind(limits.data_quota_bytes)
             .bind(tg_user_id)
             .execute(&self.pool)
             .await?;
         } else {
             sqlx::query(
                 "INSERT INTO registration_requests
-                 (tg_user_id, tg_username, tg_display_name, status, telemt_username, secret, created_at, resolved_at)
-                 VALUES (?, ?, ?, 'approved', ?, ?, ?, ?)",
+                 (tg_user_id, tg_username, tg_display_name, status, telemt_username, secret, created_at, resolved_at,
+                  access_expires_at, max_tcp_conns, max_unique_ips, data_quota_bytes)
+                 VALUES (?, ?, ?, 'approved', ?, ?, ?, ?, ?, ?, ?, ?)",
             )
             .bind(tg_user_id)
             .bind(tg_username)
@@ -439,6 +965,10 @@ impl Db {
             .bind(secret)
             .bind(now)
             .bind(now)
+            .bind(access_expires_at)
+            .bind(limits.max_tcp_conns)
+            .bind(limits.max_unique_ips)
+            .bind(limits.data_quota_bytes)
             .execute(&self.pool)
             .await?;
         }
@@ -461,6 +991,198 @@ impl Db {
         Ok(r.and_then(|x| x.telemt_username.zip(x.secret)))
     }
 
+    /// Сохраняет лимиты пользователя; возвращает `false`, если пользователь не найден.
+    pub async fn set_user_limits(
+        &self,
+        tg_user_id: i64,
+        limits: UserLimits,
+    ) -> Result<bool, anyhow::Error> {
+        let result = sqlx::query(
+            "UPDATE registration_requests
+             SET max_tcp_conns = ?, max_unique_ips = ?, data_quota_bytes = ?
+             WHERE tg_user_id = ?",
+        )
+        .bind(limits.max_tcp_conns)
+        .bind(limits.max_unique_ips)
+        .bind(limits.data_quota_bytes)
+        .bind(tg_user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Заменяет секрет одобренного пользователя, сохраняя прежний в `secret_history`.
+    ///
+    /// `replaced_by` — кто перевыпустил ссылку (админ или сам пользователь). С `cooldown_secs`
+    /// перевыпуск отклоняется, если `replaced_by` уже перевыпускал секрет за это время:
+    /// проверка и замена идут в одной транзакции, поэтому двойное подтверждение не
+    /// перевыпустит секрет дважды.
+    pub async fn rotate_secret(
+        &self,
+        tg_user_id: i64,
+        new_secret: &str,
+        replaced_by: i64,
+        cooldown_secs: Option<i64>,
+    ) -> Result<SecretRotation, anyhow::Error> {
+        let now = current_unix_timestamp()?;
+        // IMMEDIATE сразу берёт блокировку на запись: параллельный перевыпуск ждёт
+        // окончания этой транзакции и видит её результат.
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let old_secret = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT secret FROM registration_requests WHERE tg_user_id = ? AND status = ?",
+        )
+        .bind(tg_user_id)
+        .bind(STATUS_APPROVED)
+        .fetch_optional(&mut *tx)
+        .await?
+        .flatten();
+        let Some(old_secret) = old_secret else {
+            return Ok(SecretRotation::NotFound);
+        };
+
+        if let Some(cooldown_secs) = cooldown_secs {
+            let last = sqlx::query_scalar::<_, Option<i64>>(
+                "SELECT MAX(replaced_at) FROM secret_history WHERE tg_user_id = ? AND replaced_by = ?",
+            )
+            .bind(tg_user_id)
+            .bind(replaced_by)
+            .fetch_one(&mut *tx)
+            .await?;
+            if let Some(last) = last {
+                let next_allowed = last.saturating_add(cooldown_secs);
+                if now < next_allowed {
+                    return Ok(SecretRotation::Cooldown(next_allowed));
+                }
+            }
+        }
+
+        let history_id = sqlx::query(
+            "INSERT INTO secret_history (tg_user_id, secret, replaced_at, replaced_by) VALUES (?, ?, ?, ?)",
+        )
+        .bind(tg_user_id)
+        .bind(&old_secret)
+        .bind(now)
+        .bind(replaced_by)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        sqlx::query("UPDATE registration_requests SET secret = ? WHERE tg_user_id = ? AND status = ?")
+            .bind(new_secret)
+            .bind(tg_user_id)
+            .bind(STATUS_APPROVED)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(SecretRotation::Rotated {
+            old_secret,
+            history_id,
+        })
+    }
+
+    /// Отменяет перевыпуск, если секрет не удалось записать в конфиг telemt:
+    /// возвращает прежний секрет и удаляет запись истории, чтобы не тратить лимит.
+    pub async fn revert_secret_rotation(
+        &self,
+        tg_user_id: i64,
+        new_secret: &str,
+        old_secret: &str,
+        history_id: i64,
+    ) -> Result<bool, anyhow::Error> {
+        let mut tx = self.pool.begin().await?;
+        let restored = sqlx::query(
+            "UPDATE registration_requests SET secret = ? WHERE tg_user_id = ? AND secret = ?",
+        )
+        .bind(old_secret)
+        .bind(tg_user_id)
+        .bind(new_secret)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if restored {
+            sqlx::query("DELETE FROM secret_history WHERE id = ?")
+                .bind(history_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(restored)
+    }
+
+    /// Когда `replaced_by` последний раз перевыпускал секрет пользователя.
+    pub async fn last_secret_rotation_by(
+        &self,
+        tg_user_id: i64,
+        replaced_by: i64,
+    ) -> Result<Option<i64>, anyhow::Error> {
+        let last = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MAX(replaced_at) FROM secret_history WHERE tg_user_id = ? AND replaced_by = ?",
+        )
+        .bind(tg_user_id)
+        .bind(replaced_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(last)
+    }
+
+    /// Сколько раз секрет пользователя уже перевыпускался.
+    pub async fn count_secret_history(&self, tg_user_id: i64) -> Result<i64, anyhow::Error> {
+        let total = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM secret_history WHERE tg_user_id = ?",
+        )
+        .bind(tg_user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(total)
+    }
+
+    /// Экземпляры telemt, назначенные пользователю; пусто — все экземпляры.
+    pub async fn get_user_instances(&self, tg_user_id: i64) -> Result<Vec<String>, anyhow::Error> {
+        let names = sqlx::query_scalar::<_, String>(
+            "SELECT instance FROM user_instances WHERE tg_user_id = ? ORDER BY instance",
+        )
+        .bind(tg_user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(names)
+    }
+
+    /// Заменяет назначенные пользователю экземпляры; пустой список — все экземпляры.
+    pub async fn set_user_instances(
+        &self,
+        tg_user_id: i64,
+        instances: &[String],
+    ) -> Result<(), anyhow::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM user_instances WHERE tg_user_id = ?")
+            .bind(tg_user_id)
+            .execute(&mut *tx)
+            .await?;
+        for instance in instances {
+            sqlx::query("INSERT OR IGNORE INTO user_instances (tg_user_id, instance) VALUES (?, ?)")
+                .bind(tg_user_id)
+                .bind(instance)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Назначения экземпляров всех пользователей, у которых они есть.
+    pub async fn list_user_instances(&self) -> Result<HashMap<i64, Vec<String>>, anyhow::Error> {
+        let rows = sqlx::query_as::<_, (i64, String)>(
+            "SELECT tg_user_id, instance FROM user_instances ORDER BY tg_user_id, instance",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut map: HashMap<i64, Vec<String>> = HashMap::new();
+        for (tg_user_id, instance) in rows {
+            map.entry(tg_user_id).or_default().push(instance);
+        }
+        Ok(map)
+    }
+
     pub async fn get_request_by_tg_user(
         &self,
         tg_user_id: i64,
@@ -479,6 +1201,8 @@ impl Db {
         auto_approve: bool,
         max_usage: Option<i64>,
         created_by: Option<i64>,
+        access_days: Option<i64>,
+        limits: UserLimits,
     ) -> Result<InviteToken, anyhow::Error> {
         let now = current_unix_timestamp()?;
         let ttl_seconds = days
@@ -492,7 +1216,10 @@ impl Db {
         for _ in 0..8 {
             let token = Self::generate_invite_token();
             let result = sqlx::query(
-                "INSERT INTO invite_tokens (token, created_at, expires_at, auto_approve, created_by, max_usage) VALUES (?, ?, ?, ?, ?, ?)",
+                "INSERT INTO invite_tokens
+                 (token, created_at, expires_at, auto_approve, created_by, max_usage, access_days,
+                  max_tcp_conns, max_unique_ips, data_quota_bytes)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             )
             .bind(&token)
             .bind(now)
@@ -500,14 +1227,17 @@ impl Db {
             .bind(auto_approve)
             .bind(created_by)
             .bind(max_usage)
+            .bind(access_days)
+            .bind(limits.max_tcp_conns)
+            .bind(limits.max_unique_ips)
+            .bind(limits.data_quota_bytes)
             .execute(&self.pool)
             .await;
 
             match result {
                 Ok(_) => {
-                    created = sqlx::query_as::<_, InviteToken>(
-                        "SELECT id, token, created_at, expires_at, auto_approve, created_by, usage_count, max_usage, is_active FROM invite_tokens WHERE token = ?",
-                    )
+                    let sql = format!("{} WHERE token = ?", SELECT_INVITE_TOKEN);
+                    created = sqlx::query_as::<_, InviteToken>(&sql)
                     .bind(token)
                     .fetch_optional(&self.pool)
                     .await?;
@@ -528,27 +1258,6 @@ impl Db {
         created.ok_or_else(|| anyhow::anyhow!("Не удалось сгенерировать уникальный токен"))
     }
 
-    pub async fn list_active_invite_tokens(
-        &self,
-        limit: i64,
-    ) -> Result<Vec<InviteToken>, anyhow::Error> {
-        let now = current_unix_timestamp()?;
-        let rows = sqlx::query_as::<_, InviteToken>(
-            "SELECT id, token, created_at, expires_at, auto_approve, created_by, usage_count, max_usage, is_active
-             FROM invite_tokens
-             WHERE is_active = 1
-               AND expires_at > ?
-               AND (max_usage IS NULL OR usage_count < max_usage)
-             ORDER BY expires_at ASC
-             LIMIT ?",
-        )
-        .bind(now)
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await?;
-        Ok(rows)
-    }
-
     pub async fn revoke_invite_token(&self, token: &str) -> Result<bool, anyhow::Error> {
         let now = current_unix_timestamp()?;
         let result = sqlx::query(
@@ -561,11 +1270,69 @@ impl Db {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Считает все токены (активные, истёкшие, исчерпанные и отозванные).
+    pub async fn count_invite_tokens(&self) -> Result<i64, anyhow::Error> {
+        let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM invite_tokens")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(total)
+    }
+
+    /// Страница всех токенов, новые сверху.
+    pub async fn list_invite_tokens_page(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<InviteToken>, anyhow::Error> {
+        let sql = format!(
+            "{} ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?",
+            SELECT_INVITE_TOKEN
+        );
+        let rows = sqlx::query_as::<_, InviteToken>(&sql)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn get_invite_token(&self, token: &str) -> Result<Option<InviteToken>, anyhow::Error> {
+        let sql = format!("{} WHERE token = ?", SELECT_INVITE_TOKEN);
+        let row = sqlx::query_as::<_, InviteToken>(&sql)
+            .bind(token)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    /// Кто и когда использовал токен (последние использования сверху).
+    pub async fn list_invite_token_uses(
+        &self,
+        token_id: i64,
+        limit: i64,
+    ) -> Result<Vec<InviteTokenUse>, anyhow::Error> {
+        let rows = sqlx::query_as::<_, InviteTokenUse>(
+            "SELECT u.tg_user_id, r.tg_username, r.tg_display_name, r.status, u.used_at
+             FROM invite_token_uses u
+             LEFT JOIN registration_requests r ON r.tg_user_id = u.tg_user_id
+             WHERE u.token_id = ?
+             ORDER BY u.used_at DESC, u.id DESC
+             LIMIT ?",
+        )
+        .bind(token_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
     pub async fn consume_invite_token(
         &self,
         token: &str,
+        tg_user_id: i64,
     ) -> Result<ConsumedInviteToken, TokenConsumeError> {
         let now = current_unix_timestamp().map_err(|_| TokenConsumeError::NotFound)?;
+        let token_sql = format!("{} WHERE token = ?", SELECT_INVITE_TOKEN);
         let update_result = sqlx::query(
             "UPDATE invite_tokens
              SET usage_count = usage_count + 1
@@ -581,9 +1348,7 @@ impl Db {
         .map_err(|_| TokenConsumeError::NotFound)?;
 
         if update_result.rows_affected() == 0 {
-            let token_row = sqlx::query_as::<_, InviteToken>(
-                "SELECT id, token, created_at, expires_at, auto_approve, created_by, usage_count, max_usage, is_active FROM invite_tokens WHERE token = ?",
-            )
+            let token_row = sqlx::query_as::<_, InviteToken>(&token_sql)
             .bind(token)
             .fetch_optional(&self.pool)
             .await
@@ -604,14 +1369,30 @@ impl Db {
             return Err(TokenConsumeError::NotFound);
         }
 
-        let row = sqlx::query_as::<_, InviteToken>(
-            "SELECT id, token, created_at, expires_at, auto_approve, created_by, usage_count, max_usage, is_active FROM invite_tokens WHERE token = ?",
-        )
+        let row = sqlx::query_as::<_, InviteToken>(&token_sql)
         .bind(token)
         .fetch_optional(&self.pool)
         .await
         .map_err(|_| TokenConsumeError::NotFound)?;
         let row = row.ok_or(TokenConsumeError::NotFound)?;
+
+        if let Err(error) = sqlx::query(
+            "INSERT INTO invite_token_uses (token_id, tg_user_id, used_at) VALUES (?, ?, ?)",
+        )
+        .bind(row.id)
+        .bind(tg_user_id)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        {
+            tracing::warn!(
+                token_id = row.id,
+                tg_user_id = tg_user_id,
+                error = %error,
+                "Не удалось сохранить факт использования токена"
+            );
+        }
+
         Ok(ConsumedInviteToken {
             id: row.id,
             token: row.token,
@@ -624,6 +1405,8 @@ impl Db {
             created_by: row.created_by,
             usage_count: row.usage_count,
             max_usage: row.max_usage,
+            access_days: row.access_days,
+            limits: row.limits,
         })
     }
 
@@ -653,13 +1436,11 @@ impl Db {
         &self,
         limit: i64,
     ) -> Result<Vec<RegistrationRequest>, anyhow::Error> {
-        let rows = sqlx::query_as::<_, RegistrationRequest>(
-            "SELECT id, tg_user_id, tg_username, tg_display_name, status, telemt_username, secret, created_at
-             FROM registration_requests
-             WHERE status = ?
-             ORDER BY created_at ASC
-             LIMIT ?",
-        )
+        let sql = format!(
+            "{} WHERE status = ? ORDER BY created_at ASC LIMIT ?",
+            SELECT_REQUEST
+        );
+        let rows = sqlx::query_as::<_, RegistrationRequest>(&sql)
         .bind(STATUS_PENDING)
         .bind(limit)
         .fetch_all(&self.pool)
@@ -682,13 +1463,11 @@ impl Db {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<RegistrationRequest>, anyhow::Error> {
-        let rows = sqlx::query_as::<_, RegistrationRequest>(
-            "SELECT id, tg_user_id, tg_username, tg_display_name, status, telemt_username, secret, created_at
-             FROM registration_requests
-             WHERE status = ?
-             ORDER BY created_at DESC
-             LIMIT ? OFFSET ?",
-        )
+        let sql = format!(
+            "{} WHERE status = ? ORDER BY created_at DESC LIMIT ? OFFSET ?",
+            SELECT_REQUEST
+        );
+        let rows = sqlx::query_as::<_, RegistrationRequest>(&sql)
         .bind(STATUS_APPROVED)
         .bind(limit)
         .bind(offset)
@@ -697,16 +1476,22 @@ impl Db {
         Ok(rows)
     }
 
+    /// Все одобренные пользователи (для сверки с конфигом telemt).
+    pub async fn list_approved_users(&self) -> Result<Vec<RegistrationRequest>, anyhow::Error> {
+        let sql = format!("{} WHERE status = ? ORDER BY tg_user_id", SELECT_REQUEST);
+        let rows = sqlx::query_as::<_, RegistrationRequest>(&sql)
+            .bind(STATUS_APPROVED)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
     pub async fn get_active_user_by_tg_user(
         &self,
         tg_user_id: i64,
     ) -> Result<Option<RegistrationRequest>, anyhow::Error> {
-        let row = sqlx::query_as::<_, RegistrationRequest>(
-            "SELECT id, tg_user_id, tg_username, tg_display_name, status, telemt_username, secret, created_at
-             FROM registration_requests
-             WHERE status = ? AND tg_user_id = ?
-             LIMIT 1",
-        )
+        let sql = format!("{} WHERE status = ? AND tg_user_id = ? LIMIT 1", SELECT_REQUEST);
+        let row = sqlx::query_as::<_, RegistrationRequest>(&sql)
         .bind(STATUS_APPROVED)
         .bind(tg_user_id)
         .fetch_optional(&self.pool)
@@ -714,14 +1499,186 @@ impl Db {
         Ok(row)
     }
 
+    /// Одобренные пользователи, чей доступ истёк к моменту `now`.
+    pub async fn list_expired_users(&self, now: i64) -> Result<Vec<RegistrationRequest>, anyhow::Error> {
+        let sql = format!(
+            "{} WHERE status = ? AND access_expires_at IS NOT NULL AND access_expires_at <= ?",
+            SELECT_REQUEST
+        );
+        let rows = sqlx::query_as::<_, RegistrationRequest>(&sql)
+            .bind(STATUS_APPROVED)
+            .bind(now)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// Переводит одобренного пользователя в статус expired.
+    pub async fn mark_expired(&self, tg_user_id: i64) -> Result<bool, anyhow::Error> {
+        let now = current_unix_timestamp()?;
+        let r = sqlx::query(
+            "UPDATE registration_requests SET status = ?, resolved_at = ? WHERE tg_user_id = ? AND status = ?",
+        )
+        .bind(STATUS_EXPIRED)
+        .bind(now)
+        .bind(tg_user_id)
+        .bind(STATUS_APPROVED)
+        .execute(&self.pool)
+        .await?;
+        Ok(r.rows_affected() > 0)
+    }
+
+    /// Пользователи, чей доступ истекает до `deadline` и кого ещё не предупредили.
+    pub async fn list_users_to_warn_about_expiry(
+        &self,
+        now: i64,
+        deadline: i64,
+    ) -> Result<Vec<RegistrationRequest>, anyhow::Error> {
+        let sql = format!(
+            "{} WHERE status = ? AND expiry_warned_at IS NULL
+               AND access_expires_at IS NOT NULL AND access_expires_at > ? AND access_expires_at <= ?",
+            SELECT_REQUEST
+        );
+        let rows = sqlx::query_as::<_, RegistrationRequest>(&sql)
+            .bind(STATUS_APPROVED)
+            .bind(now)
+            .bind(deadline)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn mark_expiry_warned(&self, tg_user_id: i64) -> Result<(), anyhow::Error> {
+        let now = current_unix_timestamp()?;
+        sqlx::query("UPDATE registration_requests SET expiry_warned_at = ? WHERE tg_user_id = ?")
+            .bind(now)
+            .bind(tg_user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Роли, выданные из бота, упорядоченные по пользователю.
+    pub async fn list_admin_grants(&self) -> Result<Vec<AdminGrant>, anyhow::Error> {
+        let rows = sqlx::query_as::<_, AdminGrant>(
+            "SELECT tg_user_id, role, added_by, added_at FROM admins ORDER BY tg_user_id, role",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Выдаёт роль; `false`, если она уже была выдана.
+    pub async fn add_admin_grant(
+        &self,
+        tg_user_id: i64,
+        role: AdminRole,
+        added_by: Option<i64>,
+    ) -> Result<bool, anyhow::Error> {
+        let now = current_unix_timestamp()?;
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO admins (tg_user_id, role, added_by, added_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(tg_user_id)
+        .bind(role)
+        .bind(added_by)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Снимает роль `role` или все выданные из бота роли пользователя; возвращает число снятых.
+    pub async fn remove_admin_grants(
+        &self,
+        tg_user_id: i64,
+        role: Option<AdminRole>,
+    ) -> Result<u64, anyhow::Error> {
+        let result = match role {
+            Some(role) => {
+                sqlx::query("DELETE FROM admins WHERE tg_user_id = ? AND role = ?")
+                    .bind(tg_user_id)
+                    .bind(role)
+                    .execute(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query("DELETE FROM admins WHERE tg_user_id = ?")
+                    .bind(tg_user_id)
+                    .execute(&self.pool)
+                    .await?
+            }
+        };
+        Ok(result.rows_affected())
+    }
+
+    pub async fn record_audit(
+        &self,
+        actor_tg_id: Option<i64>,
+        action: AuditAction,
+        target_kind: AuditTargetKind,
+        target: &str,
+        status_before: Option<&str>,
+        status_after: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        let now = current_unix_timestamp()?;
+        sqlx::query(
+            "INSERT INTO audit_log (actor_tg_id, action, target_kind, target, status_before, status_after, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(actor_tg_id)
+        .bind(action)
+        .bind(target_kind)
+        .bind(target)
+        .bind(status_before)
+        .bind(status_after)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn count_audit_entries(&self, filter: AuditFilter) -> Result<i64, anyhow::Error> {
+        let total = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) FROM audit_log WHERE {}",
+            filter.where_clause()
+        ))
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(total)
+    }
+
+    /// Страница журнала аудита, новые записи сверху.
+    pub async fn list_audit_page(
+        &self,
+        filter: AuditFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<AuditEntry>, anyhow::Error> {
+        let rows = sqlx::query_as::<_, AuditEntry>(&format!(
+            "SELECT id, actor_tg_id, action, target_kind, target, status_before, status_after, created_at
+             FROM audit_log
+             WHERE {}
+             ORDER BY created_at DESC, id DESC
+             LIMIT ? OFFSET ?",
+            filter.where_clause()
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
     pub async fn admin_stats(&self) -> Result<AdminStats, anyhow::Error> {
-        let row = sqlx::query_as::<_, (i64, i64, i64, i64, i64)>(
+        let row = sqlx::query_as::<_, (i64, i64, i64, i64, i64, i64)>(
             "SELECT
                 COUNT(*) AS total,
                 SUM(CASE WHEN status = 'pending' THEN 1 ELSE 0 END) AS pending,
                 SUM(CASE WHEN status = 'approved' THEN 1 ELSE 0 END) AS approved,
                 SUM(CASE WHEN status = 'rejected' THEN 1 ELSE 0 END) AS rejected,
-                SUM(CASE WHEN status = 'deleted' THEN 1 ELSE 0 END) AS deleted
+                SUM(CASE WHEN status = 'deleted' THEN 1 ELSE 0 END) AS deleted,
+                SUM(CASE WHEN status = 'expired' THEN 1 ELSE 0 END) AS expired
              FROM registration_requests",
         )
         .fetch_one(&self.pool)
@@ -733,6 +1690,246 @@ impl Db {
             approved: row.2,
             rejected: row.3,
             deleted: row.4,
+            expired: row.5,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    /// Первые выпуски 0.1.x: только заявки, без `tg_display_name` и invite-токенов.
+    const SCHEMA_0_1_EARLY: &str = r#"
+        CREATE TABLE registration_requests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tg_user_id INTEGER NOT NULL,
+            tg_username TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            telemt_username TEXT,
+            secret TEXT,
+            created_at INTEGER NOT NULL,
+            resolved_at INTEGER,
+            UNIQUE(tg_user_id)
+        );
+        CREATE INDEX idx_requests_status ON registration_requests(status);
+        CREATE INDEX idx_requests_tg_user ON registration_requests(tg_user_id);
+    "#;
+
+    /// Промежуточные 0.1.x: invite-токены без лимита использований и отзыва.
+    const SCHEMA_0_1_TOKENS: &str = r#"
+        CREATE TABLE registration_requests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tg_user_id INTEGER NOT NULL,
+            tg_username TEXT,
+            tg_display_name TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            telemt_username TEXT,
+            secret TEXT,
+            created_at INTEGER NOT NULL,
+            resolved_at INTEGER,
+            UNIQUE(tg_user_id)
+        );
+        CREATE TABLE invite_tokens (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            token TEXT UNIQUE NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            auto_approve INTEGER NOT NULL DEFAULT 0,
+            created_by INTEGER,
+            usage_count INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX idx_invite_tokens_token ON invite_tokens(token);
+        CREATE INDEX idx_invite_tokens_expires_at ON invite_tokens(expires_at);
+    "#;
+
+    /// 0.1.4: последняя схема без `schema_version`.
+    const SCHEMA_0_1_4: &str = r#"
+        CREATE TABLE registration_requests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tg_user_id INTEGER NOT NULL,
+            tg_username TEXT,
+            tg_display_name TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            telemt_username TEXT,
+            secret TEXT,
+            created_at INTEGER NOT NULL,
+            resolved_at INTEGER,
+            UNIQUE(tg_user_id)
+        );
+        CREATE TABLE invite_tokens (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            token TEXT UNIQUE NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            auto_approve INTEGER NOT NULL DEFAULT 0,
+            created_by INTEGER,
+            usage_count INTEGER NOT NULL DEFAULT 0,
+            max_usage INTEGER,
+            is_active INTEGER NOT NULL DEFAULT 1,
+            revoked_at INTEGER
+        );
+        CREATE INDEX idx_invite_tokens_token ON invite_tokens(token);
+        CREATE INDEX idx_invite_tokens_active ON invite_tokens(is_active);
+        CREATE INDEX idx_invite_tokens_expires_at ON invite_tokens(expires_at);
+    "#;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    /// Одно соединение: у каждого соединения `sqlite::memory:` своя база.
+    async fn fixture_db(schema: &str) -> Db {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::raw_sql(schema).execute(&pool).await.unwrap();
+        Db { pool }
+    }
+
+    async fn seed_requests(db: &Db) {
+        sqlx::query(
+            "INSERT INTO registration_requests (tg_user_id, tg_username, status, telemt_username, secret, created_at, resolved_at) \
+             VALUES (100, 'alice', 'approved', 'tg_100', ?, 1700000000, 1700000100), \
+                    (200, 'bob', 'pending', NULL, NULL, 1700000200, NULL)",
+        )
+        .bind(SECRET)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    }
+
+    async fn seed_token(db: &Db) {
+        sqlx::query(
+            "INSERT INTO invite_tokens (token, created_at, expires_at, auto_approve, created_by, usage_count) \
+             VALUES ('oldtoken01', 1700000000, 1700600000, 1, 1, 3)",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    }
+
+    async fn applied_migrations(db: &Db) -> Vec<(i64, i64)> {
+        sqlx::query_as::<_, (i64, i64)>("SELECT version, applied_at FROM schema_version ORDER BY version")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap()
+    }
+
+    /// Миграция до текущей версии, сохранность заявок и повторный запуск без изменений.
+    async fn assert_upgrade(db: &Db) {
+        db.migrate().await.unwrap();
+        assert_eq!(db.schema_version().await.unwrap(), latest_schema_version());
+
+        let approved = db.get_request_by_tg_user(100).await.unwrap().unwrap();
+        assert_eq!(approved.status, RequestStatus::Approved);
+        assert_eq!(approved.tg_username.as_deref(), Some("alice"));
+        assert_eq!(approved.tg_display_name, None);
+        assert_eq!(approved.telemt_username.as_deref(), Some("tg_100"));
+        assert_eq!(approved.secret.as_deref(), Some(SECRET));
+        assert_eq!(approved.access_expires_at, None);
+        assert_eq!(approved.access_days, None);
+        assert_eq!(approved.limits, UserLimits::default());
+        assert_eq!(db.get_approved(100).await.unwrap(), Some(("tg_100".to_string(), SECRET.to_string())));
+
+        let pending = db.list_pending_requests(10).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].tg_user_id, 200);
+
+        let applied = applied_migrations(db).await;
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        db.migrate().await.unwrap();
+        assert_eq!(applied_migrations(db).await, applied);
+        assert_eq!(db.schema_version().await.unwrap(), latest_schema_version());
+    }
+
+    async fn assert_token_defaults(db: &Db) {
+        let token = db.get_invite_token("oldtoken01").await.unwrap().unwrap();
+        assert!(token.auto_approve);
+        assert_eq!(token.usage_count, 3);
+        assert_eq!(token.max_usage, None);
+        assert!(token.is_active);
+        assert_eq!(token.revoked_at, None);
+        assert_eq!(token.access_days, None);
+        assert_eq!(token.limits, UserLimits::default());
+    }
+
+    #[tokio::test]
+    async fn upgrades_early_schema_without_tokens() {
+        let db = fixture_db(SCHEMA_0_1_EARLY).await;
+        seed_requests(&db).await;
+        assert_upgrade(&db).await;
+        assert!(db.list_invite_tokens_page(10, 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn upgrades_tokens_without_usage_limits() {
+        let db = fixture_db(SCHEMA_0_1_TOKENS).await;
+        seed_requests(&db).await;
+        seed_token(&db).await;
+        assert_upgrade(&db).await;
+        assert_token_defaults(&db).await;
+    }
+
+    #[tokio::test]
+    async fn upgrades_0_1_4_schema() {
+        let db = fixture_db(SCHEMA_0_1_4).await;
+        seed_requests(&db).await;
+        seed_token(&db).await;
+        assert_upgrade(&db).await;
+        assert_token_defaults(&db).await;
+    }
+
+    #[tokio::test]
+    async fn migrates_empty_database() {
+        let db = fixture_db("").await;
+        db.migrate().await.unwrap();
+        assert_eq!(db.schema_version().await.unwrap(), latest_schema_version());
+        let applied = applied_migrations(&db).await;
+        db.migrate().await.unwrap();
+        assert_eq!(applied_migrations(&db).await, applied);
+    }
+
+    #[tokio::test]
+    async fn secret_rotation_respects_cooldown_and_reverts() {
+        let db = fixture_db(SCHEMA_0_1_4).await;
+        seed_requests(&db).await;
+        db.migrate().await.unwrap();
+
+        let SecretRotation::Rotated { old_secret, history_id } =
+            db.rotate_secret(100, "new-secret", 100, Some(3_600)).await.unwrap()
+        else {
+            panic!("первый перевыпуск должен пройти");
+        };
+        assert_eq!(old_secret, SECRET);
+        // Повторное подтверждение упирается в лимит внутри транзакции.
+        assert!(matches!(
+            db.rotate_secret(100, "newer-secret", 100, Some(3_600)).await.unwrap(),
+            SecretRotation::Cooldown(_)
+        ));
+        assert_eq!(db.get_approved(100).await.unwrap().unwrap().1, "new-secret");
+
+        assert!(db.revert_secret_rotation(100, "new-secret", &old_secret, history_id).await.unwrap());
+        assert_eq!(db.get_approved(100).await.unwrap().unwrap().1, SECRET);
+        assert_eq!(db.count_secret_history(100).await.unwrap(), 0);
+
+        assert!(matches!(
+            db.rotate_secret(200, "new-secret", 1, None).await.unwrap(),
+            SecretRotation::NotFound
+        ));
+    }
+
+    #[tokio::test]
+    async fn refuses_database_newer_than_binary() {
+        let db = fixture_db(SCHEMA_0_1_4).await;
+        db.migrate().await.unwrap();
+        sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, 'future', 0)")
+            .bind(latest_schema_version() + 1)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let error = db.migrate().await.unwrap_err();
+        assert!(error.to_string().contains("новее поддерживаемой"), "{}", error);
+    }
+}
@@ -0,0 +1,73 @@
+//! Общие части HTTP-серверов агента и API администрирования: проверка токена,
+//! разбор тела запроса и JSON-ответы вида `{"error": "..."}` при ошибках.
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::{header, Request, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+/// Ошибка в запросе клиента — отвечаем 400, а не 500.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct BadRequest(pub String);
+
+/// Проверяет заголовок `Authorization: Bearer <token>`.
+pub fn authorized(request: &Request<Incoming>, token: &str) -> bool {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|provided| constant_time_eq(provided.as_bytes(), token.as_bytes()))
+}
+
+/// Сравнивает SHA-256 обоих токенов без раннего выхода: время ответа не выдаёт
+/// ни совпавший префикс, ни длину настоящего токена.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    Sha256::digest(left)
+        .iter()
+        .zip(Sha256::digest(right).iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+pub fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, anyhow::Error> {
+    serde_json::from_slice(body)
+        .map_err(|e| BadRequest(format!("Некорректный JSON: {}", e)).into())
+}
+
+pub fn json_response<T: Serialize>(value: &T) -> Result<Response<Full<Bytes>>, anyhow::Error> {
+    let body = serde_json::to_vec(value)?;
+    Ok(build_response(StatusCode::OK, body))
+}
+
+pub fn error_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    let body = serde_json::to_vec(&json!({ "error": message })).unwrap_or_default();
+    build_response(status, body)
+}
+
+fn build_response(status: StatusCode, body: Vec<u8>) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_comparison_ignores_length() {
+        let token = b"agent-test-token-0123456789";
+        assert!(constant_time_eq(token, token));
+        assert!(!constant_time_eq(token, b"agent-test-token"));
+        assert!(!constant_time_eq(b"", token));
+    }
+}
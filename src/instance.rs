@@ -0,0 +1,335 @@
+//! Экземпляры telemt, которыми управляет бот: у каждого свой конфиг и сервис.
+//!
+//! Без `[[instances]]` в конфиге бота экземпляр один — `default` из полей
+//! `telemt_config_path`, `service_name` и `[service]` верхнего уровня.
+//! Экземпляр с `[instances.agent]` управляется через агент на другом сервере.
+
+use crate::agent::AgentClient;
+use crate::config::Config;
+use crate::service::{ServiceController, ServiceResult};
+use crate::telemt_cfg::{BackupInfo, TelemtConfig, TelemtLinkParams, UserPolicy};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+pub struct TelemtInstance {
+    pub name: String,
+    pub service_name: String,
+    pub telemt_cfg: ConfigStore,
+    pub service: ServiceController,
+}
+
+impl TelemtInstance {
+    pub async fn start(&self) -> ServiceResult {
+        match self.validate_config().await {
+            Some(failure) => failure,
+            None => self.service.start().await,
+        }
+    }
+
+    pub async fn restart(&self) -> ServiceResult {
+        match self.validate_config().await {
+            Some(failure) => failure,
+            None => self.service.restart().await,
+        }
+    }
+
+    pub async fn reload(&self) -> ServiceResult {
+        match self.validate_config().await {
+            Some(failure) => failure,
+            None => self.service.reload().await,
+        }
+    }
+
+    /// Проверяет конфиг перед запуском сервиса: с неверным конфигом telemt не
+    /// поднимется, поэтому действие блокируется и ошибка уходит в результат.
+    pub async fn validate_config(&self) -> Option<ServiceResult> {
+        let error = self.telemt_cfg.validate().await.err()?;
+        tracing::warn!(
+            instance = %self.name,
+            error = %error,
+            "telemt config failed validation, service action blocked"
+        );
+        Some(ServiceResult {
+            success: false,
+            stdout: String::new(),
+            stderr: error.to_string(),
+        })
+    }
+}
+
+/// Конфиг telemt экземпляра: локальный файл или файл на сервере агента.
+///
+/// Операции с локальным файлом синхронные (запись, `telemt_check_command`),
+/// поэтому выполняются в пуле блокирующих задач, а не на потоках tokio.
+pub enum ConfigStore {
+    Local(Arc<TelemtConfig>),
+    Remote(AgentClient),
+}
+
+impl ConfigStore {
+    /// Где лежит конфиг: путь к файлу или адрес агента.
+    pub fn location(&self) -> String {
+        match self {
+            Self::Local(config) => config.path().display().to_string(),
+            Self::Remote(agent) => agent.url().to_string(),
+        }
+    }
+
+    /// Выполняет операцию с локальным конфигом через `spawn_blocking`.
+    async fn blocking<T, F>(config: &Arc<TelemtConfig>, operation: F) -> Result<T, anyhow::Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&TelemtConfig) -> Result<T, anyhow::Error> + Send + 'static,
+    {
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || operation(&config))
+            .await
+            .map_err(|e| anyhow::anyhow!("Операция с конфигом telemt прервана: {}", e))?
+    }
+
+    pub async fn validate(&self) -> Result<(), anyhow::Error> {
+        match self {
+            Self::Local(config) => Self::blocking(config, |config| config.validate()).await,
+            Self::Remote(agent) => agent.validate_config().await,
+        }
+    }
+
+    pub async fn read_link_params(&self) -> Result<TelemtLinkParams, anyhow::Error> {
+        match self {
+            Self::Local(config) => Self::blocking(config, |config| config.read_link_params()).await,
+            Self::Remote(agent) => agent.read_link_params().await,
+        }
+    }
+
+    pub async fn read_users(&self) -> Result<BTreeMap<String, String>, anyhow::Error> {
+        match self {
+            Self::Local(config) => Self::blocking(config, |config| config.read_users()).await,
+            Self::Remote(agent) => agent.read_users().await,
+        }
+    }
+
+    pub async fn read_policies(&self) -> Result<BTreeMap<String, UserPolicy>, anyhow::Error> {
+        match self {
+            Self::Local(config) => Self::blocking(config, |config| config.read_policies()).await,
+            Self::Remote(agent) => agent.read_policies().await,
+        }
+    }
+
+    pub async fn upsert_user(
+        &self,
+        username: &str,
+        secret: &str,
+        policy: &UserPolicy,
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            Self::Local(config) => {
+                let (username, secret, policy) = (username.to_string(), secret.to_string(), *policy);
+                Self::blocking(config, move |config| config.upsert_user(&username, &secret, &policy)).await
+            }
+            Self::Remote(agent) => agent.upsert_user(username, secret, policy).await,
+        }
+    }
+
+    pub async fn remove_user(&self, username: &str) -> Result<bool, anyhow::Error> {
+        match self {
+            Self::Local(config) => {
+                let username = username.to_string();
+                Self::blocking(config, move |config| config.remove_user(&username)).await
+            }
+            Self::Remote(agent) => agent.remove_user(username).await,
+        }
+    }
+
+    pub async fn set_user_policy(&self, username: &str, policy: &UserPolicy) -> Result<bool, anyhow::Error> {
+        match self {
+            Self::Local(config) => {
+                let (username, policy) = (username.to_string(), *policy);
+                Self::blocking(config, move |config| config.set_user_policy(&username, &policy)).await
+            }
+            Self::Remote(agent) => agent.set_user_policy(username, policy).await,
+        }
+    }
+
+    pub async fn apply_users_changes(
+        &self,
+        upserts: &[(String, String, UserPolicy)],
+        removals: &[String],
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            Self::Local(config) => {
+                let (upserts, removals) = (upserts.to_vec(), removals.to_vec());
+                Self::blocking(config, move |config| config.apply_users_changes(&upserts, &removals)).await
+            }
+            Self::Remote(agent) => agent.apply_users_changes(upserts, removals).await,
+        }
+    }
+
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, anyhow::Error> {
+        match self {
+            Self::Local(config) => Self::blocking(config, |config| config.list_backups()).await,
+            Self::Remote(agent) => agent.list_backups().await,
+        }
+    }
+
+    pub async fn rollback(&self, id: &str) -> Result<(), anyhow::Error> {
+        match self {
+            Self::Local(config) => {
+                let id = id.to_string();
+                Self::blocking(config, move |config| config.rollback(&id)).await
+            }
+            Self::Remote(agent) => agent.rollback(id).await,
+        }
+    }
+
+    pub async fn rollback_pending(&self) -> Result<Option<String>, anyhow::Error> {
+        match self {
+            Self::Local(config) => Self::blocking(config, |config| config.rollback_pending()).await,
+            Self::Remote(agent) => agent.rollback_pending().await,
+        }
+    }
+
+    pub async fn mark_applied(&self) -> Result<(), anyhow::Error> {
+        match self {
+            Self::Local(config) => {
+                Self::blocking(config, |config| {
+                    config.mark_applied();
+                    Ok(())
+                })
+                .await
+            }
+            Self::Remote(agent) => agent.mark_applied().await,
+        }
+    }
+}
+
+/// Все экземпляры в порядке из конфига; первый считается основным.
+pub struct Instances(Vec<Arc<TelemtInstance>>);
+
+impl Instances {
+    pub fn from_config(config: &Config) -> Result<Self, anyhow::Error> {
+        let mut instances = Vec::new();
+        for instance in config.telemt_instances() {
+            let (telemt_cfg, service) = match &instance.agent {
+                Some(agent) => {
+                    let client = AgentClient::new(agent)?;
+                    tracing::info!(
+                        instance = %instance.name,
+                        agent = %client.url(),
+                        "Remote telemt instance via agent"
+                    );
+                    (
+                        ConfigStore::Remote(client.clone()),
+                        ServiceController::new(Arc::new(client)),
+                    )
+                }
+                None => (
+                    ConfigStore::Local(Arc::new(
+                        TelemtConfig::new(&instance.telemt_config_path)
+                            .with_backups(
+                                config.backups.instance_dir(&instance.name),
+                                config.backups.keep,
+                            )
+                            .with_check_command(instance.telemt_check_command.clone()),
+                    )),
+                    ServiceController::from_config(&instance),
+                ),
+            };
+            instances.push(Arc::new(TelemtInstance {
+                telemt_cfg,
+                service,
+                service_name: instance.service_name,
+                name: instance.name,
+            }));
+        }
+        Ok(Self(instances))
+    }
+
+    pub fn all(&self) -> &[Arc<TelemtInstance>] {
+        &self.0
+    }
+
+    pub fn primary(&self) -> &Arc<TelemtInstance> {
+        // Config::telemt_instances всегда возвращает хотя бы один экземпляр.
+        &self.0[0]
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<TelemtInstance>> {
+        self.0.iter().find(|instance| instance.name == name)
+    }
+
+    pub fn is_multi(&self) -> bool {
+        self.0.len() > 1
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|instance| instance.name.as_str()).collect()
+    }
+
+    /// Экземпляры пользователя по назначениям из БД.
+    ///
+    /// Без назначений пользователь получает доступ ко всем экземплярам. Если
+    /// назначенных экземпляров больше нет в конфиге, доступа нет нигде: иначе
+    /// удаление экземпляра открыло бы его пользователям все остальные.
+    pub fn select(&self, assigned: &[String]) -> Vec<Arc<TelemtInstance>> {
+        if assigned.is_empty() {
+            return self.0.clone();
+        }
+        let selected: Vec<Arc<TelemtInstance>> = self
+            .0
+            .iter()
+            .filter(|instance| assigned.contains(&instance.name))
+            .cloned()
+            .collect();
+        if selected.is_empty() {
+            tracing::warn!(
+                assigned = ?assigned,
+                "Назначенных экземпляров нет в конфиге, доступ пользователя не выдаётся"
+            );
+        }
+        selected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instances() -> Instances {
+        let config: Config = toml::from_str(
+            r#"
+            admin_ids = []
+
+            [[instances]]
+            name = "main"
+            service = { backend = "noop" }
+
+            [[instances]]
+            name = "backup"
+            service = { backend = "noop" }
+            "#,
+        )
+        .unwrap();
+        Instances::from_config(&config).unwrap()
+    }
+
+    fn names(selected: &[Arc<TelemtInstance>]) -> Vec<&str> {
+        selected.iter().map(|instance| instance.name.as_str()).collect()
+    }
+
+    #[test]
+    fn no_assignments_select_every_instance() {
+        let instances = instances();
+        assert_eq!(names(&instances.select(&[])), vec!["main", "backup"]);
+        assert_eq!(names(&instances.select(&["backup".to_string()])), vec!["backup"]);
+    }
+
+    #[test]
+    fn stale_assignments_select_nothing() {
+        let instances = instances();
+        assert!(instances.select(&["removed".to_string()]).is_empty());
+        assert_eq!(
+            names(&instances.select(&["removed".to_string(), "main".to_string()])),
+            vec!["main"]
+        );
+    }
+}
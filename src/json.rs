@@ -0,0 +1,72 @@
+//! JSON-представления пользователей, заявок, токенов и отчётов синхронизации:
+//! общий формат вывода `--json` в CLI и ответов HTTP API.
+
+use crate::db::{InviteToken, RegistrationRequest};
+use crate::link::UserLinks;
+use crate::sync::{ManagedUser, SyncReport};
+use serde_json::{json, Value};
+
+/// Пользователь без секрета; `instances` пуст — доступ ко всем экземплярам.
+pub fn user_json(user: &RegistrationRequest, instances: &[String]) -> Value {
+    json!({
+        "tg_user_id": user.tg_user_id,
+        "tg_username": user.tg_username,
+        "tg_display_name": user.tg_display_name,
+        "telemt_username": user.telemt_username,
+        "access_expires_at": user.access_expires_at,
+        "limits": user.limits,
+        "instances": instances,
+    })
+}
+
+/// Ожидающая заявка на регистрацию.
+pub fn request_json(request: &RegistrationRequest) -> Value {
+    json!({
+        "id": request.id,
+        "tg_user_id": request.tg_user_id,
+        "tg_username": request.tg_username,
+        "tg_display_name": request.tg_display_name,
+        "status": request.status.to_string(),
+        "created_at": request.created_at,
+        "access_days": request.access_days,
+        "limits": request.limits,
+    })
+}
+
+/// Ссылки пользователя по экземплярам telemt.
+pub fn links_json(links: &UserLinks) -> Value {
+    links
+        .instances()
+        .map(|(instance, links)| json!({ "instance": instance, "links": links.all().collect::<Vec<_>>() }))
+        .collect()
+}
+
+pub fn token_json(token: &InviteToken, now: i64) -> Value {
+    json!({
+        "token": token.token,
+        "status": token.status_at(now).as_str(),
+        "auto_approve": token.auto_approve,
+        "created_at": token.created_at,
+        "expires_at": token.expires_at,
+        "created_by": token.created_by,
+        "usage_count": token.usage_count,
+        "max_usage": token.max_usage,
+        "access_days": token.access_days,
+        "limits": token.limits,
+    })
+}
+
+pub fn sync_report_json(report: &SyncReport) -> Value {
+    let names = |users: &[ManagedUser]| -> Vec<String> {
+        users.iter().map(|user| user.name.clone()).collect()
+    };
+    json!({
+        "instance": report.instance,
+        "orphans_in_config": report.orphans_in_config,
+        "missing_in_config": names(&report.missing_in_config),
+        "secret_mismatches": names(&report.secret_mismatches),
+        "policy_mismatches": names(&report.policy_mismatches),
+        "approved_without_secret": report.approved_without_secret,
+        "unmanaged_in_config": report.unmanaged_in_config,
+    })
+}
@@ -1,8 +1,29 @@
-//! Генерация fake-TLS ссылок на прокси telemt.
+//! Генерация ссылок на прокси telemt для режимов fake-TLS, secure и classic.
 
 use crate::telemt_cfg::TelemtLinkParams;
 use rand::RngCore;
-use std::fmt::Write;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use std::net::{IpAddr, Ipv6Addr};
+
+/// Вид ссылки на прокси.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyLinkForm {
+    /// `https://t.me/proxy?...` — открывается из браузера, почты и любого клиента
+    Https,
+    /// `tg://proxy?...` — открывается только установленным Telegram
+    Tg,
+}
+
+impl ProxyLinkForm {
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Https => "https://t.me/proxy",
+            Self::Tg => "tg://proxy",
+        }
+    }
+}
 
 /// Генерирует 32 hex-символа (16 байт) для секрета пользователя.
 pub fn generate_user_secret() -> String {
@@ -11,6 +32,28 @@ pub fn generate_user_secret() -> String {
     hex::encode(bytes)
 }
 
+/// Режим MTProto-секрета, включённый в `[general.modes]` telemt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretMode {
+    /// Fake-TLS: `ee` + секрет + hex(tls_domain)
+    Tls,
+    /// Secure (padded): `dd` + секрет
+    Secure,
+    /// Классический режим: секрет без префикса
+    Classic,
+}
+
+impl SecretMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tls => "Fake-TLS",
+            Self::Secure => "Secure (dd)",
+            Self::Classic => "Classic",
+        }
+    }
+}
+
 /// Формирует fake-TLS секрет: ee + user_secret (32 hex) + hex(tls_domain).
 pub fn build_fake_tls_secret(user_secret: &str, tls_domain: &str) -> String {
     let domain_hex = hex::encode(tls_domain.as_bytes());
@@ -21,17 +64,287 @@ pub fn build_fake_tls_secret(user_secret: &str, tls_domain: &str) -> String {
     s
 }
 
-/// Формирует tg://proxy ссылку.
+/// Формирует секрет клиента для режима; для fake-TLS нужен `tls_domain`.
+pub fn build_secret(user_secret: &str, mode: SecretMode, tls_domain: Option<&str>) -> Option<String> {
+    match mode {
+        SecretMode::Tls => tls_domain.map(|domain| build_fake_tls_secret(user_secret, domain)),
+        SecretMode::Secure => Some(format!("dd{}", user_secret)),
+        SecretMode::Classic => Some(user_secret.to_string()),
+    }
+}
+
+/// Адрес, по которому клиенты подключаются к прокси.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyEndpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl ProxyEndpoint {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+        }
+    }
+
+    /// Подпись адреса для пользователя: IPv4, IPv6 или доменное имя.
+    pub fn label(&self) -> &'static str {
+        match self.host.trim_matches(['[', ']']).parse::<IpAddr>() {
+            Ok(IpAddr::V4(_)) => "IPv4",
+            Ok(IpAddr::V6(_)) => "IPv6",
+            Err(_) => "Домен",
+        }
+    }
+
+    /// Значение параметра `server`: IPv6 в квадратных скобках, закодированное для URL.
+    fn server_param(&self) -> String {
+        let host = self.host.trim_matches(['[', ']']);
+        let host = match host.parse::<Ipv6Addr>() {
+            Ok(_) => format!("[{}]", host),
+            Err(_) => host.to_string(),
+        };
+        urlencoding::encode(&host).into_owned()
+    }
+}
+
+/// Формирует ссылку на прокси нужного вида и режима для одного адреса.
 pub fn build_proxy_link(
     params: &TelemtLinkParams,
+    endpoint: &ProxyEndpoint,
     user_secret: &str,
-) -> Result<String, std::fmt::Error> {
-    let secret = build_fake_tls_secret(user_secret, &params.tls_domain);
+    form: ProxyLinkForm,
+    mode: SecretMode,
+) -> Result<String, fmt::Error> {
+    let secret = build_secret(user_secret, mode, params.tls_domain.as_deref()).ok_or(fmt::Error)?;
     let mut url = String::new();
     write!(
         url,
-        "tg://proxy?server={}&port={}&secret={}",
-        params.host, params.port, secret
+        "{}?server={}&port={}&secret={}",
+        form.prefix(),
+        endpoint.server_param(),
+        endpoint.port,
+        secret
     )?;
     Ok(url)
 }
+
+/// Ссылки одного адреса в одном режиме во всех видах из конфига.
+#[derive(Debug, Clone)]
+struct LinkGroup {
+    endpoint: ProxyEndpoint,
+    mode: SecretMode,
+    links: Vec<String>,
+}
+
+/// Ссылки пользователя для всех адресов и режимов telemt во всех видах из конфига.
+///
+/// Основной адрес выводится полностью, запасные — с подписью и только первым
+/// видом ссылки. Первая ссылка основного адреса идёт в QR-код.
+#[derive(Debug, Clone)]
+pub struct ProxyLinks(Vec<LinkGroup>);
+
+impl ProxyLinks {
+    pub fn build(
+        params: &TelemtLinkParams,
+        user_secret: &str,
+        forms: &[ProxyLinkForm],
+    ) -> Result<Self, fmt::Error> {
+        let mut groups = Vec::new();
+        for endpoint in &params.endpoints {
+            for mode in &params.modes {
+                let links = forms
+                    .iter()
+                    .map(|form| build_proxy_link(params, endpoint, user_secret, *form, *mode))
+                    .collect::<Result<Vec<_>, _>>()?;
+                groups.push(LinkGroup {
+                    endpoint: endpoint.clone(),
+                    mode: *mode,
+                    links,
+                });
+            }
+        }
+        Ok(Self(groups))
+    }
+
+    /// Все ссылки: для каждого адреса и режима во всех видах.
+    pub fn all(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .iter()
+            .flat_map(|group| group.links.iter().map(String::as_str))
+    }
+
+    /// Ссылка для QR-кода.
+    pub fn primary(&self) -> &str {
+        self.0
+            .first()
+            .and_then(|group| group.links.first())
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ProxyLinks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(primary) = self.0.first().map(|group| &group.endpoint) else {
+            return Ok(());
+        };
+        let (main, alternates): (Vec<&LinkGroup>, Vec<&LinkGroup>) =
+            self.0.iter().partition(|group| group.endpoint == *primary);
+
+        let labeled = main.len() > 1;
+        let sections: Vec<String> = main
+            .iter()
+            .map(|group| {
+                let links = group.links.join("\n\n");
+                if labeled {
+                    format!("{}:\n{}", group.mode.label(), links)
+                } else {
+                    links
+                }
+            })
+            .collect();
+        f.write_str(&sections.join("\n\n"))?;
+
+        if !alternates.is_empty() {
+            f.write_str("\n\nЗапасные адреса:")?;
+            for group in alternates {
+                let mode = if labeled {
+                    format!(", {}", group.mode.label())
+                } else {
+                    String::new()
+                };
+                write!(
+                    f,
+                    "\n\n{} {}{}:\n{}",
+                    group.endpoint.label(),
+                    group.endpoint.host,
+                    mode,
+                    group.links.first().map(String::as_str).unwrap_or_default()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ссылки пользователя на всех назначенных ему экземплярах telemt.
+///
+/// Если экземпляров у бота несколько, блок ссылок каждого подписан его именем.
+/// В QR-код идёт основная ссылка первого экземпляра.
+#[derive(Debug, Clone)]
+pub struct UserLinks {
+    labeled: bool,
+    instances: Vec<(String, ProxyLinks)>,
+}
+
+impl UserLinks {
+    pub fn new(labeled: bool) -> Self {
+        Self {
+            labeled,
+            instances: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, instance: impl Into<String>, links: ProxyLinks) {
+        self.instances.push((instance.into(), links));
+    }
+
+    /// Ссылки по экземплярам telemt.
+    pub fn instances(&self) -> impl Iterator<Item = (&str, &ProxyLinks)> {
+        self.instances
+            .iter()
+            .map(|(instance, links)| (instance.as_str(), links))
+    }
+
+    /// Ссылка для QR-кода.
+    pub fn primary(&self) -> &str {
+        self.instances
+            .first()
+            .map(|(_, links)| links.primary())
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for UserLinks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (instance, links)) in self.instances.iter().enumerate() {
+            if index > 0 {
+                f.write_str("\n\n")?;
+            }
+            if self.labeled {
+                write!(f, "📡 {}:\n{}", instance, links)?;
+            } else {
+                write!(f, "{}", links)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+    /// hex("www.google.com")
+    const DOMAIN_HEX: &str = "7777772e676f6f676c652e636f6d";
+
+    #[test]
+    fn classic_secret_is_raw_hex() {
+        assert_eq!(build_secret(SECRET, SecretMode::Classic, None).as_deref(), Some(SECRET));
+        assert_eq!(
+            build_secret(SECRET, SecretMode::Classic, Some("www.google.com")).as_deref(),
+            Some(SECRET)
+        );
+    }
+
+    #[test]
+    fn secure_secret_has_dd_prefix() {
+        assert_eq!(
+            build_secret(SECRET, SecretMode::Secure, None).as_deref(),
+            Some("dd0123456789abcdef0123456789abcdef")
+        );
+    }
+
+    #[test]
+    fn fake_tls_secret_appends_domain_hex() {
+        let expected = format!("ee{}{}", SECRET, DOMAIN_HEX);
+        assert_eq!(build_fake_tls_secret(SECRET, "www.google.com"), expected);
+        assert_eq!(
+            build_secret(SECRET, SecretMode::Tls, Some("www.google.com")),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn fake_tls_secret_requires_domain() {
+        assert_eq!(build_secret(SECRET, SecretMode::Tls, None), None);
+    }
+
+    #[test]
+    fn links_for_every_endpoint_mode_and_form() {
+        let params = TelemtLinkParams {
+            endpoints: vec![
+                ProxyEndpoint::new("proxy.example.com", 443),
+                ProxyEndpoint::new("2001:db8::1", 8443),
+            ],
+            modes: vec![SecretMode::Tls, SecretMode::Secure],
+            tls_domain: Some("www.google.com".to_string()),
+        };
+        let links = ProxyLinks::build(&params, SECRET, &[ProxyLinkForm::Https, ProxyLinkForm::Tg]).unwrap();
+        assert_eq!(
+            links.all().collect::<Vec<_>>(),
+            vec![
+                format!("https://t.me/proxy?server=proxy.example.com&port=443&secret=ee{}{}", SECRET, DOMAIN_HEX),
+                format!("tg://proxy?server=proxy.example.com&port=443&secret=ee{}{}", SECRET, DOMAIN_HEX),
+                format!("https://t.me/proxy?server=proxy.example.com&port=443&secret=dd{}", SECRET),
+                format!("tg://proxy?server=proxy.example.com&port=443&secret=dd{}", SECRET),
+                format!("https://t.me/proxy?server=%5B2001%3Adb8%3A%3A1%5D&port=8443&secret=ee{}{}", SECRET, DOMAIN_HEX),
+                format!("tg://proxy?server=%5B2001%3Adb8%3A%3A1%5D&port=8443&secret=ee{}{}", SECRET, DOMAIN_HEX),
+                format!("https://t.me/proxy?server=%5B2001%3Adb8%3A%3A1%5D&port=8443&secret=dd{}", SECRET),
+                format!("tg://proxy?server=%5B2001%3Adb8%3A%3A1%5D&port=8443&secret=dd{}", SECRET),
+            ]
+        );
+    }
+}
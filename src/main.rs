@@ -1,10 +1,18 @@
 //! telemt-admin — Telegram-бот для администрирования MTProxy telemt.
 
+mod agent;
+mod api;
 mod bot;
+mod cli;
 mod config;
 mod db;
+mod http;
+mod instance;
+mod json;
 mod link;
+mod roles;
 mod service;
+mod sync;
 mod telemt_cfg;
 
 use std::path::PathBuf;
@@ -15,17 +23,52 @@ use tokio::sync::Mutex;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .init();
+    let invocation = match cli::parse(std::env::args().skip(1)) {
+        Ok(invocation) => invocation,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+    // Бот и агент пишут подробный лог в stdout; команды CLI — только предупреждения
+    // в stderr, чтобы вывод (в том числе JSON) можно было разбирать.
+    let (default_level, daemon) = if invocation.is_daemon() {
+        (tracing::Level::INFO, true)
+    } else {
+        (tracing::Level::WARN, false)
+    };
+    let subscriber = tracing_subscriber::fmt().with_env_filter(
+        tracing_subscriber::EnvFilter::from_default_env().add_directive(default_level.into()),
+    );
+    if daemon {
+        subscriber.init();
+    } else {
+        subscriber.with_writer(std::io::stderr).init();
+    }
 
-    let config_path = std::env::args()
-        .nth(1)
-        .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("/etc/telemt-admin.toml"));
+    match invocation.command {
+        cli::Command::Run => run_bot(invocation.config_path).await,
+        // `telemt-admin agent <config>` — режим агента для удалённого управления telemt.
+        cli::Command::Agent(agent_config_path) => {
+            tracing::info!(
+                "Starting telemt-admin agent with config {}",
+                agent_config_path.display()
+            );
+            let agent_config = config::AgentConfig::load(&agent_config_path)?;
+            agent::run(agent_config).await?;
+            Ok(())
+        }
+        _ => {
+            if let Err(error) = cli::execute(invocation).await {
+                eprintln!("Ошибка: {:#}", error);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn run_bot(config_path: PathBuf) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     tracing::info!(
         "Starting telemt-admin with config {}",
         config_path.display()
@@ -36,15 +79,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     tracing::info!(
         admin_count = config.admin_ids.len(),
         db_path = %config.db_path.display(),
-        telemt_config_path = %config.telemt_config_path.display(),
-        service_name = %config.service_name,
         users_page_size = config.users_page_size,
         "Configuration loaded"
     );
 
     let db = Arc::new(db::Db::open(&config.db_path).await?);
-    let telemt_cfg = Arc::new(telemt_cfg::TelemtConfig::new(&config.telemt_config_path));
-    let service = service::ServiceController::new(&config.service_name);
+    let instances = Arc::new(instance::Instances::from_config(&config)?);
+    tracing::info!(instances = ?instances.names(), "telemt instances loaded");
+    for instance in instances.all() {
+        if let instance::ConfigStore::Remote(agent) = &instance.telemt_cfg {
+            match agent.health().await {
+                Ok(info) => tracing::info!(instance = %instance.name, agent = %info, "Agent is reachable"),
+                Err(error) => tracing::warn!(
+                    instance = %instance.name,
+                    error = %error,
+                    "Агент недоступен при запуске, запросы будут повторяться по мере работы"
+                ),
+            }
+        }
+    }
 
     let bot = Bot::new(token);
     let bot_username = match bot.get_me().await {
@@ -58,14 +111,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         }
     };
 
+    let (restart_scheduler, restart_queue) = bot::handlers::RestartScheduler::new();
     let state = bot::handlers::BotState {
-        config,
+        config: Arc::new(std::sync::RwLock::new(config)),
+        config_path,
         db,
-        telemt_cfg,
-        service,
+        instances,
         bot_username,
         awaiting_invite_users: Arc::new(Mutex::new(std::collections::HashSet::new())),
+        admin_navigation: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        restart_scheduler,
+        admin_grants: Arc::new(std::sync::RwLock::new(Vec::new())),
     };
+    state.reload_admin_grants().await?;
+    bot::handlers::spawn_restart_worker(bot.clone(), state.clone(), restart_queue);
+    bot::handlers::spawn_expiry_watcher(bot.clone(), state.clone());
+    bot::handlers::spawn_config_watcher(bot.clone(), state.clone());
+    bot::handlers::spawn_sighup_reloader(bot.clone(), state.clone());
+    bot::handlers::spawn_startup_sync_check(bot.clone(), state.clone());
+    if let Some(api_config) = state.config().api.clone() {
+        api::spawn(bot.clone(), state.clone(), &api_config).await?;
+    }
     tracing::info!("Dispatcher initialized, bot is ready");
 
     Dispatcher::builder(bot, bot::handlers::schema())
@@ -0,0 +1,118 @@
+//! Роли администраторов и права, которые они дают.
+//!
+//! Владельцы (`admin_ids`) могут всё; остальные роли задаются в `[roles]` и
+//! открывают только свою часть админки. Владельцы могут выдавать роли из бота
+//! (`/admin`), такие роли хранятся в БД и добавляются к ролям из конфига.
+//! Админ может иметь несколько ролей.
+
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum AdminRole {
+    /// Полный доступ
+    Owner,
+    /// Одобряет и отклоняет заявки
+    Moderator,
+    /// Видит список пользователей и статистику
+    Viewer,
+    /// Выпускает и отзывает invite-токены с ручным подтверждением
+    TokenIssuer,
+}
+
+/// Действие в админке, доступное не всем ролям.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Список пользователей, карточки и статистика
+    ViewUsers,
+    /// Новые заявки: просмотр, одобрение и отклонение
+    ModerateRequests,
+    /// Создание, просмотр и отзыв invite-токенов
+    IssueTokens,
+    /// Токены с автоподтверждением: пользователи получают доступ без модерации
+    AutoApproveTokens,
+    /// Создание, удаление, бан, лимиты, экземпляры и перевыпуск ссылок пользователей
+    ManageUsers,
+    /// Сервис telemt, логи, сверка и копии конфига
+    ManageService,
+    /// Журнал действий
+    ViewAudit,
+    /// Выдача и снятие ролей командой `/admin`
+    ManageAdmins,
+    /// Перечитывание telemt-admin.toml
+    ReloadConfig,
+}
+
+impl AdminRole {
+    pub const ALL: [AdminRole; 4] = [
+        AdminRole::Owner,
+        AdminRole::Moderator,
+        AdminRole::Viewer,
+        AdminRole::TokenIssuer,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Owner => "owner",
+            Self::Moderator => "moderator",
+            Self::Viewer => "viewer",
+            Self::TokenIssuer => "token_issuer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|role| role.as_str() == value)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Owner => "👑 владелец",
+            Self::Moderator => "🛂 модератор",
+            Self::Viewer => "👀 наблюдатель",
+            Self::TokenIssuer => "🔑 выпуск токенов",
+        }
+    }
+
+    pub fn allows(self, permission: Permission) -> bool {
+        match self {
+            Self::Owner => true,
+            Self::Moderator => permission == Permission::ModerateRequests,
+            Self::Viewer => permission == Permission::ViewUsers,
+            Self::TokenIssuer => permission == Permission::IssueTokens,
+        }
+    }
+}
+
+impl std::fmt::Display for AdminRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Роли одного пользователя; без ролей он не администратор.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdminAccess(BTreeSet<AdminRole>);
+
+impl AdminAccess {
+    pub fn new(roles: impl IntoIterator<Item = AdminRole>) -> Self {
+        Self(roles.into_iter().collect())
+    }
+
+    pub fn grant(&mut self, role: AdminRole) {
+        self.0.insert(role);
+    }
+
+    pub fn is_admin(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.0.iter().any(|role| role.allows(permission))
+    }
+
+    pub fn roles(&self) -> impl Iterator<Item = AdminRole> + '_ {
+        self.0.iter().copied()
+    }
+}
@@ -1,94 +1,314 @@
-//! Управление systemd-сервисом telemt.
+//! Управление сервисом telemt через подключаемые бэкенды.
+//!
+//! Бэкенд выбирается секцией `[service]` конфига: systemd (D-Bus на Linux,
+//! `systemctl` как запасной путь), контейнер Docker/Podman, OpenRC, runit,
+//! PID-файл с сигналами или заглушка `noop`. Все вызовы асинхронные и не
+//! блокируют потоки tokio.
 
-use std::process::Command;
+#[path = "service/container.rs"]
+mod container;
+#[cfg(target_os = "linux")]
+#[path = "service/dbus.rs"]
+mod dbus;
+#[path = "service/init.rs"]
+mod init;
+#[path = "service/noop.rs"]
+mod noop;
+#[path = "service/pidfile.rs"]
+mod pidfile;
+#[path = "service/systemd.rs"]
+mod systemd;
+
+use crate::config::{InstanceConfig, ServiceBackendConfig};
+use anyhow::anyhow;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// Способ управления процессом telemt.
+pub trait ServiceBackend: Send + Sync + std::fmt::Debug {
+    /// Короткое имя бэкенда для логов (`systemd`, `docker`, …).
+    fn kind(&self) -> &'static str;
+    /// Чем управляет бэкенд: unit, контейнер, сервис или PID-файл.
+    fn target(&self) -> &str;
+    fn run(&self, action: ServiceAction) -> BoxFuture<'_, ServiceResult>;
+    fn status(&self) -> BoxFuture<'_, Result<ServiceStatus, anyhow::Error>>;
+    /// Можно ли применить изменения конфига через reload вместо restart.
+    fn can_reload(&self) -> BoxFuture<'_, bool>;
+
+    fn journal(
+        &self,
+        _lines: usize,
+        _priority: LogPriority,
+        _window: LogWindow,
+    ) -> BoxFuture<'_, Result<Vec<JournalEntry>, anyhow::Error>> {
+        let kind = self.kind();
+        Box::pin(async move { Err(anyhow!("Просмотр логов не поддерживается бэкендом {}", kind)) })
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ServiceController {
-    service_name: String,
+    backend: Arc<dyn ServiceBackend>,
+    /// Общая блокировка: управляющие команды выполняются строго по одной.
+    lock: Arc<Mutex<()>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ServiceResult {
     pub success: bool,
     pub stdout: String,
     pub stderr: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceAction {
+    Start,
+    Stop,
+    Restart,
+    Reload,
+}
+
+impl ServiceAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+            Self::Reload => "reload",
+        }
+    }
+}
+
+/// Снимок состояния unit'а telemt.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub load_state: String,
+    pub active_state: String,
+    pub sub_state: String,
+    pub main_pid: Option<u32>,
+    pub memory_bytes: Option<u64>,
+    /// Момент перехода в active (unix-время, секунды)
+    pub active_since: Option<i64>,
+    /// Код выхода последнего завершившегося главного процесса
+    pub last_exit_code: Option<i32>,
+}
+
+impl ServiceStatus {
+    pub fn is_active(&self) -> bool {
+        self.active_state == "active"
+    }
+}
+
+/// Минимальный приоритет записей журнала, которые показываются админу.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogPriority {
+    All,
+    Warning,
+    Error,
+}
+
+impl LogPriority {
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value {
+            "all" => Some(Self::All),
+            "warn" => Some(Self::Warning),
+            "err" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Warning => "warn",
+            Self::Error => "err",
+        }
+    }
+
+    /// Проходит ли запись с приоритетом syslog `priority` через фильтр.
+    pub fn allows(self, priority: Option<u8>) -> bool {
+        let threshold = match self {
+            Self::All => return true,
+            Self::Warning => 4,
+            Self::Error => 3,
+        };
+        priority.is_some_and(|priority| priority <= threshold)
+    }
+
+    fn journalctl_level(self) -> Option<&'static str> {
+        match self {
+            Self::All => None,
+            Self::Warning => Some("warning"),
+            Self::Error => Some("err"),
+        }
+    }
+}
+
+/// Окно времени, за которое читается журнал.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogWindow {
+    Minutes15,
+    Hour,
+    Day,
+    All,
+}
+
+impl LogWindow {
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value {
+            "15m" => Some(Self::Minutes15),
+            "1h" => Some(Self::Hour),
+            "24h" => Some(Self::Day),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            Self::Minutes15 => "15m",
+            Self::Hour => "1h",
+            Self::Day => "24h",
+            Self::All => "all",
+        }
+    }
+
+    fn secs(self) -> Option<u64> {
+        match self {
+            Self::Minutes15 => Some(900),
+            Self::Hour => Some(3_600),
+            Self::Day => Some(86_400),
+            Self::All => None,
+        }
+    }
+}
+
+/// Одна запись журнала unit'а.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix-время записи, секунды
+    pub timestamp: Option<i64>,
+    /// Приоритет syslog: 0 (emerg) … 7 (debug)
+    pub priority: Option<u8>,
+    pub message: String,
+}
+
+impl JournalEntry {
+    pub fn is_error(&self) -> bool {
+        self.priority.is_some_and(|priority| priority <= 3)
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.priority == Some(4)
+    }
+}
+
 impl ServiceController {
-    pub fn new(service_name: impl Into<String>) -> Self {
+    pub fn new(backend: Arc<dyn ServiceBackend>) -> Self {
         Self {
-            service_name: service_name.into(),
+            backend,
+            lock: Arc::new(Mutex::new(())),
         }
     }
 
-    fn run_systemctl(&self, action: &str) -> ServiceResult {
+    pub fn from_config(config: &InstanceConfig) -> Self {
+        let name = config.service_name.clone();
+        let backend: Arc<dyn ServiceBackend> = match &config.service {
+            ServiceBackendConfig::Systemd => Arc::new(systemd::SystemdBackend::new(name)),
+            ServiceBackendConfig::Docker { reload_signal } => Arc::new(
+                container::ContainerBackend::new("docker", name, reload_signal.clone()),
+            ),
+            ServiceBackendConfig::Podman { reload_signal } => Arc::new(
+                container::ContainerBackend::new("podman", name, reload_signal.clone()),
+            ),
+            ServiceBackendConfig::OpenRc => Arc::new(init::OpenRcBackend::new(name)),
+            ServiceBackendConfig::Runit => Arc::new(init::RunitBackend::new(name)),
+            ServiceBackendConfig::PidFile {
+                pid_file,
+                start_command,
+                reload_signal,
+            } => Arc::new(pidfile::PidFileBackend::new(
+                pid_file.clone(),
+                start_command.clone(),
+                reload_signal.clone(),
+            )),
+            ServiceBackendConfig::Noop => Arc::new(noop::NoopBackend::new(name)),
+        };
         tracing::info!(
-            action = action,
-            service = %self.service_name,
-            "Running systemctl command"
+            instance = %config.name,
+            backend = backend.kind(),
+            target = backend.target(),
+            "Service backend selected"
         );
-        let output = Command::new("systemctl")
-            .arg(action)
-            .arg(&self.service_name)
-            .output();
-
-        match output {
-            Ok(o) => {
-                let result = ServiceResult {
-                    success: o.status.success(),
-                    stdout: String::from_utf8_lossy(&o.stdout).trim().to_string(),
-                    stderr: String::from_utf8_lossy(&o.stderr).trim().to_string(),
-                };
-                if result.success {
-                    tracing::info!(
-                        action = action,
-                        service = %self.service_name,
-                        "systemctl finished successfully"
-                    );
-                } else {
-                    tracing::warn!(
-                        action = action,
-                        service = %self.service_name,
-                        stderr = %result.stderr,
-                        "systemctl returned non-zero status"
-                    );
-                }
-                result
-            }
-            Err(e) => ServiceResult {
-                success: false,
-                stdout: String::new(),
-                stderr: {
-                    tracing::error!(
-                        action = action,
-                        service = %self.service_name,
-                        error = %e,
-                        "Failed to execute systemctl"
-                    );
-                    format!("Ошибка запуска systemctl: {}", e)
-                },
-            },
+        Self::new(backend)
+    }
+
+    pub fn backend_kind(&self) -> &'static str {
+        self.backend.kind()
+    }
+
+    async fn run(&self, action: ServiceAction) -> ServiceResult {
+        let _guard = self.lock.lock().await;
+        tracing::info!(
+            action = action.as_str(),
+            backend = self.backend.kind(),
+            service = %self.backend.target(),
+            "Running service action"
+        );
+        let result = self.backend.run(action).await;
+        if result.success {
+            tracing::info!(
+                action = action.as_str(),
+                service = %self.backend.target(),
+                "Service action finished successfully"
+            );
+        } else {
+            tracing::warn!(
+                action = action.as_str(),
+                service = %self.backend.target(),
+                stderr = %result.stderr,
+                "Service action failed"
+            );
         }
+        result
+    }
+
+    pub async fn start(&self) -> ServiceResult {
+        self.run(ServiceAction::Start).await
+    }
+
+    pub async fn stop(&self) -> ServiceResult {
+        self.run(ServiceAction::Stop).await
     }
 
-    pub fn start(&self) -> ServiceResult {
-        self.run_systemctl("start")
+    pub async fn restart(&self) -> ServiceResult {
+        self.run(ServiceAction::Restart).await
     }
 
-    pub fn stop(&self) -> ServiceResult {
-        self.run_systemctl("stop")
+    pub async fn reload(&self) -> ServiceResult {
+        self.run(ServiceAction::Reload).await
     }
 
-    pub fn restart(&self) -> ServiceResult {
-        self.run_systemctl("restart")
+    pub async fn status(&self) -> Result<ServiceStatus, anyhow::Error> {
+        self.backend.status().await
     }
 
-    pub fn reload(&self) -> ServiceResult {
-        self.run_systemctl("reload")
+    /// Поддерживает ли сервис reload без полного перезапуска.
+    pub async fn can_reload(&self) -> bool {
+        self.backend.can_reload().await
     }
 
-    pub fn status(&self) -> ServiceResult {
-        self.run_systemctl("status")
+    /// Последние `lines` записей журнала сервиса с фильтром по приоритету и времени.
+    pub async fn journal(
+        &self,
+        lines: usize,
+        priority: LogPriority,
+        window: LogWindow,
+    ) -> Result<Vec<JournalEntry>, anyhow::Error> {
+        self.backend.journal(lines, priority, window).await
     }
 
     pub fn format_result(&self, action: &str, r: &ServiceResult) -> String {
@@ -104,3 +324,89 @@ impl ServiceController {
         out.trim().to_string()
     }
 }
+
+/// Запускает внешнюю команду и собирает результат (ошибка запуска — неуспешный результат).
+async fn run_command<S: AsRef<std::ffi::OsStr>>(program: &str, args: &[S]) -> ServiceResult {
+    match Command::new(program).args(args).output().await {
+        Ok(output) => ServiceResult {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        },
+        Err(e) => {
+            tracing::error!(program = program, error = %e, "Failed to execute command");
+            ServiceResult {
+                success: false,
+                stdout: String::new(),
+                stderr: format!("Ошибка запуска {}: {}", program, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(service: &str) -> ServiceController {
+        let config: InstanceConfig =
+            toml::from_str(&format!("name = \"main\"\nservice_name = \"telemt\"\n{}", service)).unwrap();
+        ServiceController::from_config(&config)
+    }
+
+    #[test]
+    fn backend_is_chosen_from_config() {
+        let cases = [
+            ("", "systemd", "telemt"),
+            ("[service]\nbackend = \"systemd\"", "systemd", "telemt"),
+            ("[service]\nbackend = \"docker\"\nreload_signal = \"HUP\"", "docker", "telemt"),
+            ("[service]\nbackend = \"podman\"", "podman", "telemt"),
+            ("[service]\nbackend = \"openrc\"", "openrc", "telemt"),
+            ("[service]\nbackend = \"runit\"", "runit", "telemt"),
+            (
+                "[service]\nbackend = \"pidfile\"\npid_file = \"/run/telemt.pid\"",
+                "pidfile",
+                "/run/telemt.pid",
+            ),
+            ("[service]\nbackend = \"noop\"", "noop", "telemt"),
+        ];
+        for (service, kind, target) in cases {
+            let controller = controller(service);
+            assert_eq!(controller.backend_kind(), kind, "{}", service);
+            assert_eq!(controller.backend.target(), target, "{}", service);
+        }
+    }
+
+    #[test]
+    fn unknown_backend_is_rejected() {
+        let parsed: Result<InstanceConfig, _> =
+            toml::from_str("name = \"main\"\n[service]\nbackend = \"launchd\"");
+        assert!(parsed.is_err());
+        let parsed: Result<InstanceConfig, _> =
+            toml::from_str("name = \"main\"\n[service]\nbackend = \"pidfile\"");
+        assert!(parsed.is_err(), "pidfile без pid_file");
+    }
+
+    #[tokio::test]
+    async fn noop_backend_through_controller() {
+        let controller = ServiceController::new(Arc::new(noop::NoopBackend::new("telemt")));
+        for result in [
+            controller.start().await,
+            controller.stop().await,
+            controller.restart().await,
+            controller.reload().await,
+        ] {
+            assert!(result.success);
+            assert!(result.stdout.contains("noop"), "{}", result.stdout);
+        }
+        assert_eq!(
+            controller.format_result("Restart", &controller.restart().await),
+            "Restart telemt: OK\nrestart: пропущено (backend = \"noop\")"
+        );
+        let status = controller.status().await.unwrap();
+        assert!(status.is_active());
+        assert_eq!(status.sub_state, "noop");
+        assert!(controller.can_reload().await);
+        assert!(controller.journal(10, LogPriority::All, LogWindow::All).await.is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! Бэкенд контейнера Docker/Podman: управление через CLI рантайма.
+
+use super::{
+    run_command, BoxFuture, JournalEntry, LogPriority, LogWindow, ServiceAction, ServiceBackend,
+    ServiceResult, ServiceStatus,
+};
+use anyhow::anyhow;
+use tokio::process::Command;
+
+#[derive(Debug)]
+pub struct ContainerBackend {
+    /// `docker` или `podman`
+    cli: &'static str,
+    container: String,
+    /// Сигнал для reload (например, `HUP`); без него reload не поддерживается
+    reload_signal: Option<String>,
+}
+
+impl ContainerBackend {
+    pub fn new(cli: &'static str, container: impl Into<String>, reload_signal: Option<String>) -> Self {
+        Self {
+            cli,
+            container: container.into(),
+            reload_signal,
+        }
+    }
+
+    /// Аргументы CLI рантайма для действия; `Err` — действие не настроено.
+    fn action_args(&self, action: ServiceAction) -> Result<Vec<String>, String> {
+        match action {
+            ServiceAction::Reload => match &self.reload_signal {
+                Some(signal) => Ok(vec![
+                    "kill".to_string(),
+                    format!("--signal={}", signal),
+                    self.container.clone(),
+                ]),
+                None => Err("reload не настроен: укажите reload_signal в [service]".to_string()),
+            },
+            _ => Ok(vec![action.as_str().to_string(), self.container.clone()]),
+        }
+    }
+
+    async fn run_action(&self, action: ServiceAction) -> ServiceResult {
+        match self.action_args(action) {
+            Ok(args) => run_command(self.cli, &args).await,
+            Err(stderr) => ServiceResult {
+                success: false,
+                stdout: String::new(),
+                stderr,
+            },
+        }
+    }
+
+    async fn read_status(&self) -> Result<ServiceStatus, anyhow::Error> {
+        let result = run_command(
+            self.cli,
+            &["inspect", "--format", "{{json .State}}", &self.container],
+        )
+        .await;
+        if !result.success {
+            return Err(anyhow!("{} inspect: {}", self.cli, result.stderr));
+        }
+        let state: serde_json::Value = serde_json::from_str(&result.stdout)
+            .map_err(|e| anyhow!("Некорректный ответ {} inspect: {}", self.cli, e))?;
+        Ok(status_from_state(&state))
+    }
+
+    /// Аргументы `logs`: последние `lines` строк с отметками времени за окно `window`.
+    fn logs_args(&self, lines: usize, window: LogWindow) -> Vec<String> {
+        let mut args = vec![
+            "logs".to_string(),
+            "--timestamps".to_string(),
+            "--tail".to_string(),
+            lines.to_string(),
+        ];
+        if let Some(secs) = window.secs() {
+            args.push("--since".to_string());
+            args.push(format!("{}s", secs));
+        }
+        args.push(self.container.clone());
+        args
+    }
+
+    async fn read_logs(
+        &self,
+        lines: usize,
+        priority: LogPriority,
+        window: LogWindow,
+    ) -> Result<Vec<JournalEntry>, anyhow::Error> {
+        let output = Command::new(self.cli)
+            .args(self.logs_args(lines, window))
+            .output()
+            .await
+            .map_err(|e| anyhow!("Ошибка запуска {}: {}", self.cli, e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} logs: {}",
+                self.cli,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        // stdout и stderr контейнера приходят раздельно — сводим их по времени.
+        let mut entries: Vec<(String, JournalEntry)> = [&output.stdout, &output.stderr]
+            .into_iter()
+            .flat_map(|stream| {
+                String::from_utf8_lossy(stream)
+                    .lines()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .filter_map(|line| {
+                let (timestamp, message) = line.split_once(' ')?;
+                let entry = JournalEntry {
+                    timestamp: chrono::DateTime::parse_from_rfc3339(timestamp)
+                        .ok()
+                        .map(|dt| dt.timestamp()),
+                    priority: Some(guess_priority(message)),
+                    message: message.to_string(),
+                };
+                Some((timestamp.to_string(), entry))
+            })
+            .filter(|(_, entry)| priority.allows(entry.priority))
+            .collect();
+        entries.sort_by(|left, right| left.0.cmp(&right.0));
+        let skip = entries.len().saturating_sub(lines);
+        Ok(entries.into_iter().skip(skip).map(|(_, entry)| entry).collect())
+    }
+}
+
+/// Состояние из `inspect --format {{json .State}}`; отсутствующие поля не ошибка.
+fn status_from_state(state: &serde_json::Value) -> ServiceStatus {
+    let flag = |name: &str| state.get(name).and_then(serde_json::Value::as_bool) == Some(true);
+    let status = state
+        .get("Status")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or("unknown")
+        .to_string();
+    let exit_code = state
+        .get("ExitCode")
+        .and_then(serde_json::Value::as_i64)
+        .and_then(|code| i32::try_from(code).ok());
+    let active_state = if flag("Restarting") {
+        "activating"
+    } else if flag("Running") {
+        "active"
+    } else if exit_code.is_some_and(|code| code != 0) {
+        "failed"
+    } else {
+        "inactive"
+    };
+
+    ServiceStatus {
+        load_state: "loaded".to_string(),
+        active_state: active_state.to_string(),
+        sub_state: status,
+        main_pid: state
+            .get("Pid")
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|pid| u32::try_from(pid).ok())
+            .filter(|pid| *pid != 0),
+        memory_bytes: None,
+        active_since: state
+            .get("StartedAt")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|value| chrono::DateTime::parse_from_rfc3339(value).ok())
+            .map(|started| started.timestamp())
+            .filter(|ts| *ts > 0),
+        last_exit_code: exit_code,
+    }
+}
+
+/// У логов контейнера нет приоритета — определяем его по тексту строки.
+fn guess_priority(message: &str) -> u8 {
+    let lower = message.to_lowercase();
+    if ["error", "panic", "fatal", "crit"]
+        .iter()
+        .any(|marker| lower.contains(marker))
+    {
+        3
+    } else if lower.contains("warn") {
+        4
+    } else {
+        6
+    }
+}
+
+impl ServiceBackend for ContainerBackend {
+    fn kind(&self) -> &'static str {
+        self.cli
+    }
+
+    fn target(&self) -> &str {
+        &self.container
+    }
+
+    fn run(&self, action: ServiceAction) -> BoxFuture<'_, ServiceResult> {
+        Box::pin(self.run_action(action))
+    }
+
+    fn status(&self) -> BoxFuture<'_, Result<ServiceStatus, anyhow::Error>> {
+        Box::pin(self.read_status())
+    }
+
+    fn can_reload(&self) -> BoxFuture<'_, bool> {
+        let supported = self.reload_signal.is_some();
+        Box::pin(async move { supported })
+    }
+
+    fn journal(
+        &self,
+        lines: usize,
+        priority: LogPriority,
+        window: LogWindow,
+    ) -> BoxFuture<'_, Result<Vec<JournalEntry>, anyhow::Error>> {
+        Box::pin(self.read_logs(lines, priority, window))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_command_lines() {
+        let docker = ContainerBackend::new("docker", "telemt", Some("HUP".to_string()));
+        assert_eq!(docker.action_args(ServiceAction::Start).unwrap(), ["start", "telemt"]);
+        assert_eq!(docker.action_args(ServiceAction::Stop).unwrap(), ["stop", "telemt"]);
+        assert_eq!(docker.action_args(ServiceAction::Restart).unwrap(), ["restart", "telemt"]);
+        assert_eq!(
+            docker.action_args(ServiceAction::Reload).unwrap(),
+            ["kill", "--signal=HUP", "telemt"]
+        );
+
+        let podman = ContainerBackend::new("podman", "telemt", None);
+        assert!(podman.action_args(ServiceAction::Reload).is_err());
+    }
+
+    #[test]
+    fn logs_command_lines() {
+        let backend = ContainerBackend::new("docker", "telemt", None);
+        assert_eq!(
+            backend.logs_args(50, LogWindow::Hour),
+            ["logs", "--timestamps", "--tail", "50", "--since", "3600s", "telemt"]
+        );
+        assert_eq!(
+            backend.logs_args(10, LogWindow::All),
+            ["logs", "--timestamps", "--tail", "10", "telemt"]
+        );
+    }
+
+    #[test]
+    fn status_of_running_container() {
+        let status = status_from_state(&serde_json::json!({
+            "Status": "running",
+            "Running": true,
+            "Restarting": false,
+            "Pid": 4321,
+            "ExitCode": 0,
+            "StartedAt": "2023-11-14T22:13:20.123456789Z"
+        }));
+        assert!(status.is_active());
+        assert_eq!(status.sub_state, "running");
+        assert_eq!(status.main_pid, Some(4321));
+        assert_eq!(status.active_since, Some(1_700_000_000));
+        assert_eq!(status.last_exit_code, Some(0));
+    }
+
+    #[test]
+    fn status_of_exited_container() {
+        let status = status_from_state(&serde_json::json!({
+            "Status": "exited",
+            "Running": false,
+            "Pid": 0,
+            "ExitCode": 137,
+            "StartedAt": "0001-01-01T00:00:00Z"
+        }));
+        assert_eq!(status.active_state, "failed");
+        assert_eq!(status.main_pid, None);
+        assert_eq!(status.active_since, None);
+        assert_eq!(status.last_exit_code, Some(137));
+
+        let unknown = status_from_state(&serde_json::json!({}));
+        assert_eq!(unknown.active_state, "inactive");
+        assert_eq!(unknown.sub_state, "unknown");
+    }
+
+    #[test]
+    fn priority_is_guessed_from_text() {
+        assert_eq!(guess_priority("ERROR listener failed"), 3);
+        assert_eq!(guess_priority("thread panicked"), 3);
+        assert_eq!(guess_priority("WARN slow client"), 4);
+        assert_eq!(guess_priority("accepted connection"), 6);
+    }
+}
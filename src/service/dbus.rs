@@ -0,0 +1,251 @@
+//! Доступ к systemd через системную шину D-Bus (org.freedesktop.systemd1).
+
+use super::{ServiceAction, ServiceResult, ServiceStatus};
+use anyhow::anyhow;
+use futures_util::StreamExt;
+use std::time::Duration;
+use zbus::Connection;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+
+/// Сколько ждать завершения задания systemd (совпадает с TimeoutStartSec по умолчанию).
+const JOB_TIMEOUT: Duration = Duration::from_secs(90);
+
+#[zbus::proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1"
+)]
+trait Manager {
+    fn start_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+    fn stop_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+    fn restart_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+    fn reload_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+    fn load_unit(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+    fn subscribe(&self) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn job_removed(
+        &self,
+        id: u32,
+        job: ObjectPath<'_>,
+        unit: &str,
+        result: &str,
+    ) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.systemd1.Unit",
+    default_service = "org.freedesktop.systemd1"
+)]
+trait Unit {
+    #[zbus(property)]
+    fn load_state(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn active_state(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn sub_state(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn active_enter_timestamp(&self) -> zbus::Result<u64>;
+    #[zbus(property)]
+    fn can_reload(&self) -> zbus::Result<bool>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.systemd1.Service",
+    default_service = "org.freedesktop.systemd1"
+)]
+trait Service {
+    #[zbus(property, name = "MainPID")]
+    fn main_pid(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn memory_current(&self) -> zbus::Result<u64>;
+    #[zbus(property)]
+    fn exec_main_status(&self) -> zbus::Result<i32>;
+}
+
+pub async fn connect() -> zbus::Result<Connection> {
+    Connection::system().await
+}
+
+/// Ставит задание systemd и дожидается его завершения (аналог `systemctl <action>`).
+pub async fn run_action(conn: &Connection, unit: &str, action: ServiceAction) -> ServiceResult {
+    match run_job(conn, unit, action).await {
+        Ok(result) if result == "done" => ServiceResult {
+            success: true,
+            stdout: String::new(),
+            stderr: String::new(),
+        },
+        Ok(result) => ServiceResult {
+            success: false,
+            stdout: String::new(),
+            stderr: format!("Задание systemd завершилось с результатом «{}»", result),
+        },
+        Err(error) => ServiceResult {
+            success: false,
+            stdout: String::new(),
+            stderr: format!("Ошибка D-Bus: {}", error),
+        },
+    }
+}
+
+async fn run_job(conn: &Connection, unit: &str, action: ServiceAction) -> Result<String, anyhow::Error> {
+    let manager = ManagerProxy::new(conn).await?;
+    // Без Subscribe systemd не рассылает JobRemoved; повторная подписка безопасна.
+    if let Err(error) = manager.subscribe().await {
+        tracing::debug!(error = %error, "systemd Subscribe failed");
+    }
+    // Подписываемся до постановки задания, чтобы не пропустить быстрый JobRemoved.
+    let mut removed = manager.receive_job_removed().await?;
+
+    let job = match action {
+        ServiceAction::Start => manager.start_unit(unit, "replace").await?,
+        ServiceAction::Stop => manager.stop_unit(unit, "replace").await?,
+        ServiceAction::Restart => manager.restart_unit(unit, "replace").await?,
+        ServiceAction::Reload => manager.reload_unit(unit, "replace").await?,
+    };
+
+    let wait = async {
+        while let Some(signal) = removed.next().await {
+            let args = signal.args()?;
+            if args.job().as_str() == job.as_str() {
+                return Ok(args.result().to_string());
+            }
+        }
+        Err(anyhow!("Поток сигналов systemd закрыт"))
+    };
+    tokio::time::timeout(JOB_TIMEOUT, wait).await.map_err(|_| {
+        anyhow!(
+            "Задание systemd не завершилось за {} с",
+            JOB_TIMEOUT.as_secs()
+        )
+    })?
+}
+
+async fn unit_proxy<'a>(conn: &Connection, path: OwnedObjectPath) -> zbus::Result<UnitProxy<'a>> {
+    UnitProxy::builder(conn)
+        .path(path)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+}
+
+pub async fn status(conn: &Connection, unit: &str) -> Result<ServiceStatus, anyhow::Error> {
+    let manager = ManagerProxy::new(conn).await?;
+    let path = manager.load_unit(unit).await?;
+    let unit_proxy = unit_proxy(conn, path.clone()).await?;
+    let service_proxy = ServiceProxy::builder(conn)
+        .path(path)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    // Свойства Service есть только у .service-юнитов, поэтому их ошибки не фатальны.
+    let properties = UnitProperties {
+        load_state: unit_proxy.load_state().await?,
+        active_state: unit_proxy.active_state().await?,
+        sub_state: unit_proxy.sub_state().await?,
+        active_enter_usec: unit_proxy.active_enter_timestamp().await?,
+        main_pid: service_proxy.main_pid().await.ok(),
+        memory_current: service_proxy.memory_current().await.ok(),
+        exec_main_status: service_proxy.exec_main_status().await.ok(),
+    };
+    Ok(properties.into_status())
+}
+
+/// Свойства unit'а и сервиса в том виде, в каком их отдаёт systemd.
+#[derive(Debug, Default)]
+struct UnitProperties {
+    load_state: String,
+    active_state: String,
+    sub_state: String,
+    /// ActiveEnterTimestamp в микросекундах; 0 — unit ещё не был активен
+    active_enter_usec: u64,
+    main_pid: Option<u32>,
+    memory_current: Option<u64>,
+    exec_main_status: Option<i32>,
+}
+
+impl UnitProperties {
+    /// systemd обозначает отсутствующие значения нулевым PID и `u64::MAX` для памяти.
+    fn into_status(self) -> ServiceStatus {
+        ServiceStatus {
+            load_state: self.load_state,
+            active_state: self.active_state,
+            sub_state: self.sub_state,
+            main_pid: self.main_pid.filter(|pid| *pid != 0),
+            memory_bytes: self.memory_current.filter(|bytes| *bytes != u64::MAX),
+            active_since: match self.active_enter_usec {
+                0 => None,
+                usec => i64::try_from(usec / 1_000_000).ok(),
+            },
+            last_exit_code: self.exec_main_status,
+        }
+    }
+}
+
+pub async fn can_reload(conn: &Connection, unit: &str) -> Result<bool, anyhow::Error> {
+    let manager = ManagerProxy::new(conn).await?;
+    let path = manager.load_unit(unit).await?;
+    Ok(unit_proxy(conn, path).await?.can_reload().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_service_status() {
+        let status = UnitProperties {
+            load_state: "loaded".to_string(),
+            active_state: "active".to_string(),
+            sub_state: "running".to_string(),
+            active_enter_usec: 1_700_000_000_123_456,
+            main_pid: Some(4321),
+            memory_current: Some(52_428_800),
+            exec_main_status: Some(0),
+        }
+        .into_status();
+        assert!(status.is_active());
+        assert_eq!(status.sub_state, "running");
+        assert_eq!(status.main_pid, Some(4321));
+        assert_eq!(status.memory_bytes, Some(52_428_800));
+        assert_eq!(status.active_since, Some(1_700_000_000));
+        assert_eq!(status.last_exit_code, Some(0));
+    }
+
+    #[test]
+    fn failed_service_status_drops_placeholders() {
+        let status = UnitProperties {
+            load_state: "loaded".to_string(),
+            active_state: "failed".to_string(),
+            sub_state: "failed".to_string(),
+            active_enter_usec: 0,
+            main_pid: Some(0),
+            memory_current: Some(u64::MAX),
+            exec_main_status: Some(1),
+        }
+        .into_status();
+        assert!(!status.is_active());
+        assert_eq!(status.main_pid, None);
+        assert_eq!(status.memory_bytes, None);
+        assert_eq!(status.active_since, None);
+        assert_eq!(status.last_exit_code, Some(1));
+    }
+
+    #[test]
+    fn missing_service_properties() {
+        let status = UnitProperties {
+            load_state: "not-found".to_string(),
+            active_state: "inactive".to_string(),
+            sub_state: "dead".to_string(),
+            active_enter_usec: u64::MAX,
+            ..UnitProperties::default()
+        }
+        .into_status();
+        assert_eq!(status.main_pid, None);
+        assert_eq!(status.memory_bytes, None);
+        assert_eq!(status.active_since, Some((u64::MAX / 1_000_000) as i64));
+        assert_eq!(status.last_exit_code, None);
+    }
+}
@@ -0,0 +1,208 @@
+//! Бэкенды классических init-систем: OpenRC (`rc-service`) и runit (`sv`).
+
+use super::{BoxFuture, ServiceAction, ServiceBackend, ServiceResult, ServiceStatus, run_command};
+use anyhow::anyhow;
+
+#[derive(Debug)]
+pub struct OpenRcBackend {
+    service: String,
+}
+
+impl OpenRcBackend {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    async fn read_status(&self) -> Result<ServiceStatus, anyhow::Error> {
+        // rc-service status возвращает ненулевой код для остановленного сервиса,
+        // поэтому смотрим только на текст « * status: started».
+        let result = run_command("rc-service", &[&self.service, "status"]).await;
+        let output = format!("{}\n{}", result.stdout, result.stderr);
+        openrc_status(&output).ok_or_else(|| anyhow!("rc-service status: {}", output.trim()))
+    }
+
+    fn action_args(&self, action: ServiceAction) -> [&str; 2] {
+        [&self.service, action.as_str()]
+    }
+
+    /// reload есть только у init-скриптов, объявивших его в extra_started_commands.
+    async fn read_can_reload(&self) -> bool {
+        let result = run_command("rc-service", &[&self.service, "describe"]).await;
+        result.success
+            && format!("{}\n{}", result.stdout, result.stderr)
+                .lines()
+                .any(|line| line.trim_start_matches([' ', '*']).starts_with("reload"))
+    }
+}
+
+impl ServiceBackend for OpenRcBackend {
+    fn kind(&self) -> &'static str {
+        "openrc"
+    }
+
+    fn target(&self) -> &str {
+        &self.service
+    }
+
+    fn run(&self, action: ServiceAction) -> BoxFuture<'_, ServiceResult> {
+        Box::pin(async move { run_command("rc-service", &self.action_args(action)).await })
+    }
+
+    fn status(&self) -> BoxFuture<'_, Result<ServiceStatus, anyhow::Error>> {
+        Box::pin(self.read_status())
+    }
+
+    fn can_reload(&self) -> BoxFuture<'_, bool> {
+        Box::pin(self.read_can_reload())
+    }
+}
+
+/// Состояние из вывода `rc-service <service> status`.
+fn openrc_status(output: &str) -> Option<ServiceStatus> {
+    let state = output
+        .lines()
+        .find_map(|line| line.split_once("status:"))
+        .map(|(_, state)| state.trim().to_string())?;
+    let active_state = match state.as_str() {
+        "started" => "active",
+        "starting" | "stopping" => "activating",
+        "crashed" => "failed",
+        _ => "inactive",
+    };
+    Some(ServiceStatus {
+        load_state: "loaded".to_string(),
+        active_state: active_state.to_string(),
+        sub_state: state,
+        ..ServiceStatus::default()
+    })
+}
+
+#[derive(Debug)]
+pub struct RunitBackend {
+    service: String,
+}
+
+impl RunitBackend {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    async fn read_status(&self) -> Result<ServiceStatus, anyhow::Error> {
+        let result = run_command("sv", &["status", &self.service]).await;
+        if !result.success {
+            return Err(anyhow!("sv status: {}", result.stderr));
+        }
+        Ok(runit_status(&result.stdout, chrono::Utc::now().timestamp()))
+    }
+
+    fn action_args(&self, action: ServiceAction) -> [&str; 2] {
+        [action.as_str(), &self.service]
+    }
+}
+
+/// Разбирает `run: telemt: (pid 123) 456s; run: log: ...` или `down: telemt: 10s, normally up`;
+/// `now` — текущее unix-время для расчёта момента запуска.
+fn runit_status(stdout: &str, now: i64) -> ServiceStatus {
+    let main = stdout.split(';').next().unwrap_or_default();
+    let state = main.split(':').next().unwrap_or_default().trim().to_string();
+    let main_pid = main
+        .split_once("(pid ")
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .and_then(|(pid, _)| pid.trim().parse().ok());
+    let uptime_secs: Option<i64> = main
+        .rsplit([')', ':'])
+        .next()
+        .and_then(|rest| rest.trim().split('s').next())
+        .and_then(|secs| secs.trim().parse().ok());
+    let active_state = match state.as_str() {
+        "run" => "active",
+        "finish" => "deactivating",
+        "down" => "inactive",
+        _ => "failed",
+    };
+    ServiceStatus {
+        load_state: "loaded".to_string(),
+        active_state: active_state.to_string(),
+        sub_state: state,
+        main_pid,
+        active_since: uptime_secs
+            .filter(|_| active_state == "active")
+            .map(|secs| now - secs),
+        ..ServiceStatus::default()
+    }
+}
+
+impl ServiceBackend for RunitBackend {
+    fn kind(&self) -> &'static str {
+        "runit"
+    }
+
+    fn target(&self) -> &str {
+        &self.service
+    }
+
+    fn run(&self, action: ServiceAction) -> BoxFuture<'_, ServiceResult> {
+        Box::pin(async move { run_command("sv", &self.action_args(action)).await })
+    }
+
+    fn status(&self) -> BoxFuture<'_, Result<ServiceStatus, anyhow::Error>> {
+        Box::pin(self.read_status())
+    }
+
+    /// `sv reload` шлёт SIGHUP; без обработчика в telemt это равносильно падению,
+    /// поэтому автоматические перезапуски всегда используют restart.
+    fn can_reload(&self) -> BoxFuture<'_, bool> {
+        Box::pin(async { false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openrc_command_lines() {
+        let backend = OpenRcBackend::new("telemt");
+        assert_eq!(backend.action_args(ServiceAction::Start), ["telemt", "start"]);
+        assert_eq!(backend.action_args(ServiceAction::Reload), ["telemt", "reload"]);
+    }
+
+    #[test]
+    fn openrc_status_text() {
+        assert_eq!(openrc_status(" * status: started\n").unwrap().active_state, "active");
+        assert_eq!(openrc_status(" * status: stopping").unwrap().active_state, "activating");
+        assert_eq!(openrc_status(" * status: crashed").unwrap().active_state, "failed");
+        let stopped = openrc_status("\n * status: stopped").unwrap();
+        assert_eq!(stopped.active_state, "inactive");
+        assert_eq!(stopped.sub_state, "stopped");
+        assert!(openrc_status(" * rc-service: service `telemt' does not exist").is_none());
+    }
+
+    #[test]
+    fn runit_command_lines() {
+        let backend = RunitBackend::new("telemt");
+        assert_eq!(backend.action_args(ServiceAction::Restart), ["restart", "telemt"]);
+        assert_eq!(backend.action_args(ServiceAction::Stop), ["stop", "telemt"]);
+    }
+
+    #[test]
+    fn runit_status_text() {
+        let running = runit_status("run: telemt: (pid 123) 456s; run: log: (pid 100) 500s", 10_000);
+        assert!(running.is_active());
+        assert_eq!(running.main_pid, Some(123));
+        assert_eq!(running.active_since, Some(9_544));
+
+        let down = runit_status("down: telemt: 10s, normally up", 10_000);
+        assert_eq!(down.active_state, "inactive");
+        assert_eq!(down.main_pid, None);
+        assert_eq!(down.active_since, None);
+
+        let broken = runit_status("fail: telemt: unable to change to service directory", 10_000);
+        assert_eq!(broken.active_state, "failed");
+        assert_eq!(runit_status("", 10_000).active_state, "failed");
+    }
+}
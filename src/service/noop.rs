@@ -0,0 +1,50 @@
+//! Бэкенд-заглушка: ничего не запускает и всегда сообщает об успехе.
+
+use super::{BoxFuture, ServiceAction, ServiceBackend, ServiceResult, ServiceStatus};
+
+#[derive(Debug)]
+pub struct NoopBackend {
+    name: String,
+}
+
+impl NoopBackend {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl ServiceBackend for NoopBackend {
+    fn kind(&self) -> &'static str {
+        "noop"
+    }
+
+    fn target(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, action: ServiceAction) -> BoxFuture<'_, ServiceResult> {
+        tracing::info!(action = action.as_str(), "noop service backend: action skipped");
+        Box::pin(async move {
+            ServiceResult {
+                success: true,
+                stdout: format!("{}: пропущено (backend = \"noop\")", action.as_str()),
+                stderr: String::new(),
+            }
+        })
+    }
+
+    fn status(&self) -> BoxFuture<'_, Result<ServiceStatus, anyhow::Error>> {
+        Box::pin(async {
+            Ok(ServiceStatus {
+                load_state: "loaded".to_string(),
+                active_state: "active".to_string(),
+                sub_state: "noop".to_string(),
+                ..ServiceStatus::default()
+            })
+        })
+    }
+
+    fn can_reload(&self) -> BoxFuture<'_, bool> {
+        Box::pin(async { true })
+    }
+}
@@ -0,0 +1,267 @@
+//! Бэкенд «PID-файл + сигналы» для telemt, запущенного без менеджера сервисов.
+
+use super::{BoxFuture, ServiceAction, ServiceBackend, ServiceResult, ServiceStatus, run_command};
+use anyhow::anyhow;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Сколько ждать завершения процесса после SIGTERM.
+const STOP_TIMEOUT: Duration = Duration::from_secs(15);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug)]
+pub struct PidFileBackend {
+    pid_file: PathBuf,
+    /// Команда запуска telemt; без неё start/restart недоступны
+    start_command: Vec<String>,
+    reload_signal: Option<String>,
+}
+
+impl PidFileBackend {
+    pub fn new(pid_file: PathBuf, start_command: Vec<String>, reload_signal: Option<String>) -> Self {
+        Self {
+            pid_file,
+            start_command,
+            reload_signal,
+        }
+    }
+
+    async fn read_pid(&self) -> Result<u32, anyhow::Error> {
+        let content = tokio::fs::read_to_string(&self.pid_file)
+            .await
+            .map_err(|e| anyhow!("Не удалось прочитать {}: {}", self.pid_file.display(), e))?;
+        content
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("Некорректный PID в {}", self.pid_file.display()))
+    }
+
+    async fn running_pid(&self) -> Option<u32> {
+        let pid = self.read_pid().await.ok()?;
+        is_alive(pid).await.then_some(pid)
+    }
+
+    async fn signal(&self, signal: &str) -> ServiceResult {
+        match self.read_pid().await {
+            Ok(pid) => run_command("kill", &kill_args(signal, pid)).await,
+            Err(error) => failure(error.to_string()),
+        }
+    }
+
+    async fn start(&self) -> ServiceResult {
+        if self.running_pid().await.is_some() {
+            return failure("telemt уже запущен".to_string());
+        }
+        let Some((program, args)) = self.start_command.split_first() else {
+            return failure("start не настроен: укажите start_command в [service]".to_string());
+        };
+        // Процесс telemt должен сам записать PID-файл (или демонизироваться).
+        match Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => ServiceResult {
+                success: true,
+                stdout: child
+                    .id()
+                    .map(|pid| format!("Запущен процесс {}", pid))
+                    .unwrap_or_default(),
+                stderr: String::new(),
+            },
+            Err(e) => failure(format!("Ошибка запуска {}: {}", program, e)),
+        }
+    }
+
+    async fn stop(&self) -> ServiceResult {
+        let Some(pid) = self.running_pid().await else {
+            return ServiceResult {
+                success: true,
+                stdout: "telemt не запущен".to_string(),
+                stderr: String::new(),
+            };
+        };
+        let result = run_command("kill", &kill_args("TERM", pid)).await;
+        if !result.success {
+            return result;
+        }
+        let deadline = tokio::time::Instant::now() + STOP_TIMEOUT;
+        while tokio::time::Instant::now() < deadline {
+            if !is_alive(pid).await {
+                return result;
+            }
+            tokio::time::sleep(STOP_POLL_INTERVAL).await;
+        }
+        failure(format!(
+            "Процесс {} не завершился за {} с",
+            pid,
+            STOP_TIMEOUT.as_secs()
+        ))
+    }
+
+    async fn run_action(&self, action: ServiceAction) -> ServiceResult {
+        match action {
+            ServiceAction::Start => self.start().await,
+            ServiceAction::Stop => self.stop().await,
+            ServiceAction::Restart => {
+                let stopped = self.stop().await;
+                if !stopped.success {
+                    return stopped;
+                }
+                self.start().await
+            }
+            ServiceAction::Reload => match &self.reload_signal {
+                Some(signal) => self.signal(signal).await,
+                None => failure("reload не настроен: укажите reload_signal в [service]".to_string()),
+            },
+        }
+    }
+
+    async fn read_status(&self) -> Result<ServiceStatus, anyhow::Error> {
+        let pid = self.running_pid().await;
+        let active_since = match pid {
+            Some(_) => tokio::fs::metadata(&self.pid_file)
+                .await
+                .ok()
+                .and_then(|meta| meta.modified().ok())
+                .map(|modified| chrono::DateTime::<chrono::Utc>::from(modified).timestamp()),
+            None => None,
+        };
+        let memory_bytes = match pid {
+            Some(pid) => read_rss_bytes(pid).await,
+            None => None,
+        };
+        Ok(ServiceStatus {
+            load_state: "loaded".to_string(),
+            active_state: if pid.is_some() { "active" } else { "inactive" }.to_string(),
+            sub_state: if pid.is_some() { "running" } else { "dead" }.to_string(),
+            main_pid: pid,
+            memory_bytes,
+            active_since,
+            last_exit_code: None,
+        })
+    }
+}
+
+impl ServiceBackend for PidFileBackend {
+    fn kind(&self) -> &'static str {
+        "pidfile"
+    }
+
+    fn target(&self) -> &str {
+        self.pid_file.to_str().unwrap_or("pid_file")
+    }
+
+    fn run(&self, action: ServiceAction) -> BoxFuture<'_, ServiceResult> {
+        Box::pin(self.run_action(action))
+    }
+
+    fn status(&self) -> BoxFuture<'_, Result<ServiceStatus, anyhow::Error>> {
+        Box::pin(self.read_status())
+    }
+
+    fn can_reload(&self) -> BoxFuture<'_, bool> {
+        let supported = self.reload_signal.is_some();
+        Box::pin(async move { supported })
+    }
+}
+
+/// Аргументы `kill` для отправки `signal` (`HUP`, `TERM`, `0`) процессу `pid`.
+fn kill_args(signal: &str, pid: u32) -> [String; 2] {
+    [format!("-{}", signal), pid.to_string()]
+}
+
+async fn is_alive(pid: u32) -> bool {
+    run_command("kill", &kill_args("0", pid)).await.success
+}
+
+/// Резидентная память процесса из /proc (только Linux).
+async fn read_rss_bytes(pid: u32) -> Option<u64> {
+    let status = tokio::fs::read_to_string(format!("/proc/{}/status", pid))
+        .await
+        .ok()?;
+    let kib: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+fn failure(stderr: String) -> ServiceResult {
+    ServiceResult {
+        success: false,
+        stdout: String::new(),
+        stderr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pid_file(name: &str, content: Option<&str>) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("telemt-admin-{}-{}.pid", name, std::process::id()));
+        match content {
+            Some(content) => std::fs::write(&path, content).unwrap(),
+            None => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        path
+    }
+
+    #[test]
+    fn kill_command_lines() {
+        assert_eq!(kill_args("HUP", 42), ["-HUP", "42"]);
+        assert_eq!(kill_args("0", 7), ["-0", "7"]);
+    }
+
+    #[tokio::test]
+    async fn running_process_from_pid_file() {
+        let path = pid_file("running", Some(&format!("{}\n", std::process::id())));
+        let backend = PidFileBackend::new(path.clone(), Vec::new(), None);
+
+        let status = backend.read_status().await.unwrap();
+        assert!(status.is_active());
+        assert_eq!(status.main_pid, Some(std::process::id()));
+        assert!(status.active_since.is_some());
+
+        let start = backend.run_action(ServiceAction::Start).await;
+        assert!(!start.success);
+        assert_eq!(start.stderr, "telemt уже запущен");
+        assert!(!backend.run_action(ServiceAction::Reload).await.success);
+        assert!(!backend.can_reload().await);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_pid_file_means_stopped() {
+        let path = pid_file("missing", None);
+        let backend = PidFileBackend::new(path, Vec::new(), Some("HUP".to_string()));
+
+        let status = backend.read_status().await.unwrap();
+        assert_eq!(status.active_state, "inactive");
+        assert_eq!(status.main_pid, None);
+        assert!(backend.run_action(ServiceAction::Stop).await.success);
+        assert!(!backend.run_action(ServiceAction::Start).await.success);
+        assert!(!backend.run_action(ServiceAction::Reload).await.success);
+        assert!(backend.can_reload().await);
+    }
+
+    #[tokio::test]
+    async fn garbage_pid_file_means_stopped() {
+        let path = pid_file("garbage", Some("not a pid"));
+        let backend = PidFileBackend::new(path.clone(), Vec::new(), None);
+        assert_eq!(backend.read_status().await.unwrap().active_state, "inactive");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
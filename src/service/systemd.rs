@@ -0,0 +1,288 @@
+//! Бэкенд systemd: D-Bus на Linux, `systemctl`/`journalctl` как запасной путь.
+
+use super::{
+    run_command, BoxFuture, JournalEntry, LogPriority, LogWindow, ServiceAction, ServiceBackend,
+    ServiceResult, ServiceStatus,
+};
+use anyhow::anyhow;
+use std::collections::HashMap;
+use tokio::process::Command;
+
+#[derive(Debug)]
+pub struct SystemdBackend {
+    unit: String,
+}
+
+impl SystemdBackend {
+    pub fn new(unit: impl Into<String>) -> Self {
+        Self { unit: unit.into() }
+    }
+
+    async fn run_action(&self, action: ServiceAction) -> ServiceResult {
+        #[cfg(target_os = "linux")]
+        match super::dbus::connect().await {
+            Ok(conn) => return super::dbus::run_action(&conn, &self.unit, action).await,
+            Err(error) => tracing::warn!(
+                error = %error,
+                "Системная шина D-Bus недоступна, использую systemctl"
+            ),
+        }
+
+        run_command("systemctl", &[action.as_str(), &self.unit]).await
+    }
+
+    async fn read_status(&self) -> Result<ServiceStatus, anyhow::Error> {
+        #[cfg(target_os = "linux")]
+        if let Ok(conn) = super::dbus::connect().await {
+            return super::dbus::status(&conn, &self.unit).await;
+        }
+
+        let properties = self
+            .show_properties(&[
+                "LoadState",
+                "ActiveState",
+                "SubState",
+                "MainPID",
+                "MemoryCurrent",
+                "ActiveEnterTimestamp",
+                "ExecMainStatus",
+            ])
+            .await?;
+        Ok(status_from_properties(&properties))
+    }
+
+    async fn read_can_reload(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Ok(conn) = super::dbus::connect().await {
+            return super::dbus::can_reload(&conn, &self.unit)
+                .await
+                .unwrap_or(false);
+        }
+
+        self.show_properties(&["CanReload"])
+            .await
+            .ok()
+            .and_then(|properties| properties.get("CanReload").cloned())
+            .is_some_and(|value| value == "yes")
+    }
+
+    async fn show_properties(&self, names: &[&str]) -> Result<HashMap<String, String>, anyhow::Error> {
+        let property_arg = format!("--property={}", names.join(","));
+        let result = run_command(
+            "systemctl",
+            &["show", "--timestamp=unix", &property_arg, &self.unit],
+        )
+        .await;
+        if !result.success {
+            return Err(anyhow!("systemctl show: {}", result.stderr));
+        }
+        Ok(result
+            .stdout
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect())
+    }
+
+    async fn read_journal(
+        &self,
+        lines: usize,
+        priority: LogPriority,
+        window: LogWindow,
+    ) -> Result<Vec<JournalEntry>, anyhow::Error> {
+        let mut command = Command::new("journalctl");
+        command
+            .arg("--unit")
+            .arg(&self.unit)
+            .arg("--lines")
+            .arg(lines.to_string())
+            .arg("--no-pager")
+            .arg("--output=json");
+        if let Some(level) = priority.journalctl_level() {
+            command.arg("--priority").arg(level);
+        }
+        if let Some(secs) = window.secs() {
+            command.arg("--since").arg(format!("-{}s", secs));
+        }
+
+        let output = command
+            .output()
+            .await
+            .map_err(|e| anyhow!("Ошибка запуска journalctl: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "journalctl: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(journal_entry_from_json)
+            .collect())
+    }
+}
+
+impl ServiceBackend for SystemdBackend {
+    fn kind(&self) -> &'static str {
+        "systemd"
+    }
+
+    fn target(&self) -> &str {
+        &self.unit
+    }
+
+    fn run(&self, action: ServiceAction) -> BoxFuture<'_, ServiceResult> {
+        Box::pin(self.run_action(action))
+    }
+
+    fn status(&self) -> BoxFuture<'_, Result<ServiceStatus, anyhow::Error>> {
+        Box::pin(self.read_status())
+    }
+
+    fn can_reload(&self) -> BoxFuture<'_, bool> {
+        Box::pin(self.read_can_reload())
+    }
+
+    fn journal(
+        &self,
+        lines: usize,
+        priority: LogPriority,
+        window: LogWindow,
+    ) -> BoxFuture<'_, Result<Vec<JournalEntry>, anyhow::Error>> {
+        Box::pin(self.read_journal(lines, priority, window))
+    }
+}
+
+/// Состояние из вывода `systemctl show --timestamp=unix`; отсутствующие и
+/// нечисловые значения (`[not set]`) дают `None`.
+fn status_from_properties(properties: &HashMap<String, String>) -> ServiceStatus {
+    let get = |key: &str| properties.get(key).map(String::as_str).unwrap_or_default();
+    ServiceStatus {
+        load_state: get("LoadState").to_string(),
+        active_state: get("ActiveState").to_string(),
+        sub_state: get("SubState").to_string(),
+        main_pid: get("MainPID").parse().ok().filter(|pid| *pid != 0),
+        memory_bytes: get("MemoryCurrent").parse().ok().filter(|bytes| *bytes != u64::MAX),
+        // С --timestamp=unix значение имеет вид "@1700000000"
+        active_since: get("ActiveEnterTimestamp")
+            .strip_prefix('@')
+            .and_then(|value| value.parse().ok()),
+        last_exit_code: get("ExecMainStatus").parse().ok(),
+    }
+}
+
+fn journal_entry_from_json(line: &str) -> Option<JournalEntry> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let field = |name: &str| value.get(name).and_then(serde_json::Value::as_str);
+    // Не-UTF-8 сообщения journalctl отдаёт массивом байтов.
+    let message = match value.get("MESSAGE")? {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .filter_map(|byte| byte.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        _ => String::new(),
+    };
+    Some(JournalEntry {
+        timestamp: field("__REALTIME_TIMESTAMP")
+            .and_then(|usec| usec.parse::<i64>().ok())
+            .map(|usec| usec / 1_000_000),
+        priority: field("PRIORITY").and_then(|priority| priority.parse().ok()),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(output: &str) -> HashMap<String, String> {
+        output
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn status_from_systemctl_show() {
+        let status = status_from_properties(&properties(
+            "LoadState=loaded\nActiveState=active\nSubState=running\nMainPID=4321\n\
+             MemoryCurrent=52428800\nActiveEnterTimestamp=@1700000000\nExecMainStatus=0",
+        ));
+        assert!(status.is_active());
+        assert_eq!(status.load_state, "loaded");
+        assert_eq!(status.sub_state, "running");
+        assert_eq!(status.main_pid, Some(4321));
+        assert_eq!(status.memory_bytes, Some(52_428_800));
+        assert_eq!(status.active_since, Some(1_700_000_000));
+        assert_eq!(status.last_exit_code, Some(0));
+    }
+
+    #[test]
+    fn status_without_values() {
+        let status = status_from_properties(&properties(
+            "LoadState=not-found\nActiveState=inactive\nSubState=dead\nMainPID=0\n\
+             MemoryCurrent=[not set]\nActiveEnterTimestamp=\nExecMainStatus=abc",
+        ));
+        assert!(!status.is_active());
+        assert_eq!(status.main_pid, None);
+        assert_eq!(status.memory_bytes, None);
+        assert_eq!(status.active_since, None);
+        assert_eq!(status.last_exit_code, None);
+
+        let empty = status_from_properties(&HashMap::new());
+        assert_eq!(empty.active_state, "");
+        assert_eq!(empty.main_pid, None);
+        assert_eq!(
+            status_from_properties(&properties("MemoryCurrent=18446744073709551615")).memory_bytes,
+            None
+        );
+    }
+
+    #[test]
+    fn journal_entry_fields() {
+        let entry = journal_entry_from_json(
+            r#"{"__REALTIME_TIMESTAMP":"1700000000123456","PRIORITY":"3","MESSAGE":"listener failed"}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.timestamp, Some(1_700_000_000));
+        assert_eq!(entry.priority, Some(3));
+        assert_eq!(entry.message, "listener failed");
+        assert!(entry.is_error());
+    }
+
+    #[test]
+    fn journal_entry_with_byte_array_message() {
+        let entry = journal_entry_from_json(r#"{"MESSAGE":[104,105,255,300,-1,"x"]}"#).unwrap();
+        assert_eq!(entry.message, "hi\u{FFFD}");
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.priority, None);
+    }
+
+    #[test]
+    fn journal_entry_with_malformed_fields() {
+        let entry = journal_entry_from_json(
+            r#"{"__REALTIME_TIMESTAMP":"99999999999999999999999","PRIORITY":"300","MESSAGE":null}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.priority, None);
+        assert_eq!(entry.message, "");
+
+        let entry = journal_entry_from_json(r#"{"__REALTIME_TIMESTAMP":1700000000,"PRIORITY":6,"MESSAGE":"ok"}"#)
+            .unwrap();
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.priority, None);
+    }
+
+    #[test]
+    fn journal_lines_without_message_are_skipped() {
+        for line in ["", "not json", "{", "[]", "42", "\"MESSAGE\"", r#"{"PRIORITY":"6"}"#] {
+            assert!(journal_entry_from_json(line).is_none(), "{}", line);
+        }
+    }
+}
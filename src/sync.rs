@@ -0,0 +1,178 @@
+//! Сверка одобренных пользователей в SQLite с [access.users] и таблицами
+//! лимитов конфига telemt.
+//!
+//! Источник истины — БД: управляемые ботом записи `tg_<id>` приводятся к ней,
+//! остальные записи конфига (добавленные вручную) только показываются в отчёте.
+//! Каждый экземпляр telemt сверяется отдельно с пользователями, назначенными на него.
+
+use crate::db::{Db, RegistrationRequest};
+use crate::instance::{ConfigStore, Instances};
+use crate::telemt_cfg::UserPolicy;
+use std::collections::{BTreeMap, HashMap};
+
+/// Пользователь telemt в том виде, в каком он должен быть в конфиге по данным БД.
+#[derive(Debug, Clone)]
+pub struct ManagedUser {
+    pub name: String,
+    pub secret: String,
+    pub policy: UserPolicy,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Экземпляр telemt, конфиг которого сверялся
+    pub instance: String,
+    /// Записи `tg_<id>` в конфиге без одобренного пользователя в БД
+    pub orphans_in_config: Vec<String>,
+    /// Одобренные пользователи, которых нет в конфиге
+    pub missing_in_config: Vec<ManagedUser>,
+    /// Секрет в конфиге отличается от БД
+    pub secret_mismatches: Vec<ManagedUser>,
+    /// Секрет совпадает, но лимиты или срок в конфиге отличаются от БД
+    pub policy_mismatches: Vec<ManagedUser>,
+    /// Одобренные пользователи без секрета в БД — автоматически не исправить
+    pub approved_without_secret: Vec<i64>,
+    /// Записи конфига, не созданные ботом (не `tg_<id>`), — не трогаем
+    pub unmanaged_in_config: Vec<String>,
+}
+
+impl SyncReport {
+    /// Есть ли расхождения, которые можно исправить.
+    pub fn has_fixes(&self) -> bool {
+        !self.orphans_in_config.is_empty()
+            || !self.missing_in_config.is_empty()
+            || !self.secret_mismatches.is_empty()
+            || !self.policy_mismatches.is_empty()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        !self.has_fixes() && self.approved_without_secret.is_empty()
+    }
+}
+
+/// Имя пользователя telemt, которое бот создаёт для tg_user_id.
+pub fn is_managed_username(name: &str) -> bool {
+    name.strip_prefix("tg_")
+        .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+}
+
+pub fn compute_report(
+    approved: &[RegistrationRequest],
+    config_users: &BTreeMap<String, String>,
+    config_policies: &BTreeMap<String, UserPolicy>,
+) -> SyncReport {
+    let mut report = SyncReport::default();
+    let mut expected: BTreeMap<String, ManagedUser> = BTreeMap::new();
+
+    for user in approved {
+        let Some(secret) = user.secret.clone() else {
+            report.approved_without_secret.push(user.tg_user_id);
+            continue;
+        };
+        let name = user
+            .telemt_username
+            .clone()
+            .unwrap_or_else(|| format!("tg_{}", user.tg_user_id));
+        let policy = UserPolicy {
+            limits: user.limits,
+            expires_at: user.access_expires_at,
+        };
+        expected.insert(
+            name.clone(),
+            ManagedUser {
+                name,
+                secret,
+                policy,
+            },
+        );
+    }
+
+    for (name, user) in &expected {
+        let current_policy = config_policies.get(name).copied().unwrap_or_default();
+        match config_users.get(name) {
+            None => report.missing_in_config.push(user.clone()),
+            Some(current) if *current != user.secret => report.secret_mismatches.push(user.clone()),
+            Some(_) if current_policy != user.policy => report.policy_mismatches.push(user.clone()),
+            Some(_) => {}
+        }
+    }
+
+    for name in config_users.keys() {
+        if expected.contains_key(name) {
+            continue;
+        }
+        if is_managed_username(name) {
+            report.orphans_in_config.push(name.clone());
+        } else {
+            report.unmanaged_in_config.push(name.clone());
+        }
+    }
+
+    report
+}
+
+/// Сверяет БД с конфигом каждого экземпляра; отчёты идут в порядке экземпляров.
+pub async fn build_reports(db: &Db, instances: &Instances) -> Result<Vec<SyncReport>, anyhow::Error> {
+    let approved = db.list_approved_users().await?;
+    let assignments = db.list_user_instances().await?;
+    // Выбор экземпляров один раз на пользователя: select предупреждает об устаревших назначениях.
+    let selections: HashMap<i64, Vec<String>> = approved
+        .iter()
+        .map(|user| {
+            let assigned = assignments
+                .get(&user.tg_user_id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let selected = instances
+                .select(assigned)
+                .iter()
+                .map(|selected| selected.name.clone())
+                .collect();
+            (user.tg_user_id, selected)
+        })
+        .collect();
+    let mut reports = Vec::with_capacity(instances.all().len());
+    for instance in instances.all() {
+        let members: Vec<RegistrationRequest> = approved
+            .iter()
+            .filter(|user| {
+                selections
+                    .get(&user.tg_user_id)
+                    .is_some_and(|selected| selected.contains(&instance.name))
+            })
+            .cloned()
+            .collect();
+        let config_users = instance.telemt_cfg.read_users().await?;
+        let config_policies = instance.telemt_cfg.read_policies().await?;
+        let mut report = compute_report(&members, &config_users, &config_policies);
+        report.instance = instance.name.clone();
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// Записывает исправления в конфиг одной операцией. Возвращает `true`, если конфиг изменён.
+pub async fn apply_report(telemt_cfg: &ConfigStore, report: &SyncReport) -> Result<bool, anyhow::Error> {
+    if !report.has_fixes() {
+        return Ok(false);
+    }
+    let upserts: Vec<(String, String, UserPolicy)> = report
+        .missing_in_config
+        .iter()
+        .chain(report.secret_mismatches.iter())
+        .chain(report.policy_mismatches.iter())
+        .map(|user| (user.name.clone(), user.secret.clone(), user.policy))
+        .collect();
+    telemt_cfg
+        .apply_users_changes(&upserts, &report.orphans_in_config)
+        .await?;
+    tracing::info!(
+        instance = %report.instance,
+        orphans = report.orphans_in_config.len(),
+        missing = report.missing_in_config.len(),
+        mismatches = report.secret_mismatches.len(),
+        policies = report.policy_mismatches.len(),
+        "telemt config reconciled with database"
+    );
+    Ok(true)
+}
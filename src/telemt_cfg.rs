@@ -1,26 +1,77 @@
 //! Чтение и обновление конфига telemt (/etc/telemt.toml).
 
-use serde::Deserialize;
+#[path = "telemt_cfg/backup.rs"]
+mod backup;
+#[path = "telemt_cfg/validate.rs"]
+mod validate;
+#[path = "telemt_cfg/watch.rs"]
+mod watch;
+
+pub use backup::BackupInfo;
+pub use watch::ConfigWatcher;
+
+use crate::db::UserLimits;
+use crate::link::{ProxyEndpoint, SecretMode};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::ErrorKind;
 use std::path::Path;
+use backup::BackupStore;
 use std::sync::Mutex;
-use toml_edit::{DocumentMut, Item};
+use std::time::SystemTime;
+use toml_edit::{DocumentMut, Item, Table};
 
-/// Параметры для генерации ссылки (host, port, tls_domain).
-#[derive(Debug, Clone)]
+/// Параметры для генерации ссылки (адреса, режимы и tls_domain).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TelemtLinkParams {
-    pub host: String,
-    pub port: u16,
-    pub tls_domain: String,
+    /// Все объявленные адреса из server.listeners; первый — основной
+    pub endpoints: Vec<ProxyEndpoint>,
+    /// Включённые режимы секрета в порядке предпочтения (fake-TLS первым)
+    pub modes: Vec<SecretMode>,
+    /// Домен маскировки; задан, если включён fake-TLS
+    pub tls_domain: Option<String>,
+}
+
+/// Таблицы [access.*] с персональными политиками пользователей telemt.
+const TABLE_MAX_TCP_CONNS: &str = "user_max_tcp_conns";
+const TABLE_MAX_UNIQUE_IPS: &str = "user_max_unique_ips";
+const TABLE_DATA_QUOTA: &str = "user_data_quota";
+const TABLE_EXPIRATIONS: &str = "user_expirations";
+
+/// Лимиты и срок действия пользователя в конфиге telemt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserPolicy {
+    pub limits: UserLimits,
+    /// Момент окончания доступа (unix-время); в конфиг пишется как RFC 3339
+    pub expires_at: Option<i64>,
 }
 
 /// Минимальная структура для чтения нужных полей telemt.
 #[derive(Debug, Deserialize)]
 struct TelemtConfigRaw {
+    general: Option<GeneralSection>,
     server: Option<ServerSection>,
     censorship: Option<CensorshipSection>,
 }
 
+#[derive(Debug, Deserialize)]
+struct GeneralSection {
+    modes: Option<ModesSection>,
+}
+
+/// `[general.modes]`; без секции telemt работает только в fake-TLS.
+#[derive(Debug, Deserialize)]
+struct ModesSection {
+    #[serde(default)]
+    classic: bool,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default)]
+    tls: bool,
+}
+
 #[derive(Debug, Deserialize)]
 struct ServerSection {
     port: Option<u16>,
@@ -42,6 +93,15 @@ struct CensorshipSection {
 pub struct TelemtConfig {
     path: std::path::PathBuf,
     write_lock: Mutex<()>,
+    backups: Option<BackupStore>,
+    /// Копия до первой записи, ещё не применённой перезапуском telemt.
+    pending_backup: Mutex<Option<String>>,
+    /// Внешняя проверка конфига самим telemt; пусто — только встроенная
+    check_command: Vec<String>,
+    /// Разобранные параметры ссылок вместе с mtime файла, из которого они прочитаны.
+    link_params: Mutex<Option<(SystemTime, TelemtLinkParams)>>,
+    /// Хеш последнего текста, записанного ботом: отличает свои записи от ручных правок.
+    written_hash: Mutex<Option<u64>>,
 }
 
 impl TelemtConfig {
@@ -49,51 +109,133 @@ impl TelemtConfig {
         Self {
             path: path.as_ref().to_path_buf(),
             write_lock: Mutex::new(()),
+            backups: None,
+            pending_backup: Mutex::new(None),
+            check_command: Vec::new(),
+            link_params: Mutex::new(None),
+            written_hash: Mutex::new(None),
         }
     }
 
-    /// Читает параметры для генерации ссылки.
-    pub fn read_link_params(&self) -> Result<TelemtLinkParams, anyhow::Error> {
-        tracing::debug!("Reading link params from {}", self.path.display());
+    /// Дополнительно проверять конфиг командой telemt (`{config}` — путь к файлу).
+    pub fn with_check_command(mut self, command: Vec<String>) -> Self {
+        self.check_command = command;
+        self
+    }
+
+    /// Проверяет текущий файл конфига перед запуском или перезапуском telemt.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
         let content = std::fs::read_to_string(&self.path)
             .map_err(|e| anyhow::anyhow!("Не удалось прочитать {}: {}", self.path.display(), e))?;
+        validate::validate(&content)?;
+        validate::run_check_command(&self.check_command, &self.path)
+    }
 
-        let parsed: TelemtConfigRaw = toml::from_str(&content)
-            .map_err(|e| anyhow::anyhow!("Ошибка парсинга telemt конфига: {}", e))?;
+    /// Перед каждой записью сохранять копию текущего файла в `dir`, храня `keep` последних.
+    pub fn with_backups(mut self, dir: impl AsRef<Path>, keep: usize) -> Self {
+        let store = BackupStore::new(dir, keep);
+        self.backups = store.is_enabled().then_some(store);
+        self
+    }
 
-        let port = parsed.server.as_ref().and_then(|s| s.port).unwrap_or(443);
+    /// Резервные копии от новых к старым.
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>, anyhow::Error> {
+        match &self.backups {
+            Some(store) => store.list(),
+            None => Ok(Vec::new()),
+        }
+    }
 
-        let host = parsed
-            .server
+    /// Восстанавливает конфиг из копии; текущий файл перед этим тоже копируется.
+    pub fn rollback(&self, id: &str) -> Result<(), anyhow::Error> {
+        let store = self
+            .backups
             .as_ref()
-            .and_then(|s| s.listeners.as_ref())
-            .and_then(|list| {
-                list.iter()
-                    .find_map(|l| l.announce.clone().or(l.announce_ip.clone()))
-            })
-            .ok_or_else(|| anyhow::anyhow!("Не найден announce/announce_ip в server.listeners"))?;
+            .ok_or_else(|| anyhow::anyhow!("Резервные копии отключены (backups.keep = 0)"))?;
+        let content = store.read(id)?;
+        let _lock = self
+            .write_lock
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Mutex poisoned: {}", e))?;
+        self.write_atomic(&content)?;
+        tracing::info!(backup = id, path = %self.path.display(), "telemt config rolled back");
+        Ok(())
+    }
 
-        let tls_domain = parsed
-            .censorship
-            .as_ref()
-            .and_then(|c| c.tls_domain.clone())
-            .ok_or_else(|| anyhow::anyhow!("Не задан censorship.tls_domain"))?;
+    /// Изменения применены перезапуском telemt: откатывать больше некуда.
+    pub fn mark_applied(&self) {
+        if let Ok(mut pending) = self.pending_backup.lock() {
+            *pending = None;
+        }
+    }
 
-        let params = TelemtLinkParams {
-            host,
-            port,
-            tls_domain,
+    /// Откатывает все записи с последнего успешного перезапуска; возвращает id копии.
+    pub fn rollback_pending(&self) -> Result<Option<String>, anyhow::Error> {
+        let pending = self
+            .pending_backup
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Mutex poisoned: {}", e))?
+            .take();
+        let Some(id) = pending else {
+            return Ok(None);
         };
-        tracing::debug!(
-            host = %params.host,
-            port = params.port,
-            "Link params loaded from telemt config"
-        );
+        self.rollback(&id)?;
+        // Копия сломанного состояния, сделанная при откате, не должна стать точкой отката.
+        self.mark_applied();
+        Ok(Some(id))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Читает параметры для генерации ссылки; пока файл не менялся, отдаёт их из кэша.
+    pub fn read_link_params(&self) -> Result<TelemtLinkParams, anyhow::Error> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if let Some(modified) = modified
+            && let Ok(cache) = self.link_params.lock()
+            && let Some((cached_at, params)) = cache.as_ref()
+            && *cached_at == modified
+        {
+            return Ok(params.clone());
+        }
+
+        tracing::debug!("Reading link params from {}", self.path.display());
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| anyhow::anyhow!("Не удалось прочитать {}: {}", self.path.display(), e))?;
+        let params = parse_link_params(&content)?;
+        if let Some(modified) = modified
+            && let Ok(mut cache) = self.link_params.lock()
+        {
+            *cache = Some((modified, params.clone()));
+        }
         Ok(params)
     }
 
-    /// Добавляет или обновляет пользователя в [access.users].
-    pub fn upsert_user(&self, username: &str, secret: &str) -> Result<(), anyhow::Error> {
+    /// Сбрасывает кэш параметров ссылок, например после правки файла вне бота.
+    pub fn invalidate_cache(&self) {
+        if let Ok(mut cache) = self.link_params.lock() {
+            *cache = None;
+        }
+    }
+
+    /// Записан ли `content` самим ботом последним.
+    pub fn is_own_write(&self, content: &str) -> bool {
+        self.written_hash
+            .lock()
+            .map(|hash| *hash == Some(content_hash(content)))
+            .unwrap_or(false)
+    }
+
+    /// Добавляет или обновляет пользователя в [access.users] вместе с его лимитами.
+    pub fn upsert_user(
+        &self,
+        username: &str,
+        secret: &str,
+        policy: &UserPolicy,
+    ) -> Result<(), anyhow::Error> {
         tracing::info!(username = username, "Upserting user in telemt config");
         let _lock = self
             .write_lock
@@ -107,17 +249,9 @@ impl TelemtConfig {
             .parse()
             .map_err(|e| anyhow::anyhow!("Ошибка парсинга TOML: {}", e))?;
 
-        let access = doc
-            .get_mut("access")
-            .and_then(|a| a.as_table_mut())
-            .ok_or_else(|| anyhow::anyhow!("Секция [access] не найдена"))?;
-
-        let users = access
-            .get_mut("users")
-            .and_then(|u| u.as_table_mut())
-            .ok_or_else(|| anyhow::anyhow!("Секция [access.users] не найдена"))?;
-
+        let users = access_users_mut(&mut doc)?;
         users[username] = Item::Value(toml_edit::Value::from(secret));
+        write_policy(access_mut(&mut doc)?, username, policy);
 
         let new_content = doc.to_string();
         self.write_atomic(&new_content)?;
@@ -125,7 +259,7 @@ impl TelemtConfig {
         Ok(())
     }
 
-    /// Удаляет пользователя из [access.users].
+    /// Удаляет пользователя из [access.users] и таблиц лимитов.
     pub fn remove_user(&self, username: &str) -> Result<bool, anyhow::Error> {
         tracing::info!(username = username, "Removing user from telemt config");
         let _lock = self
@@ -140,18 +274,11 @@ impl TelemtConfig {
             .parse()
             .map_err(|e| anyhow::anyhow!("Ошибка парсинга TOML: {}", e))?;
 
-        let access = doc
-            .get_mut("access")
-            .and_then(|a| a.as_table_mut())
-            .ok_or_else(|| anyhow::anyhow!("Секция [access] не найдена"))?;
-
-        let users = access
-            .get_mut("users")
-            .and_then(|u| u.as_table_mut())
-            .ok_or_else(|| anyhow::anyhow!("Секция [access.users] не найдена"))?;
+        let users = access_users_mut(&mut doc)?;
 
         let existed = users.contains_key(username);
         users.remove(username);
+        remove_policy(access_mut(&mut doc)?, username);
 
         if existed {
             let new_content = doc.to_string();
@@ -163,10 +290,118 @@ impl TelemtConfig {
         Ok(existed)
     }
 
+    /// Читает [access.users] как `имя -> секрет` (нестроковые значения пропускаются).
+    pub fn read_users(&self) -> Result<BTreeMap<String, String>, anyhow::Error> {
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| anyhow::anyhow!("Не удалось прочитать {}: {}", self.path.display(), e))?;
+        parse_users(&content)
+    }
+
+    /// Обновляет только лимиты пользователя; `false`, если его нет в [access.users].
+    pub fn set_user_policy(&self, username: &str, policy: &UserPolicy) -> Result<bool, anyhow::Error> {
+        tracing::info!(username = username, "Updating user policy in telemt config");
+        let _lock = self
+            .write_lock
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Mutex poisoned: {}", e))?;
+
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| anyhow::anyhow!("Не удалось прочитать {}: {}", self.path.display(), e))?;
+        let mut doc: DocumentMut = content
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Ошибка парсинга TOML: {}", e))?;
+
+        if !access_users_mut(&mut doc)?.contains_key(username) {
+            return Ok(false);
+        }
+        let access = access_mut(&mut doc)?;
+        if read_policy(access, username) == *policy {
+            return Ok(false);
+        }
+        write_policy(access, username, policy);
+        self.write_atomic(&doc.to_string())?;
+        Ok(true)
+    }
+
+    /// Читает лимиты всех пользователей из таблиц [access.user_*].
+    pub fn read_policies(&self) -> Result<BTreeMap<String, UserPolicy>, anyhow::Error> {
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| anyhow::anyhow!("Не удалось прочитать {}: {}", self.path.display(), e))?;
+        let mut doc: DocumentMut = content
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Ошибка парсинга TOML: {}", e))?;
+        let access = access_mut(&mut doc)?;
+
+        let mut names: Vec<String> = Vec::new();
+        for table in [
+            TABLE_MAX_TCP_CONNS,
+            TABLE_MAX_UNIQUE_IPS,
+            TABLE_DATA_QUOTA,
+            TABLE_EXPIRATIONS,
+        ] {
+            if let Some(entries) = access.get(table).and_then(Item::as_table_like) {
+                names.extend(entries.iter().map(|(name, _)| name.to_string()));
+            }
+        }
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                let policy = read_policy(access, &name);
+                (name, policy)
+            })
+            .collect())
+    }
+
+    /// Применяет пачку изменений [access.users] и лимитов одной записью файла.
+    pub fn apply_users_changes(
+        &self,
+        upserts: &[(String, String, UserPolicy)],
+        removals: &[String],
+    ) -> Result<(), anyhow::Error> {
+        if upserts.is_empty() && removals.is_empty() {
+            return Ok(());
+        }
+        tracing::info!(
+            upserts = upserts.len(),
+            removals = removals.len(),
+            "Applying batch of user changes to telemt config"
+        );
+        let _lock = self
+            .write_lock
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Mutex poisoned: {}", e))?;
+
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| anyhow::anyhow!("Не удалось прочитать {}: {}", self.path.display(), e))?;
+
+        let mut doc: DocumentMut = content
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Ошибка парсинга TOML: {}", e))?;
+
+        let users = access_users_mut(&mut doc)?;
+        for username in removals {
+            users.remove(username);
+        }
+        for (username, secret, _) in upserts {
+            users[username.as_str()] = Item::Value(toml_edit::Value::from(secret.as_str()));
+        }
+        let access = access_mut(&mut doc)?;
+        for username in removals {
+            remove_policy(access, username);
+        }
+        for (username, _, policy) in upserts {
+            write_policy(access, username, policy);
+        }
+
+        let new_content = doc.to_string();
+        self.write_atomic(&new_content)
+    }
+
     fn write_atomic(&self, content: &str) -> Result<(), anyhow::Error> {
-        // Дополнительная валидация финального текста перед заменой файла.
-        let _: toml::Value = toml::from_str(content)
-            .map_err(|e| anyhow::anyhow!("Невалидный TOML перед записью: {}", e))?;
+        // Сломанный конфиг не записываем: telemt с ним не поднимется.
+        self.check_content(content)?;
+        self.backup_current()?;
+        self.remember_write(content);
 
         let parent = self.path.parent().unwrap_or(std::path::Path::new("."));
         let nonce = std::time::SystemTime::now()
@@ -205,4 +440,348 @@ impl TelemtConfig {
         );
         Ok(())
     }
+
+    /// Запоминает свою запись до замены файла, чтобы наблюдатель не принял её за ручную правку.
+    fn remember_write(&self, content: &str) {
+        if let Ok(mut hash) = self.written_hash.lock() {
+            *hash = Some(content_hash(content));
+        }
+        self.invalidate_cache();
+    }
+
+    /// Проверяет текст до записи; внешней проверке он передаётся временным файлом.
+    fn check_content(&self, content: &str) -> Result<(), anyhow::Error> {
+        validate::validate(content)?;
+        if self.check_command.is_empty() {
+            return Ok(());
+        }
+        let nonce = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|value| value.as_nanos())
+            .unwrap_or(0);
+        let tmp = std::env::temp_dir().join(format!(
+            "telemt-check.{}.{}.toml",
+            std::process::id(),
+            nonce
+        ));
+        std::fs::write(&tmp, content)
+            .map_err(|e| anyhow::anyhow!("Не удалось записать {} для проверки: {}", tmp.display(), e))?;
+        let result = validate::run_check_command(&self.check_command, &tmp);
+        if let Err(error) = std::fs::remove_file(&tmp) {
+            tracing::warn!(path = %tmp.display(), error = %error, "Не удалось удалить временный файл проверки");
+        }
+        result
+    }
+
+    /// Копирует текущий файл перед записью; без копии запись не выполняется.
+    fn backup_current(&self) -> Result<(), anyhow::Error> {
+        let Some(store) = &self.backups else {
+            return Ok(());
+        };
+        let current = match std::fs::read(&self.path) {
+            Ok(current) => current,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(error) => {
+                return Err(anyhow::anyhow!(
+                    "Не удалось прочитать {} для резервной копии: {}",
+                    self.path.display(),
+                    error
+                ));
+            }
+        };
+        let mut pending = self
+            .pending_backup
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Mutex poisoned: {}", e))?;
+        let id = store.save(&current, pending.as_deref())?;
+        pending.get_or_insert(id);
+        Ok(())
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Разбирает адреса, режимы и tls_domain из текста конфига.
+/// Разбирает [access.users] из текста конфига, см. [`TelemtConfig::read_users`].
+pub fn parse_users(content: &str) -> Result<BTreeMap<String, String>, anyhow::Error> {
+    let doc: DocumentMut = content
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Ошибка парсинга TOML: {}", e))?;
+
+    let users = doc
+        .get("access")
+        .and_then(|a| a.get("users"))
+        .and_then(|u| u.as_table_like())
+        .ok_or_else(|| anyhow::anyhow!("Секция [access.users] не найдена"))?;
+
+    let mut result = BTreeMap::new();
+    for (name, value) in users.iter() {
+        match value.as_str() {
+            Some(secret) => {
+                result.insert(name.to_string(), secret.to_string());
+            }
+            None => tracing::warn!(
+                username = name,
+                "Skipping non-string secret in [access.users]"
+            ),
+        }
+    }
+    Ok(result)
+}
+
+fn parse_link_params(content: &str) -> Result<TelemtLinkParams, anyhow::Error> {
+    let parsed: TelemtConfigRaw = toml::from_str(content)
+        .map_err(|e| anyhow::anyhow!("Ошибка парсинга telemt конфига: {}", e))?;
+
+    let port = parsed.server.as_ref().and_then(|s| s.port).unwrap_or(443);
+
+    let mut endpoints: Vec<ProxyEndpoint> = Vec::new();
+    let listeners = parsed
+        .server
+        .as_ref()
+        .and_then(|s| s.listeners.as_deref())
+        .unwrap_or_default();
+    for listener in listeners {
+        for host in [&listener.announce, &listener.announce_ip].into_iter().flatten() {
+            let host = host.trim();
+            if !host.is_empty() && !endpoints.iter().any(|e| e.host == host) {
+                endpoints.push(ProxyEndpoint::new(host, port));
+            }
+        }
+    }
+    if endpoints.is_empty() {
+        return Err(anyhow::anyhow!("Не найден announce/announce_ip в server.listeners"));
+    }
+
+    let modes = match parsed.general.as_ref().and_then(|g| g.modes.as_ref()) {
+        Some(modes) => [
+            (modes.tls, SecretMode::Tls),
+            (modes.secure, SecretMode::Secure),
+            (modes.classic, SecretMode::Classic),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, mode)| enabled.then_some(mode))
+        .collect(),
+        None => vec![SecretMode::Tls],
+    };
+    if modes.is_empty() {
+        return Err(anyhow::anyhow!("В general.modes не включён ни один режим"));
+    }
+
+    let tls_domain = parsed.censorship.as_ref().and_then(|c| c.tls_domain.clone());
+    if modes.contains(&SecretMode::Tls) && tls_domain.is_none() {
+        return Err(anyhow::anyhow!("Не задан censorship.tls_domain"));
+    }
+
+    let params = TelemtLinkParams {
+        endpoints,
+        modes,
+        tls_domain,
+    };
+    tracing::debug!(
+        endpoints = ?params.endpoints,
+        modes = ?params.modes,
+        "Link params loaded from telemt config"
+    );
+    Ok(params)
+}
+
+fn access_mut(doc: &mut DocumentMut) -> Result<&mut Table, anyhow::Error> {
+    doc.get_mut("access")
+        .and_then(|a| a.as_table_mut())
+        .ok_or_else(|| anyhow::anyhow!("Секция [access] не найдена"))
+}
+
+fn access_users_mut(doc: &mut DocumentMut) -> Result<&mut Table, anyhow::Error> {
+    access_mut(doc)?
+        .get_mut("users")
+        .and_then(|u| u.as_table_mut())
+        .ok_or_else(|| anyhow::anyhow!("Секция [access.users] не найдена"))
+}
+
+fn read_policy(access: &Table, username: &str) -> UserPolicy {
+    let entry = |table: &str| {
+        access
+            .get(table)
+            .and_then(Item::as_table_like)
+            .and_then(|entries| entries.get(username))
+    };
+    let integer = |table: &str| entry(table).and_then(Item::as_integer);
+    // Дата может быть строкой или нативным TOML datetime.
+    let expires_at = entry(TABLE_EXPIRATIONS).and_then(|item| {
+        let text = item
+            .as_str()
+            .map(str::to_string)
+            .or_else(|| item.as_datetime().map(ToString::to_string))?;
+        let parsed = parse_expiration(&text);
+        if parsed.is_none() {
+            tracing::warn!(
+                user = username,
+                value = %text,
+                "Не удалось разобрать дату в user_expirations, срок доступа не учитывается"
+            );
+        }
+        parsed
+    });
+    UserPolicy {
+        limits: UserLimits {
+            max_tcp_conns: integer(TABLE_MAX_TCP_CONNS),
+            max_unique_ips: integer(TABLE_MAX_UNIQUE_IPS),
+            data_quota_bytes: integer(TABLE_DATA_QUOTA),
+        },
+        expires_at,
+    }
+}
+
+/// Разбирает дату окончания доступа: RFC 3339 со смещением, а дата и время без
+/// смещения (TOML local datetime/date) считаются UTC.
+fn parse_expiration(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if let Ok(value) = DateTime::parse_from_rfc3339(text) {
+        return Some(value.timestamp());
+    }
+    for pattern in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(value) = NaiveDateTime::parse_from_str(text, pattern) {
+            return Some(value.and_utc().timestamp());
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|value| value.and_utc().timestamp())
+}
+
+fn write_policy(access: &mut Table, username: &str, policy: &UserPolicy) {
+    let expiration = policy
+        .expires_at
+        .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+        .map(|value| toml_edit::Value::from(value.to_rfc3339_opts(SecondsFormat::Secs, true)));
+    let limits = &policy.limits;
+    for (table, value) in [
+        (TABLE_MAX_TCP_CONNS, limits.max_tcp_conns.map(toml_edit::Value::from)),
+        (TABLE_MAX_UNIQUE_IPS, limits.max_unique_ips.map(toml_edit::Value::from)),
+        (TABLE_DATA_QUOTA, limits.data_quota_bytes.map(toml_edit::Value::from)),
+        (TABLE_EXPIRATIONS, expiration),
+    ] {
+        match value {
+            Some(value) => {
+                let entries = access
+                    .entry(table)
+                    .or_insert_with(|| Item::Table(Table::new()));
+                if let Some(entries) = entries.as_table_like_mut() {
+                    entries.insert(username, Item::Value(value));
+                }
+            }
+            None => remove_policy_entry(access, table, username),
+        }
+    }
+}
+
+fn remove_policy(access: &mut Table, username: &str) {
+    for table in [
+        TABLE_MAX_TCP_CONNS,
+        TABLE_MAX_UNIQUE_IPS,
+        TABLE_DATA_QUOTA,
+        TABLE_EXPIRATIONS,
+    ] {
+        remove_policy_entry(access, table, username);
+    }
+}
+
+/// Удаляет запись пользователя и саму таблицу, если она опустела.
+fn remove_policy_entry(access: &mut Table, table: &str, username: &str) {
+    let now_empty = match access.get_mut(table).and_then(Item::as_table_like_mut) {
+        Some(entries) => {
+            entries.remove(username);
+            entries.is_empty()
+        }
+        None => return,
+    };
+    if now_empty {
+        access.remove(table);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy_of(content: &str, username: &str) -> UserPolicy {
+        let doc: DocumentMut = content.parse().unwrap();
+        let access = doc["access"].as_table().unwrap();
+        read_policy(access, username)
+    }
+
+    #[test]
+    fn expirations_without_offset_are_utc() {
+        for value in [
+            "2030-01-01T03:00:00+03:00",
+            "\"2030-01-01T00:00:00Z\"",
+            "2030-01-01T00:00:00",
+            "2030-01-01 00:00:00",
+            "\"2030-01-01 00:00:00\"",
+            "2030-01-01",
+        ] {
+            let content = format!("[access.user_expirations]\nuser = {}\n", value);
+            assert_eq!(policy_of(&content, "user").expires_at, Some(1_893_456_000), "{}", value);
+        }
+    }
+
+    #[test]
+    fn unparsable_expiration_is_ignored() {
+        let policy = policy_of("[access.user_expirations]\nuser = \"завтра\"\n", "user");
+        assert_eq!(policy.expires_at, None);
+        assert_eq!(parse_expiration("2030-13-01"), None);
+    }
+
+    const LISTENERS: &str = r#"
+        [server]
+        port = 443
+        [[server.listeners]]
+        ip = "0.0.0.0"
+        announce = "proxy.example.com"
+    "#;
+
+    fn link_params(extra: &str) -> Result<TelemtLinkParams, anyhow::Error> {
+        parse_link_params(&format!("{}\n{}", extra, LISTENERS))
+    }
+
+    #[test]
+    fn no_modes_table_means_tls_only() {
+        let params = link_params("[censorship]\ntls_domain = \"www.google.com\"").unwrap();
+        assert_eq!(params.modes, vec![SecretMode::Tls]);
+        assert_eq!(params.tls_domain.as_deref(), Some("www.google.com"));
+        assert_eq!(params.endpoints, vec![ProxyEndpoint::new("proxy.example.com", 443)]);
+    }
+
+    #[test]
+    fn enabled_modes_are_listed_tls_first() {
+        let params = link_params(
+            "[general.modes]\nclassic = true\nsecure = true\ntls = true\n[censorship]\ntls_domain = \"a.example\"",
+        )
+        .unwrap();
+        assert_eq!(params.modes, vec![SecretMode::Tls, SecretMode::Secure, SecretMode::Classic]);
+    }
+
+    #[test]
+    fn modes_without_tls_do_not_need_domain() {
+        let params = link_params("[general.modes]\nsecure = true").unwrap();
+        assert_eq!(params.modes, vec![SecretMode::Secure]);
+        assert_eq!(params.tls_domain, None);
+    }
+
+    #[test]
+    fn tls_mode_requires_domain() {
+        assert!(link_params("").is_err());
+        assert!(link_params("[general.modes]\ntls = true").is_err());
+    }
+
+    #[test]
+    fn empty_modes_table_is_rejected() {
+        assert!(link_params("[general.modes]\nclassic = false").is_err());
+    }
 }
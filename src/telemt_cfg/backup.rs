@@ -0,0 +1,139 @@
+//! Резервные копии конфига telemt: файл `telemt-<id>.toml` на каждую запись,
+//! хранятся последние `keep` копий.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const BACKUP_PREFIX: &str = "telemt-";
+const BACKUP_SUFFIX: &str = ".toml";
+
+/// Сведения о резервной копии для списка в боте.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// Идентификатор копии: локальное время создания `YYYYMMDD-HHMMSS-mmm`
+    pub id: String,
+    /// Момент создания (unix-время, секунды)
+    pub created_at: i64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupStore {
+    dir: PathBuf,
+    keep: usize,
+}
+
+impl BackupStore {
+    pub fn new(dir: impl AsRef<Path>, keep: usize) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            keep,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.keep > 0
+    }
+
+    /// Сохраняет копию и удаляет лишние старые, кроме `protected`; возвращает id новой копии.
+    pub fn save(&self, content: &[u8], protected: Option<&str>) -> Result<String, anyhow::Error> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            anyhow::anyhow!("Не удалось создать каталог копий {}: {}", self.dir.display(), e)
+        })?;
+        let base = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+        let mut id = base.clone();
+        let mut attempt = 1;
+        while self.file_path(&id).exists() {
+            id = format!("{}-{}", base, attempt);
+            attempt += 1;
+        }
+        let path = self.file_path(&id);
+        std::fs::write(&path, content)
+            .map_err(|e| anyhow::anyhow!("Не удалось сохранить копию {}: {}", path.display(), e))?;
+        tracing::info!(backup = %path.display(), "telemt config backup saved");
+        self.prune(protected);
+        Ok(id)
+    }
+
+    /// Копии от новых к старым.
+    pub fn list(&self) -> Result<Vec<BackupInfo>, anyhow::Error> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => {
+                return Err(anyhow::anyhow!(
+                    "Не удалось прочитать каталог копий {}: {}",
+                    self.dir.display(),
+                    error
+                ));
+            }
+        };
+        let mut backups: Vec<BackupInfo> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let id = name
+                    .strip_prefix(BACKUP_PREFIX)?
+                    .strip_suffix(BACKUP_SUFFIX)?
+                    .to_string();
+                let metadata = entry.metadata().ok()?;
+                let created_at = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs() as i64)
+                    .unwrap_or_default();
+                Some(BackupInfo {
+                    id,
+                    created_at,
+                    size: metadata.len(),
+                })
+            })
+            .collect();
+        // id начинается с времени создания, поэтому сортировка по строке хронологическая.
+        backups.sort_by(|a, b| b.id.cmp(&a.id));
+        Ok(backups)
+    }
+
+    pub fn read(&self, id: &str) -> Result<String, anyhow::Error> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+            return Err(anyhow::anyhow!("Некорректный id копии: {}", id));
+        }
+        let path = self.file_path(id);
+        std::fs::read_to_string(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!("Копия {} не найдена", id),
+            _ => anyhow::anyhow!("Не удалось прочитать копию {}: {}", path.display(), e),
+        })
+    }
+
+    fn file_path(&self, id: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}{}{}", BACKUP_PREFIX, id, BACKUP_SUFFIX))
+    }
+
+    /// Удаляет копии сверх `keep`; копия `protected` нужна для автоотката и
+    /// не удаляется. Ошибки только логируются.
+    fn prune(&self, protected: Option<&str>) {
+        let backups = match self.list() {
+            Ok(backups) => backups,
+            Err(error) => {
+                tracing::warn!(error = %error, "Не удалось получить список копий для очистки");
+                return;
+            }
+        };
+        for backup in backups
+            .iter()
+            .skip(self.keep)
+            .filter(|backup| Some(backup.id.as_str()) != protected)
+        {
+            let path = self.file_path(&backup.id);
+            if let Err(error) = std::fs::remove_file(&path) {
+                tracing::warn!(
+                    backup = %path.display(),
+                    error = %error,
+                    "Не удалось удалить старую копию конфига"
+                );
+            }
+        }
+    }
+}
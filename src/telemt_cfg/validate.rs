@@ -0,0 +1,242 @@
+//! Проверка конфига telemt перед записью и перезапуском: обязательные секции,
+//! порты, секреты пользователей и `censorship.tls_domain`. Дополнительно можно
+//! вызвать проверку самого telemt (`telemt_check_command`).
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use toml::{Table, Value};
+
+/// Длина секрета пользователя в hex-символах (16 байт).
+const SECRET_HEX_LEN: usize = 32;
+
+/// Сколько ждать внешнюю проверку, прежде чем считать её неудавшейся.
+const CHECK_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Подстановка пути к проверяемому файлу в `telemt_check_command`.
+const CONFIG_PLACEHOLDER: &str = "{config}";
+
+/// Проверяет текст конфига; в ошибке перечислены все найденные проблемы.
+pub fn validate(content: &str) -> Result<(), anyhow::Error> {
+    let problems = find_problems(content);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "Конфиг telemt не прошёл проверку:\n{}",
+        problems
+            .iter()
+            .map(|problem| format!("• {}", problem))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+fn find_problems(content: &str) -> Vec<String> {
+    let doc: Table = match toml::from_str(content) {
+        Ok(doc) => doc,
+        // Повторяющиеся ключи, в том числе пользователи в [access.users], тоже ошибка разбора.
+        Err(error) => return vec![format!("невалидный TOML: {}", error)],
+    };
+    let mut problems = Vec::new();
+    check_server(&doc, &mut problems);
+    check_users(&doc, &mut problems);
+    check_modes(&doc, &mut problems);
+    problems
+}
+
+fn check_server(doc: &Table, problems: &mut Vec<String>) {
+    let Some(server) = doc.get("server").and_then(Value::as_table) else {
+        problems.push("нет секции [server]".to_string());
+        return;
+    };
+    if let Some(port) = server.get("port") {
+        check_port("server.port", port, problems);
+    }
+    let Some(listeners) = server.get("listeners") else {
+        problems.push("нет server.listeners: не из чего строить ссылки".to_string());
+        return;
+    };
+    let Some(listeners) = listeners.as_array() else {
+        problems.push("server.listeners должен быть массивом [[server.listeners]]".to_string());
+        return;
+    };
+    let mut announced = false;
+    for (index, listener) in listeners.iter().enumerate() {
+        let Some(listener) = listener.as_table() else {
+            problems.push(format!("server.listeners[{}] должен быть таблицей", index));
+            continue;
+        };
+        if let Some(port) = listener.get("port") {
+            check_port(&format!("server.listeners[{}].port", index), port, problems);
+        }
+        for key in ["announce", "announce_ip"] {
+            match listener.get(key) {
+                None => {}
+                Some(Value::String(host)) if !host.trim().is_empty() => announced = true,
+                Some(_) => problems.push(format!(
+                    "server.listeners[{}].{} должен быть непустой строкой",
+                    index, key
+                )),
+            }
+        }
+    }
+    if !announced {
+        problems.push("в server.listeners нет announce/announce_ip".to_string());
+    }
+}
+
+fn check_port(name: &str, value: &Value, problems: &mut Vec<String>) {
+    match value.as_integer() {
+        Some(port) if (1..=65535).contains(&port) => {}
+        Some(port) => problems.push(format!("{} = {} вне диапазона 1–65535", name, port)),
+        None => problems.push(format!("{} должен быть числом", name)),
+    }
+}
+
+fn check_users(doc: &Table, problems: &mut Vec<String>) {
+    let Some(users) = doc
+        .get("access")
+        .and_then(Value::as_table)
+        .and_then(|access| access.get("users"))
+        .and_then(Value::as_table)
+    else {
+        problems.push("нет секции [access.users]".to_string());
+        return;
+    };
+    let mut owners: HashMap<String, &str> = HashMap::new();
+    for (username, secret) in users {
+        let Some(secret) = secret.as_str() else {
+            problems.push(format!("секрет пользователя {} должен быть строкой", username));
+            continue;
+        };
+        if secret.len() != SECRET_HEX_LEN || !secret.chars().all(|c| c.is_ascii_hexdigit()) {
+            problems.push(format!(
+                "секрет пользователя {} должен состоять из {} hex-символов",
+                username, SECRET_HEX_LEN
+            ));
+            continue;
+        }
+        // Telemt различает пользователей по секрету, поэтому общий секрет — фактически дубль.
+        if let Some(owner) = owners.insert(secret.to_ascii_lowercase(), username) {
+            problems.push(format!(
+                "пользователи {} и {} используют один секрет",
+                owner, username
+            ));
+        }
+    }
+}
+
+fn check_modes(doc: &Table, problems: &mut Vec<String>) {
+    let modes = doc
+        .get("general")
+        .and_then(Value::as_table)
+        .and_then(|general| general.get("modes"))
+        .and_then(Value::as_table);
+    let enabled = |mode: &str| {
+        modes
+            .and_then(|modes| modes.get(mode))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    };
+    // Без [general.modes] telemt работает только в fake-TLS.
+    let tls = modes.is_none() || enabled("tls");
+    if modes.is_some() && !tls && !enabled("secure") && !enabled("classic") {
+        problems.push("в general.modes не включён ни один режим".to_string());
+    }
+    if !tls {
+        return;
+    }
+    let domain = doc
+        .get("censorship")
+        .and_then(Value::as_table)
+        .and_then(|censorship| censorship.get("tls_domain"));
+    match domain.map(Value::as_str) {
+        None => problems.push("для fake-TLS нужен censorship.tls_domain".to_string()),
+        Some(None) => problems.push("censorship.tls_domain должен быть строкой".to_string()),
+        Some(Some(domain)) if !is_valid_domain(domain) => problems.push(format!(
+            "censorship.tls_domain \"{}\" не похож на доменное имя",
+            domain
+        )),
+        Some(Some(_)) => {}
+    }
+}
+
+/// Доменное имя без схемы, порта и пути: метки из латиницы, цифр и `-`, хотя бы одна точка.
+fn is_valid_domain(domain: &str) -> bool {
+    domain.len() <= 253
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Запускает внешнюю проверку конфига `config_path`. `{config}` в аргументах
+/// заменяется путём к файлу; без подстановки путь передаётся последним аргументом.
+pub fn run_check_command(command: &[String], config_path: &Path) -> Result<(), anyhow::Error> {
+    let Some((program, args)) = command.split_first() else {
+        return Ok(());
+    };
+    let path = config_path.display().to_string();
+    let mut args: Vec<String> = args
+        .iter()
+        .map(|arg| arg.replace(CONFIG_PLACEHOLDER, &path))
+        .collect();
+    if !command.iter().any(|arg| arg.contains(CONFIG_PLACEHOLDER)) {
+        args.push(path);
+    }
+
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Не удалось запустить проверку конфига {}: {}", program, e))?;
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < CHECK_COMMAND_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(anyhow::anyhow!(
+                    "Проверка конфига {} не завершилась за {} с",
+                    program,
+                    CHECK_COMMAND_TIMEOUT.as_secs()
+                ));
+            }
+            Err(error) => {
+                return Err(anyhow::anyhow!("Не удалось дождаться проверки конфига: {}", error));
+            }
+        }
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| anyhow::anyhow!("Не удалось получить вывод проверки конфига: {}", e))?;
+    if output.status.success() {
+        tracing::debug!(program = %program, config = %config_path.display(), "telemt config check passed");
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let details = if stderr.trim().is_empty() {
+        stdout.trim()
+    } else {
+        stderr.trim()
+    };
+    Err(anyhow::anyhow!(
+        "Проверка конфига telemt ({}) не пройдена, {}:\n{}",
+        program,
+        output.status,
+        details
+    ))
+}
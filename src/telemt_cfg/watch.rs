@@ -0,0 +1,76 @@
+//! Наблюдение за файлами конфигов telemt через inotify (`notify`).
+//!
+//! Следим за каталогами, а не за самими файлами: редакторы и `write_atomic`
+//! заменяют файл переименованием, и наблюдение за старым inode бы терялось.
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Сколько ждать после события, чтобы серия записей одного сохранения пришла одним изменением.
+const SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+/// Активное наблюдение; события прекращаются, когда значение удалено.
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+    receiver: mpsc::UnboundedReceiver<PathBuf>,
+}
+
+impl ConfigWatcher {
+    /// Начинает следить за файлами `paths`.
+    pub fn new(paths: &[PathBuf]) -> Result<Self, anyhow::Error> {
+        // inotify сообщает пути от наблюдаемого каталога, поэтому сравниваем абсолютные,
+        // а наружу отдаём путь в том виде, в котором он задан в конфиге.
+        let targets: HashMap<PathBuf, PathBuf> = paths
+            .iter()
+            .map(|path| (std::path::absolute(path).unwrap_or_else(|_| path.clone()), path.clone()))
+            .collect();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let watched = targets.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(error) => {
+                    tracing::warn!(error = %error, "Ошибка наблюдения за конфигом telemt");
+                    return;
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            for path in event.paths {
+                if let Some(target) = watched.get(&path) {
+                    let _ = sender.send(target.clone());
+                }
+            }
+        })
+        .map_err(|e| anyhow::anyhow!("Не удалось запустить наблюдение за конфигом: {}", e))?;
+
+        let dirs: BTreeSet<&Path> = targets
+            .keys()
+            .map(|path| path.parent().unwrap_or(Path::new("/")))
+            .collect();
+        for dir in dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(|e| anyhow::anyhow!("Не удалось следить за {}: {}", dir.display(), e))?;
+        }
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    /// Ждёт изменений и возвращает изменённые файлы; `None`, если наблюдение остановлено.
+    pub async fn changed(&mut self) -> Option<BTreeSet<PathBuf>> {
+        let first = self.receiver.recv().await?;
+        tokio::time::sleep(SETTLE_DELAY).await;
+        let mut changed = BTreeSet::from([first]);
+        while let Ok(path) = self.receiver.try_recv() {
+            changed.insert(path);
+        }
+        Some(changed)
+    }
+}